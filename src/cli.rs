@@ -1,8 +1,10 @@
 use anyhow::Result;
-use clap::{Parser, Subcommand};
+use clap::{CommandFactory, Parser, Subcommand};
 use std::process;
 
+use crate::commands::hook::{Annotations, CollateArgs, CollateOptions, ReportSpec};
 use crate::commands::*;
+use crate::utils::git::ChangeScope;
 
 const VERSION: &str = env!("CARGO_PKG_VERSION");
 
@@ -47,11 +49,31 @@ enum Commands {
         #[command(subcommand)]
         command: DartCommands,
     },
-    /// Git hook utilities for Dart
+    /// Git and agent hook utilities for Dart
     Hook {
         #[command(subcommand)]
         command: HookCommands,
     },
+    /// GraphQL schema and operation utilities
+    Graphql {
+        #[command(subcommand)]
+        command: GraphqlCommands,
+    },
+    /// Model Context Protocol server exposing mobdev to coding assistants
+    Mcp {
+        #[command(subcommand)]
+        command: McpCommands,
+    },
+}
+
+#[derive(Subcommand)]
+enum McpCommands {
+    /// Serve mobdev commands as MCP tools over stdio (JSON-RPC, one message per line)
+    Serve {
+        /// Show human-readable status messages (output to stderr)
+        #[arg(short, long)]
+        verbose: bool,
+    },
 }
 
 #[derive(Subcommand)]
@@ -128,20 +150,35 @@ enum GitCommands {
         #[arg(short, long)]
         verbose: bool,
     },
-    /// Generate a commit message from staged changes using Claude
+    /// Generate a commit message for the staged changes with a generator command (claude -p by default)
     CommitMsg {
         /// Automatically create the commit with generated message
         #[arg(short, long)]
         commit: bool,
+        /// Command generating the message from the prompt on stdin (overrides llm.command)
+        #[arg(long, value_name = "COMMAND")]
+        generator: Option<String>,
+        /// List the secrets redacted from the diff before it is sent (output to stderr)
+        #[arg(long)]
+        show_redactions: bool,
         /// Show progress messages (output to stderr)
         #[arg(short, long)]
         verbose: bool,
     },
-    /// Generate a GitHub PR description from branch changes using Claude
+    /// Generate a GitHub PR description from the branch's changes, filling in the repository's PR template
     PrDescription {
         /// Base branch to compare against
         #[arg(short, long, default_value = "main")]
         base_branch: String,
+        /// Print a structured summary of the changes instead of generating a description
+        #[arg(long, conflicts_with = "generator")]
+        no_llm: bool,
+        /// Command generating the description from the prompt on stdin (overrides llm.command)
+        #[arg(long, value_name = "COMMAND")]
+        generator: Option<String>,
+        /// List the secrets redacted from the diff before it is sent (output to stderr)
+        #[arg(long)]
+        show_redactions: bool,
         /// Show progress messages (output to stderr)
         #[arg(short, long)]
         verbose: bool,
@@ -155,12 +192,41 @@ enum GitCommands {
 
 #[derive(Subcommand)]
 enum CodeownersCommands {
-    /// Check if CODEOWNERS files are in sync (suitable for CI checks)
+    /// Check if .github/CODEOWNERS is in sync with the OWNERS files and pubspec codeowners keys (suitable for CI checks)
     Check {
         /// Show human-readable status messages (output to stderr)
         #[arg(short, long)]
         verbose: bool,
     },
+    /// Generate .github/CODEOWNERS from the OWNERS files and pubspec codeowners keys
+    Generate {
+        /// Print the generated file instead of writing it
+        #[arg(long)]
+        stdout: bool,
+
+        /// Show human-readable status messages (output to stderr)
+        #[arg(short, long)]
+        verbose: bool,
+    },
+    /// Audit CODEOWNERS: unowned files, dead and shadowed rules, and owners missing from the roster
+    Audit {
+        /// Output format
+        #[arg(long, value_enum, default_value = "text")]
+        format: git::codeowners::AuditFormat,
+
+        /// Show human-readable status messages (output to stderr)
+        #[arg(short, long)]
+        verbose: bool,
+    },
+    /// Show the owners of files from CODEOWNERS (reads paths from stdin when none are given)
+    Who {
+        /// Paths relative to the git root
+        paths: Vec<String>,
+
+        /// Show human-readable status messages (output to stderr)
+        #[arg(short, long)]
+        verbose: bool,
+    },
 }
 
 #[derive(Subcommand)]
@@ -315,7 +381,12 @@ enum HookCommands {
     /// Check if GraphQL fakes are up to date (suitable for pre-push hooks)
     Graphql {
         #[command(subcommand)]
-        command: GraphqlCommands,
+        command: HookGraphqlCommands,
+    },
+    /// Scan added lines for credentials and other secrets (suitable for pre-commit hooks)
+    Secrets {
+        #[command(subcommand)]
+        command: HookSecretsCommands,
     },
     /// Run multiple hook checks and track failures (suitable for pre-push hooks)
     Collate {
@@ -331,21 +402,120 @@ enum HookCommands {
         /// Base branch to compare against
         #[arg(short, long, default_value = "main")]
         base_branch: String,
-        /// Run dart format check
+        #[command(flatten)]
+        checks: CollateArgs,
+        /// Check the staged content: set aside unstaged changes while the checks run
+        #[arg(long, requires = "staged")]
+        snapshot: bool,
+        /// Run dart format, dart fix and dcm fix first, re-staging the fixes of staged files
+        #[arg(long)]
+        fix: bool,
+        /// Stop at the first check that does not pass, cancelling the running checks
+        #[arg(long)]
+        fail_fast: bool,
+        /// Print the files, packages and commands each check would use, without running anything
         #[arg(long)]
-        dart_format: bool,
-        /// Run dart analysis check
+        explain: bool,
+        /// Write a report as <format>=<path> (formats: json, junit, sarif; repeatable)
+        #[arg(long, value_name = "FORMAT=PATH")]
+        report: Vec<ReportSpec>,
+        /// Annotate results for a CI system (defaults to github when GITHUB_ACTIONS=true)
+        #[arg(long, value_enum)]
+        annotations: Option<Annotations>,
+        /// Show human-readable status messages (output to stderr)
+        #[arg(short, long)]
+        verbose: bool,
+    },
+    /// Run the collated checks on the files changed by a push (reads git's pre-push input on stdin)
+    PrePush {
+        /// Remote being pushed to (passed by git)
+        remote: Option<String>,
+        /// URL of the remote (passed by git)
+        url: Option<String>,
+        /// Branch new branches are compared against when the remote has no default branch
+        #[arg(short, long, default_value = "main")]
+        base_branch: String,
+        #[command(flatten)]
+        checks: CollateArgs,
+        /// Stop at the first check that does not pass, cancelling the running checks
         #[arg(long)]
-        dart_analysis: bool,
-        /// Run DCM analyze check
+        fail_fast: bool,
+        /// Write a report as <format>=<path> (formats: json, junit, sarif; repeatable)
+        #[arg(long, value_name = "FORMAT=PATH")]
+        report: Vec<ReportSpec>,
+        /// Annotate results for a CI system (defaults to github when GITHUB_ACTIONS=true)
+        #[arg(long, value_enum)]
+        annotations: Option<Annotations>,
+        /// Show human-readable status messages (output to stderr)
+        #[arg(short, long)]
+        verbose: bool,
+    },
+    /// Check a commit message against the commit conventions (passed by git to commit-msg hooks)
+    CommitMsg {
+        /// File holding the commit message
+        file: std::path::PathBuf,
+        /// Show human-readable status messages (output to stderr)
+        #[arg(short, long)]
+        verbose: bool,
+    },
+    /// Install the configured git hooks (pre-commit, pre-push, commit-msg, post-checkout)
+    Install {
+        /// Remove the installed hooks and restore any hooks they replaced
         #[arg(long)]
-        dcm_analyze: bool,
-        /// Run GraphQL check
+        uninstall: bool,
+        /// Show human-readable status messages (output to stderr)
+        #[arg(short, long)]
+        verbose: bool,
+    },
+    /// Show which git hooks are installed and whether they match mobdev.yaml
+    Status {
+        /// Show human-readable status messages (output to stderr)
+        #[arg(short, long)]
+        verbose: bool,
+    },
+    /// Manage the cache of passing hook checks
+    Cache {
+        #[command(subcommand)]
+        command: HookCacheCommands,
+    },
+    /// Handle coding agent hook events (reads the event JSON on stdin)
+    Agent {
+        #[command(subcommand)]
+        command: HookAgentCommands,
+    },
+}
+
+#[derive(Subcommand)]
+enum HookAgentCommands {
+    /// Format or fix the Dart files written by a file-editing tool (PostToolUse)
+    PostEdit {
+        /// Also run dart fix on the edited files
         #[arg(long)]
-        graphql: bool,
-        /// Run git codeowners check
+        dart_fix: bool,
+        /// Also run dcm fix on the edited files
         #[arg(long)]
-        codeowners: bool,
+        dcm_fix: bool,
+        /// Show human-readable status messages (output to stderr)
+        #[arg(short, long)]
+        verbose: bool,
+    },
+    /// Run the collated checks and block the agent from stopping while they fail (Stop)
+    Stop {
+        /// Base branch to compare against
+        #[arg(short, long, default_value = "main")]
+        base_branch: String,
+        #[command(flatten)]
+        checks: CollateArgs,
+        /// Show human-readable status messages (output to stderr)
+        #[arg(short, long)]
+        verbose: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum HookCacheCommands {
+    /// Remove every recorded pass from the cache
+    Clear {
         /// Show human-readable status messages (output to stderr)
         #[arg(short, long)]
         verbose: bool,
@@ -367,6 +537,12 @@ enum FormatCommands {
         /// Base branch to compare against
         #[arg(short, long, default_value = "main")]
         base_branch: String,
+        /// Write a report as <format>=<path> (formats: json, junit, sarif; repeatable)
+        #[arg(long, value_name = "FORMAT=PATH")]
+        report: Vec<ReportSpec>,
+        /// Annotate results for a CI system (defaults to github when GITHUB_ACTIONS=true)
+        #[arg(long, value_enum)]
+        annotations: Option<Annotations>,
         /// Show human-readable status messages (output to stderr)
         #[arg(short, long)]
         verbose: bool,
@@ -388,6 +564,12 @@ enum AnalysisCommands {
         /// Base branch to compare against
         #[arg(short, long, default_value = "main")]
         base_branch: String,
+        /// Write a report as <format>=<path> (formats: json, junit, sarif; repeatable)
+        #[arg(long, value_name = "FORMAT=PATH")]
+        report: Vec<ReportSpec>,
+        /// Annotate results for a CI system (defaults to github when GITHUB_ACTIONS=true)
+        #[arg(long, value_enum)]
+        annotations: Option<Annotations>,
         /// Show human-readable status messages (output to stderr)
         #[arg(short, long)]
         verbose: bool,
@@ -409,6 +591,12 @@ enum FixCommands {
         /// Base branch to compare against
         #[arg(short, long, default_value = "main")]
         base_branch: String,
+        /// Write a report as <format>=<path> (formats: json, junit, sarif; repeatable)
+        #[arg(long, value_name = "FORMAT=PATH")]
+        report: Vec<ReportSpec>,
+        /// Annotate results for a CI system (defaults to github when GITHUB_ACTIONS=true)
+        #[arg(long, value_enum)]
+        annotations: Option<Annotations>,
         /// Show human-readable status messages (output to stderr)
         #[arg(short, long)]
         verbose: bool,
@@ -444,6 +632,12 @@ enum DcmFixCommands {
         /// Base branch to compare against
         #[arg(short, long, default_value = "main")]
         base_branch: String,
+        /// Write a report as <format>=<path> (formats: json, junit, sarif; repeatable)
+        #[arg(long, value_name = "FORMAT=PATH")]
+        report: Vec<ReportSpec>,
+        /// Annotate results for a CI system (defaults to github when GITHUB_ACTIONS=true)
+        #[arg(long, value_enum)]
+        annotations: Option<Annotations>,
         /// Show human-readable status messages (output to stderr)
         #[arg(short, long)]
         verbose: bool,
@@ -465,6 +659,12 @@ enum DcmAnalyzeCommands {
         /// Base branch to compare against
         #[arg(short, long, default_value = "main")]
         base_branch: String,
+        /// Write a report as <format>=<path> (formats: json, junit, sarif; repeatable)
+        #[arg(long, value_name = "FORMAT=PATH")]
+        report: Vec<ReportSpec>,
+        /// Annotate results for a CI system (defaults to github when GITHUB_ACTIONS=true)
+        #[arg(long, value_enum)]
+        annotations: Option<Annotations>,
         /// Show human-readable status messages (output to stderr)
         #[arg(short, long)]
         verbose: bool,
@@ -472,7 +672,7 @@ enum DcmAnalyzeCommands {
 }
 
 #[derive(Subcommand)]
-enum GraphqlCommands {
+enum HookGraphqlCommands {
     Check {
         /// Check staged changes only
         #[arg(short, long)]
@@ -486,6 +686,73 @@ enum GraphqlCommands {
         /// Base branch to compare against
         #[arg(short, long, default_value = "main")]
         base_branch: String,
+        /// Write a report as <format>=<path> (formats: json, junit, sarif; repeatable)
+        #[arg(long, value_name = "FORMAT=PATH")]
+        report: Vec<ReportSpec>,
+        /// Annotate results for a CI system (defaults to github when GITHUB_ACTIONS=true)
+        #[arg(long, value_enum)]
+        annotations: Option<Annotations>,
+        /// Show human-readable status messages (output to stderr)
+        #[arg(short, long)]
+        verbose: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum HookSecretsCommands {
+    /// Check the added lines for secrets, except allowlisted fingerprints
+    Check {
+        /// Check staged changes only
+        #[arg(short, long)]
+        staged: bool,
+        /// Check unstaged changes only
+        #[arg(short, long)]
+        unstaged: bool,
+        /// Check all changes (committed, staged, and unstaged)
+        #[arg(short, long)]
+        all: bool,
+        /// Base branch to compare against
+        #[arg(short, long, default_value = "main")]
+        base_branch: String,
+        /// Write a report as <format>=<path> (formats: json, junit, sarif; repeatable)
+        #[arg(long, value_name = "FORMAT=PATH")]
+        report: Vec<ReportSpec>,
+        /// Annotate results for a CI system (defaults to github when GITHUB_ACTIONS=true)
+        #[arg(long, value_enum)]
+        annotations: Option<Annotations>,
+        /// Show human-readable status messages (output to stderr)
+        #[arg(short, long)]
+        verbose: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum GraphqlCommands {
+    /// Validate GraphQL operation files against the schema without running codegen
+    Validate {
+        /// Operation files to validate (defaults to changed .graphql files)
+        files: Vec<String>,
+        /// Schema file to validate against (overrides graphql.schema in mobdev.yaml)
+        #[arg(long)]
+        schema: Option<String>,
+        /// Validate staged changes only
+        #[arg(short, long)]
+        staged: bool,
+        /// Validate unstaged changes only
+        #[arg(short, long)]
+        unstaged: bool,
+        /// Validate all changes (committed, staged, and unstaged)
+        #[arg(short, long)]
+        all: bool,
+        /// Base branch to compare against
+        #[arg(short, long, default_value = "main")]
+        base_branch: String,
+        /// Write a report as <format>=<path> (formats: json, junit, sarif; repeatable)
+        #[arg(long, value_name = "FORMAT=PATH")]
+        report: Vec<ReportSpec>,
+        /// Annotate results for a CI system (defaults to github when GITHUB_ACTIONS=true)
+        #[arg(long, value_enum)]
+        annotations: Option<Annotations>,
         /// Show human-readable status messages (output to stderr)
         #[arg(short, long)]
         verbose: bool,
@@ -521,13 +788,32 @@ pub fn run() -> Result<()> {
                 base_branch,
                 verbose,
             } => git::changed(staged, unstaged, all, push, &base_branch, verbose),
-            GitCommands::CommitMsg { commit, verbose } => git::commit_msg(commit, verbose),
+            GitCommands::CommitMsg {
+                commit,
+                generator,
+                show_redactions,
+                verbose,
+            } => git::commit_msg::run(commit, generator, show_redactions, verbose),
             GitCommands::PrDescription {
                 base_branch,
+                no_llm,
+                generator,
+                show_redactions,
                 verbose,
-            } => git::pr_description(&base_branch, verbose),
+            } => {
+                git::pr_description::run(&base_branch, no_llm, generator, show_redactions, verbose)
+            }
             GitCommands::Codeowners { command } => match command {
-                CodeownersCommands::Check { verbose } => git::codeowners::check(verbose),
+                CodeownersCommands::Check { verbose } => {
+                    hook::finish(&[git::codeowners::check(verbose)], &[], None, verbose)
+                }
+                CodeownersCommands::Generate { stdout, verbose } => {
+                    git::codeowners::generate(stdout, verbose)
+                }
+                CodeownersCommands::Audit { format, verbose } => {
+                    git::codeowners::audit(format, verbose)
+                }
+                CodeownersCommands::Who { paths, verbose } => git::codeowners::who(paths, verbose),
             },
         },
         Commands::Files { command } => match command {
@@ -580,8 +866,18 @@ pub fn run() -> Result<()> {
                     unstaged,
                     all,
                     base_branch,
+                    report,
+                    annotations,
+                    verbose,
+                } => hook::finish(
+                    &[hook::format::check(
+                        &ChangeScope::new(staged, unstaged, all, &base_branch),
+                        verbose,
+                    )],
+                    &report,
+                    annotations,
                     verbose,
-                } => hook::format::check(staged, unstaged, all, &base_branch, verbose),
+                ),
             },
             HookCommands::Analysis { command } => match command {
                 AnalysisCommands::Check {
@@ -589,8 +885,18 @@ pub fn run() -> Result<()> {
                     unstaged,
                     all,
                     base_branch,
+                    report,
+                    annotations,
+                    verbose,
+                } => hook::finish(
+                    &[hook::analysis::check(
+                        &ChangeScope::new(staged, unstaged, all, &base_branch),
+                        verbose,
+                    )],
+                    &report,
+                    annotations,
                     verbose,
-                } => hook::analysis::check(staged, unstaged, all, &base_branch, verbose),
+                ),
             },
             HookCommands::Fix { command } => match command {
                 FixCommands::Check {
@@ -598,8 +904,21 @@ pub fn run() -> Result<()> {
                     unstaged,
                     all,
                     base_branch,
+                    report,
+                    annotations,
                     verbose,
-                } => hook::fix::check(staged, unstaged, all, &base_branch, verbose),
+                } => hook::finish(
+                    &[hook::fix::check(
+                        staged,
+                        unstaged,
+                        all,
+                        &base_branch,
+                        verbose,
+                    )],
+                    &report,
+                    annotations,
+                    verbose,
+                ),
             },
             HookCommands::Dcm { command } => match command {
                 HookDcmCommands::Fix { command } => match command {
@@ -608,8 +927,21 @@ pub fn run() -> Result<()> {
                         unstaged,
                         all,
                         base_branch,
+                        report,
+                        annotations,
+                        verbose,
+                    } => hook::finish(
+                        &[hook::dcm::fix_check(
+                            staged,
+                            unstaged,
+                            all,
+                            &base_branch,
+                            verbose,
+                        )],
+                        &report,
+                        annotations,
                         verbose,
-                    } => hook::dcm::fix_check(staged, unstaged, all, &base_branch, verbose),
+                    ),
                 },
                 HookDcmCommands::Analyze { command } => match command {
                     DcmAnalyzeCommands::Check {
@@ -617,47 +949,183 @@ pub fn run() -> Result<()> {
                         unstaged,
                         all,
                         base_branch,
+                        report,
+                        annotations,
                         verbose,
-                    } => hook::dcm::analyze_check(staged, unstaged, all, &base_branch, verbose),
+                    } => hook::finish(
+                        &[hook::dcm::analyze_check(
+                            &ChangeScope::new(staged, unstaged, all, &base_branch),
+                            verbose,
+                        )],
+                        &report,
+                        annotations,
+                        verbose,
+                    ),
                 },
             },
             HookCommands::Graphql { command } => match command {
-                GraphqlCommands::Check {
+                HookGraphqlCommands::Check {
                     staged,
                     unstaged,
                     all,
                     base_branch,
+                    report,
+                    annotations,
+                    verbose,
+                } => hook::finish(
+                    &[hook::graphql::check(
+                        &ChangeScope::new(staged, unstaged, all, &base_branch),
+                        verbose,
+                    )],
+                    &report,
+                    annotations,
                     verbose,
-                } => hook::graphql::check(staged, unstaged, all, &base_branch, verbose),
+                ),
+            },
+            HookCommands::Secrets { command } => match command {
+                HookSecretsCommands::Check {
+                    staged,
+                    unstaged,
+                    all,
+                    base_branch,
+                    report,
+                    annotations,
+                    verbose,
+                } => hook::finish(
+                    &[hook::secrets::check(
+                        &ChangeScope::new(staged, unstaged, all, &base_branch),
+                        verbose,
+                    )],
+                    &report,
+                    annotations,
+                    verbose,
+                ),
             },
             HookCommands::Collate {
                 staged,
                 unstaged,
                 all,
                 base_branch,
-                dart_format,
-                dart_analysis,
-                dcm_analyze,
-                graphql,
-                codeowners,
+                checks,
+                snapshot,
+                fix,
+                fail_fast,
+                explain,
+                report,
+                annotations,
+                verbose,
+            } => {
+                let outcomes = hook::collate(&CollateOptions {
+                    scope: ChangeScope::new(staged, unstaged, all, &base_branch),
+                    args: checks,
+                    snapshot,
+                    fix,
+                    fail_fast,
+                    explain,
+                    verbose,
+                    pushed: None,
+                });
+                if explain {
+                    Ok(())
+                } else {
+                    hook::finish(&outcomes, &report, annotations, verbose)
+                }
+            }
+            HookCommands::PrePush {
+                remote,
+                url: _,
+                base_branch,
+                checks,
+                fail_fast,
+                report,
+                annotations,
                 verbose,
-            } => hook::collate(
+            } => match hook::pre_push::pushed_changes(remote.as_deref(), &base_branch, verbose) {
+                Ok(pushed) if pushed.files.is_empty() => Ok(()),
+                Ok(pushed) => hook::finish(
+                    &hook::collate(&CollateOptions {
+                        scope: ChangeScope::new(false, false, false, &base_branch)
+                            .with_files(pushed.files),
+                        args: checks,
+                        fail_fast,
+                        verbose,
+                        pushed: Some(hook::skip::PushedCommits {
+                            ranges: pushed.ranges,
+                            branches: pushed.branches,
+                        }),
+                        ..CollateOptions::default()
+                    }),
+                    &report,
+                    annotations,
+                    verbose,
+                ),
+                Err(e) => Err(e),
+            },
+            HookCommands::CommitMsg { file, verbose } => hook::finish(
+                &[hook::commit_msg::check(&file, verbose)],
+                &[],
+                None,
+                verbose,
+            ),
+            HookCommands::Install { uninstall, verbose } => {
+                hook::install::install(uninstall, verbose)
+            }
+            HookCommands::Status { verbose } => hook::install::status(verbose),
+            HookCommands::Cache { command } => match command {
+                HookCacheCommands::Clear { verbose } => hook::cache::clear(verbose),
+            },
+            HookCommands::Agent { command } => match command {
+                HookAgentCommands::PostEdit {
+                    dart_fix,
+                    dcm_fix,
+                    verbose,
+                } => hook::agent::post_edit(dart_fix, dcm_fix, verbose),
+                HookAgentCommands::Stop {
+                    base_branch,
+                    checks,
+                    verbose,
+                } => hook::agent::stop(&base_branch, |scope| {
+                    hook::collate(&CollateOptions {
+                        scope,
+                        args: checks,
+                        verbose,
+                        ..CollateOptions::default()
+                    })
+                }),
+            },
+        },
+        Commands::Graphql { command } => match command {
+            GraphqlCommands::Validate {
+                files,
+                schema,
                 staged,
                 unstaged,
                 all,
-                &base_branch,
-                dart_format,
-                dart_analysis,
-                dcm_analyze,
-                graphql,
-                codeowners,
+                base_branch,
+                report,
+                annotations,
+                verbose,
+            } => hook::finish(
+                &[graphql::validate(
+                    files,
+                    schema,
+                    &ChangeScope::new(staged, unstaged, all, &base_branch),
+                    verbose,
+                )],
+                &report,
+                annotations,
                 verbose,
             ),
         },
+        Commands::Mcp { command } => match command {
+            McpCommands::Serve { verbose } => mcp::serve(Cli::command(), verbose),
+        },
     };
 
+    // Exit codes are decided here only; commands report failure as errors.
     match result {
         Ok(_) => Ok(()),
+        Err(e) if e.is::<SilentFailure>() => process::exit(1),
         Err(e) => {
             eprintln!("Error: {}", e);
             process::exit(1);
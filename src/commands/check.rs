@@ -1,3 +1,4 @@
+use crate::commands::SilentFailure;
 use crate::utils::shell::is_command_installed;
 use anyhow::Result;
 
@@ -34,7 +35,7 @@ pub fn externals(verbose: bool) -> Result<()> {
         }
         Ok(())
     } else {
-        std::process::exit(1);
+        Err(SilentFailure.into())
     }
 }
 
@@ -1,5 +1,6 @@
 pub mod dcm;
 
+use crate::commands::SilentFailure;
 use crate::utils::dart::{
     find_dart_package_root, find_file_package_root, is_dart_package, is_generated_dart_file,
 };
@@ -22,7 +23,7 @@ pub fn check(path: Option<String>, verbose: bool) -> Result<()> {
         if verbose {
             eprintln!("✗ Not a Dart package");
         }
-        std::process::exit(1);
+        Err(SilentFailure.into())
     }
 }
 
@@ -39,7 +40,7 @@ pub fn root(path: Option<String>, verbose: bool) -> Result<()> {
         if verbose {
             eprintln!("Error: Not in a Dart package");
         }
-        std::process::exit(1);
+        Err(SilentFailure.into())
     }
 }
 
@@ -54,7 +55,7 @@ pub fn package(file: &str, verbose: bool) -> Result<()> {
         if verbose {
             eprintln!("Error: Could not find Dart package for {}", file);
         }
-        std::process::exit(1);
+        Err(SilentFailure.into())
     }
 }
 
@@ -69,7 +70,7 @@ pub fn changed(
         if verbose {
             eprintln!("Error: Not in a git repository");
         }
-        std::process::exit(1);
+        return Err(SilentFailure.into());
     }
 
     let files = get_changed_files(None::<&str>, base_branch, staged, unstaged, all)?;
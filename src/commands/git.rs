@@ -1,5 +1,8 @@
 pub mod codeowners;
+pub mod commit_msg;
+pub mod pr_description;
 
+use crate::commands::SilentFailure;
 use crate::utils::git::{
     get_changed_files, get_current_branch, get_files_to_push, get_git_root, is_git_repo,
     is_main_branch,
@@ -21,7 +24,7 @@ pub fn check(path: Option<String>, verbose: bool) -> Result<()> {
         if verbose {
             eprintln!("✗ Not a git repository");
         }
-        std::process::exit(1);
+        Err(SilentFailure.into())
     }
 }
 
@@ -32,7 +35,7 @@ pub fn root(path: Option<String>, verbose: bool) -> Result<()> {
         if verbose {
             eprintln!("Error: Not in a git repository");
         }
-        std::process::exit(1);
+        return Err(SilentFailure.into());
     }
 
     let root = get_git_root(Some(target_path))?;
@@ -52,7 +55,7 @@ pub fn branch(path: Option<String>, verbose: bool) -> Result<()> {
         if verbose {
             eprintln!("Error: Not in a git repository");
         }
-        std::process::exit(1);
+        return Err(SilentFailure.into());
     }
 
     let branch = get_current_branch(Some(target_path))?;
@@ -72,7 +75,7 @@ pub fn is_main(path: Option<String>, main_branch: &str, verbose: bool) -> Result
         if verbose {
             eprintln!("Error: Not in a git repository");
         }
-        std::process::exit(1);
+        return Err(SilentFailure.into());
     }
 
     let is_main = is_main_branch(Some(target_path), main_branch)?;
@@ -87,7 +90,7 @@ pub fn is_main(path: Option<String>, main_branch: &str, verbose: bool) -> Result
             let current = get_current_branch(Some(target_path))?;
             eprintln!("✗ Current branch is {} (not {})", current, main_branch);
         }
-        std::process::exit(1);
+        Err(SilentFailure.into())
     }
 }
 
@@ -103,7 +106,7 @@ pub fn changed(
         if verbose {
             eprintln!("Error: Not in a git repository");
         }
-        std::process::exit(1);
+        return Err(SilentFailure.into());
     }
 
     let files = if push {
@@ -131,26 +134,3 @@ pub fn changed(
 
     Ok(())
 }
-
-pub fn commit_msg(commit: bool, verbose: bool) -> Result<()> {
-    if verbose {
-        eprintln!("Commit message generation requires Claude CLI");
-        eprintln!("This feature is not yet fully implemented");
-    }
-
-    if commit && verbose {
-        eprintln!("Auto-commit flag set");
-    }
-
-    anyhow::bail!("Claude integration not yet implemented");
-}
-
-pub fn pr_description(base_branch: &str, verbose: bool) -> Result<()> {
-    if verbose {
-        eprintln!("PR description generation requires Claude CLI");
-        eprintln!("Base branch: {}", base_branch);
-        eprintln!("This feature is not yet fully implemented");
-    }
-
-    anyhow::bail!("Claude integration not yet implemented");
-}
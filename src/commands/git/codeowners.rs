@@ -1,8 +1,445 @@
-use anyhow::Result;
+use crate::commands::hook::outcome::{Finding, Severity};
+use crate::commands::hook::CheckOutcome;
+use crate::commands::SilentFailure;
+use crate::utils::codeowners::{
+    generate as generate_codeowners, parse_roster, Audit, Codeowners, Generated,
+    CODEOWNERS_LOCATIONS, GENERATED_CODEOWNERS,
+};
+use crate::utils::config::load_config;
+use crate::utils::git::{get_git_root, get_tracked_files, get_worktree_files, is_git_repo};
+use anyhow::{Context, Result};
+use std::io::{BufRead, IsTerminal};
+use std::path::Path;
+
+/// Checks that the generated CODEOWNERS file matches the ownership
+/// declarations. Passes when nothing declares owners.
+pub fn check(verbose: bool) -> CheckOutcome {
+    CheckOutcome::run("codeowners", || check_sync(verbose))
+}
+
+fn check_sync(verbose: bool) -> Result<Vec<Finding>> {
+    let (root, generated) = load_declarations()?;
+    if generated.sources.is_empty() && generated.errors.is_empty() {
+        if verbose {
+            eprintln!("No OWNERS files or pubspec codeowners keys: nothing to check");
+        }
+        return Ok(Vec::new());
+    }
 
-pub fn check(verbose: bool) -> Result<()> {
     if verbose {
-        eprintln!("CODEOWNERS check not yet implemented");
+        eprintln!(
+            "Comparing {} with {} declaration file(s)",
+            GENERATED_CODEOWNERS,
+            generated.sources.len()
+        );
+    }
+
+    let mut findings: Vec<Finding> = generated
+        .errors
+        .iter()
+        .map(|e| {
+            Finding::new("invalid-owners", Severity::Error, e.message.clone()).at(
+                e.file.clone(),
+                Some(e.line),
+                None,
+            )
+        })
+        .collect();
+    // An invalid declaration would be left out of the generated file, so the
+    // comparison would be misleading.
+    if !findings.is_empty() {
+        return Ok(findings);
+    }
+
+    let fix = "run `mobdev git codeowners generate`";
+    match std::fs::read_to_string(root.join(GENERATED_CODEOWNERS)) {
+        Err(_) => findings.push(
+            Finding::new(
+                "codeowners-out-of-date",
+                Severity::Error,
+                format!("Missing; {}", fix),
+            )
+            .at(GENERATED_CODEOWNERS, None, None),
+        ),
+        Ok(current) if current != generated.text => {
+            let line = first_difference(&current, &generated.text);
+            findings.push(
+                Finding::new(
+                    "codeowners-out-of-date",
+                    Severity::Error,
+                    format!(
+                        "Not in sync with the OWNERS files and pubspec codeowners keys; {}",
+                        fix
+                    ),
+                )
+                .at(GENERATED_CODEOWNERS, Some(line), None),
+            );
+        }
+        Ok(_) => {
+            if verbose {
+                eprintln!("✓ {} is up to date", GENERATED_CODEOWNERS);
+            }
+        }
+    }
+
+    Ok(findings)
+}
+
+/// Writes the CODEOWNERS file generated from the `OWNERS` files and the
+/// `codeowners` key of pubspecs, or prints it with `stdout`.
+pub fn generate(stdout: bool, verbose: bool) -> Result<()> {
+    let (root, generated) = load_declarations()?;
+
+    if !generated.errors.is_empty() {
+        for error in &generated.errors {
+            eprintln!("{}", error);
+        }
+        anyhow::bail!(
+            "{} invalid ownership declaration(s)",
+            generated.errors.len()
+        );
+    }
+    if generated.sources.is_empty() {
+        anyhow::bail!("No OWNERS files or pubspec codeowners keys to generate CODEOWNERS from");
+    }
+
+    if verbose {
+        for source in &generated.sources {
+            eprintln!("From {}", source);
+        }
+    }
+
+    if stdout {
+        print!("{}", generated.text);
+        return Ok(());
+    }
+
+    let path = root.join(GENERATED_CODEOWNERS);
+    if std::fs::read_to_string(&path).ok().as_deref() == Some(generated.text.as_str()) {
+        if verbose {
+            eprintln!("✓ {} is up to date", GENERATED_CODEOWNERS);
+        }
+        return Ok(());
+    }
+
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create {}", parent.display()))?;
     }
+    std::fs::write(&path, &generated.text)
+        .with_context(|| format!("Failed to write {}", path.display()))?;
+    if verbose {
+        eprintln!("Wrote {}", GENERATED_CODEOWNERS);
+    }
+
     Ok(())
 }
+
+/// Generates CODEOWNERS from the declarations in the working tree.
+fn load_declarations() -> Result<(std::path::PathBuf, Generated)> {
+    if !is_git_repo(None::<&str>) {
+        anyhow::bail!("Not in a git repository");
+    }
+
+    let root = get_git_root(None::<&str>)?;
+    let files = get_worktree_files(&root)?;
+    let generated = generate_codeowners(&root, &files)?;
+    Ok((root, generated))
+}
+
+/// The first line, from 1, where two texts differ.
+fn first_difference(a: &str, b: &str) -> usize {
+    let mut a_lines = a.lines();
+    let mut b_lines = b.lines();
+    let mut line = 1;
+    while let (Some(x), Some(y)) = (a_lines.next(), b_lines.next()) {
+        if x != y {
+            return line;
+        }
+        line += 1;
+    }
+    line
+}
+
+/// Prints the owners of each path, as `<path>\t<owners>` with the owners
+/// separated by spaces and none for unowned paths. Paths are relative to the
+/// git root and read from stdin, one per line, when none are given, so that
+/// `git changed` can be piped in.
+pub fn who(paths: Vec<String>, verbose: bool) -> Result<()> {
+    if !is_git_repo(None::<&str>) {
+        anyhow::bail!("Not in a git repository");
+    }
+
+    let root = get_git_root(None::<&str>)?;
+    let codeowners = load(&root, verbose)?;
+
+    let paths = if paths.is_empty() {
+        read_paths()?
+    } else {
+        paths
+    };
+
+    let mut unowned = 0;
+    for path in &paths {
+        let path = relative_path(&root, path);
+        let rule = codeowners.rule_for(&path);
+        let owners = codeowners.owners_of(&path).join(" ");
+        if owners.is_empty() {
+            unowned += 1;
+        }
+
+        if verbose {
+            match rule {
+                Some(rule) => eprintln!("{}: line {} ({})", path, rule.line, rule.pattern),
+                None => eprintln!("{}: no matching rule", path),
+            }
+        }
+        println!("{}\t{}", path, owners);
+    }
+
+    if verbose {
+        eprintln!("{} path(s), {} unowned", paths.len(), unowned);
+    }
+
+    Ok(())
+}
+
+/// Output format of `audit`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum AuditFormat {
+    Text,
+    Json,
+}
+
+/// Audits CODEOWNERS against the tracked files: unowned files, rules
+/// matching no file, rules shadowed by later ones, and owners missing from
+/// the roster configured as `codeowners.roster`. Fails when it finds any.
+pub fn audit(format: AuditFormat, verbose: bool) -> Result<()> {
+    if !is_git_repo(None::<&str>) {
+        anyhow::bail!("Not in a git repository");
+    }
+
+    let root = get_git_root(None::<&str>)?;
+    let codeowners = Codeowners::find(&root)?.with_context(|| {
+        format!(
+            "No CODEOWNERS file (looked for {})",
+            CODEOWNERS_LOCATIONS.join(", ")
+        )
+    })?;
+    let name = codeowners.path.clone().unwrap_or_default();
+
+    let roster = match load_config(&root)?.codeowners.roster {
+        Some(roster) => {
+            let path = root.join(&roster);
+            let text = std::fs::read_to_string(&path)
+                .with_context(|| format!("Failed to read roster {}", path.display()))?;
+            Some(parse_roster(&text))
+        }
+        None => None,
+    };
+
+    let files = get_tracked_files(&root)?;
+    if verbose {
+        eprintln!(
+            "Auditing {} ({} rules) against {} tracked file(s)",
+            name,
+            codeowners.rules.len(),
+            files.len()
+        );
+        if roster.is_none() {
+            eprintln!("No roster configured (codeowners.roster): owners not checked");
+        }
+    }
+
+    let audit = codeowners.audit(&files, roster.as_ref());
+    match format {
+        AuditFormat::Text => print!("{}", render_audit(&name, &audit)),
+        AuditFormat::Json => {
+            let mut json = serde_json::to_value(&audit).expect("audit serializes");
+            json["codeowners"] = name.into();
+            println!(
+                "{}",
+                serde_json::to_string_pretty(&json).expect("audit serializes")
+            );
+        }
+    }
+
+    if audit.is_clean() {
+        if verbose {
+            eprintln!("✓ No problems found");
+        }
+        Ok(())
+    } else {
+        Err(SilentFailure.into())
+    }
+}
+
+/// Renders an audit as text, one section per kind of problem found.
+fn render_audit(name: &str, audit: &Audit) -> String {
+    let mut text = String::new();
+    let mut section = |title: &str, lines: Vec<String>| {
+        if !lines.is_empty() {
+            if !text.is_empty() {
+                text.push('\n');
+            }
+            text.push_str(&format!("{} ({}):\n", title, lines.len()));
+            for line in lines {
+                text.push_str(&format!("  {}\n", line));
+            }
+        }
+    };
+    let rule = |line: usize, pattern: &str, owners: &[String]| {
+        let mut text = format!("{}:{} {}", name, line, pattern);
+        for owner in owners {
+            text.push(' ');
+            text.push_str(owner);
+        }
+        text
+    };
+    let lines = |lines: &[usize]| {
+        let lines: Vec<String> = lines.iter().map(ToString::to_string).collect();
+        format!(
+            "line{} {}",
+            if lines.len() == 1 { "" } else { "s" },
+            lines.join(", ")
+        )
+    };
+
+    section(
+        "Invalid lines",
+        audit
+            .errors
+            .iter()
+            .map(|e| format!("{}:{} {}", name, e.line, e.message))
+            .collect(),
+    );
+    section("Unowned files", audit.unowned.clone());
+    section(
+        "Dead rules",
+        audit
+            .dead_rules
+            .iter()
+            .map(|r| rule(r.line, &r.pattern, &r.owners))
+            .collect(),
+    );
+    section(
+        "Shadowed rules",
+        audit
+            .shadowed_rules
+            .iter()
+            .map(|r| {
+                format!(
+                    "{} (shadowed by {})",
+                    rule(r.rule.line, &r.rule.pattern, &r.rule.owners),
+                    lines(&r.shadowed_by)
+                )
+            })
+            .collect(),
+    );
+    section(
+        "Owners not in the roster",
+        audit
+            .unknown_owners
+            .iter()
+            .flatten()
+            .map(|o| format!("{} ({})", o.owner, lines(&o.lines)))
+            .collect(),
+    );
+
+    text
+}
+
+/// Reads the repository's CODEOWNERS file, reporting its invalid lines on
+/// stderr since GitHub ignores them.
+fn load(root: &Path, verbose: bool) -> Result<Codeowners> {
+    let codeowners = Codeowners::find(root)?.with_context(|| {
+        format!(
+            "No CODEOWNERS file (looked for {})",
+            CODEOWNERS_LOCATIONS.join(", ")
+        )
+    })?;
+
+    let name = codeowners.path.as_deref().unwrap_or("CODEOWNERS");
+    if verbose {
+        eprintln!("Using {} ({} rules)", name, codeowners.rules.len());
+    }
+    for error in &codeowners.errors {
+        eprintln!("{}:{}: {} (line ignored)", name, error.line, error.message);
+    }
+
+    Ok(codeowners)
+}
+
+/// Reads paths from stdin, one per line, skipping blank lines.
+fn read_paths() -> Result<Vec<String>> {
+    let stdin = std::io::stdin();
+    if stdin.is_terminal() {
+        anyhow::bail!("Pass paths as arguments or pipe them on stdin, e.g. `mobdev git changed | mobdev git codeowners who`");
+    }
+
+    let mut paths = Vec::new();
+    for line in stdin.lock().lines() {
+        let line = line.context("Failed to read paths from stdin")?;
+        let line = line.trim();
+        if !line.is_empty() {
+            paths.push(line.to_string());
+        }
+    }
+    Ok(paths)
+}
+
+/// Makes a path relative to the git root: absolute paths inside it are
+/// stripped of it, and a leading `./` is dropped.
+fn relative_path(root: &Path, path: &str) -> String {
+    let stripped = Path::new(path)
+        .strip_prefix(root)
+        .map(|p| p.to_string_lossy().into_owned())
+        .unwrap_or_else(|_| path.to_string());
+    stripped.trim_start_matches("./").replace('\\', "/")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_audit() {
+        let codeowners = Codeowners::parse("* @a\n*.md @b\n/old/ @c\n*.md @d\n");
+        let files = vec!["README.md".to_string()];
+        let roster = parse_roster("@a\n@d\n");
+
+        assert_eq!(
+            render_audit("CODEOWNERS", &codeowners.audit(&files, Some(&roster))),
+            "\
+Dead rules (1):
+  CODEOWNERS:3 /old/ @c
+
+Shadowed rules (2):
+  CODEOWNERS:1 * @a (shadowed by line 4)
+  CODEOWNERS:2 *.md @b (shadowed by line 4)
+
+Owners not in the roster (2):
+  @b (line 2)
+  @c (line 3)
+"
+        );
+    }
+
+    #[test]
+    fn test_first_difference() {
+        assert_eq!(first_difference("a\nb\nc\n", "a\nx\nc\n"), 2);
+        assert_eq!(first_difference("a\n", "a\nb\n"), 2);
+        assert_eq!(first_difference("", "a\n"), 1);
+    }
+
+    #[test]
+    fn test_relative_path() {
+        let root = Path::new("/repo");
+        assert_eq!(
+            relative_path(root, "/repo/app/lib/a.dart"),
+            "app/lib/a.dart"
+        );
+        assert_eq!(relative_path(root, "./README.md"), "README.md");
+        assert_eq!(relative_path(root, "app/lib/a.dart"), "app/lib/a.dart");
+    }
+}
@@ -0,0 +1,182 @@
+use crate::utils::config::{load_config, CommitMsgConfig};
+use crate::utils::git::{commit, get_current_branch, get_diff, get_git_root, is_git_repo};
+use crate::utils::llm::{
+    generate, load_template, prepare_diff, render_template, DEFAULT_GENERATOR,
+};
+use anyhow::Result;
+use regex::Regex;
+
+/// Prompt used unless `commit-msg.template` names another one.
+const DEFAULT_TEMPLATE: &str = "\
+Write a git commit message for the staged changes below.
+
+Follow the Conventional Commits format:
+- Subject line: `<type>(<optional scope>): <summary>`, where type is one of: {{types}}
+- Keep the subject line within {{max_subject_length}} characters, in the imperative mood, without a trailing period
+- If the change needs explaining, add a blank line and a body wrapped at 72 characters
+
+Reply with the commit message only, without code fences or commentary.
+
+Branch: {{branch}}
+
+Changed files:
+{{stat}}
+
+Diff:
+{{diff}}
+";
+
+/// Generates a commit message for the staged changes and prints it, or
+/// commits with it. The message must follow the commit conventions. Secrets
+/// in the diff are redacted before it is passed to the generator.
+pub fn run(
+    commit_changes: bool,
+    generator: Option<String>,
+    show_redactions: bool,
+    verbose: bool,
+) -> Result<()> {
+    if !is_git_repo(None::<&str>) {
+        anyhow::bail!("Not in a git repository");
+    }
+
+    let root = get_git_root(None::<&str>)?;
+    let config = load_config(&root)?;
+    let settings = &config.commit_msg;
+
+    let stat = get_diff(&root, &["--cached", "--stat"])?;
+    if stat.trim().is_empty() {
+        anyhow::bail!("Nothing staged to describe");
+    }
+    let diff = prepare_diff(
+        &get_diff(&root, &["--cached"])?,
+        &config.llm,
+        show_redactions,
+        verbose,
+    );
+    let branch = get_current_branch(Some(&root)).unwrap_or_default();
+
+    let template = load_template(&root, settings.template.as_deref(), DEFAULT_TEMPLATE)?;
+    let prompt = render_template(
+        &template,
+        &[
+            ("types", &settings.types.join(", ")),
+            (
+                "max_subject_length",
+                &settings.max_subject_length.to_string(),
+            ),
+            ("branch", &branch),
+            ("stat", stat.trim_end()),
+            ("diff", diff.trim_end()),
+        ],
+    );
+
+    let generator = generator
+        .or(config.llm.command.clone())
+        .unwrap_or_else(|| DEFAULT_GENERATOR.to_string());
+    if verbose {
+        eprintln!(
+            "Generating a commit message with \"{}\" ({} byte prompt)",
+            generator,
+            prompt.len()
+        );
+    }
+
+    let message = generate(&generator, &prompt, &root)?;
+
+    let problems = validate(&message, settings);
+    if !problems.is_empty() {
+        eprintln!("{}", message);
+        anyhow::bail!(
+            "Generated message does not follow the commit conventions:\n  - {}",
+            problems.join("\n  - ")
+        );
+    }
+
+    if commit_changes {
+        commit(&root, &message)?;
+        if verbose {
+            eprintln!("✓ Committed");
+        }
+    }
+
+    println!("{}", message);
+    Ok(())
+}
+
+/// Checks a message against the commit conventions and returns the
+/// problems: a `<type>(<scope>): <summary>` subject with an allowed type, no
+/// trailing period and a limited length, separated from any body by a blank
+/// line.
+pub fn validate(message: &str, config: &CommitMsgConfig) -> Vec<String> {
+    let subject_pattern =
+        Regex::new(r"^([A-Za-z]+)(\([^()\s]+\))?!?: (\S.*)$").expect("valid regex");
+    let mut problems = Vec::new();
+    let mut lines = message.lines();
+    let subject = lines.next().unwrap_or("").trim_end();
+
+    match subject_pattern.captures(subject) {
+        Some(captures) => {
+            let kind = &captures[1];
+            if !config.types.iter().any(|t| t == kind) {
+                problems.push(format!(
+                    "Type \"{}\" is not one of: {}",
+                    kind,
+                    config.types.join(", ")
+                ));
+            }
+            if captures[3].ends_with('.') {
+                problems.push("Subject ends with a period".to_string());
+            }
+        }
+        None => problems.push(format!(
+            "Subject \"{}\" is not shaped like \"<type>(<scope>): <summary>\"",
+            subject
+        )),
+    }
+
+    let length = subject.chars().count();
+    if length > config.max_subject_length {
+        problems.push(format!(
+            "Subject is {} characters long (at most {})",
+            length, config.max_subject_length
+        ));
+    }
+
+    if lines.next().is_some_and(|line| !line.trim().is_empty()) {
+        problems.push("Subject and body are not separated by a blank line".to_string());
+    }
+
+    problems
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate() {
+        let config = CommitMsgConfig::default();
+
+        assert!(validate("feat: add stash support", &config).is_empty());
+        assert!(validate("fix(git)!: handle detached HEAD\n\nBody.", &config).is_empty());
+
+        assert_eq!(
+            validate("Add stash support.", &config),
+            vec![
+                "Subject \"Add stash support.\" is not shaped like \"<type>(<scope>): <summary>\""
+            ]
+        );
+        assert_eq!(
+            validate("feature: add stash support.\nBody", &config),
+            vec![
+                "Type \"feature\" is not one of: feat, fix, docs, test, refactor, chore",
+                "Subject ends with a period",
+                "Subject and body are not separated by a blank line",
+            ]
+        );
+        assert_eq!(
+            validate(&format!("feat: {}", "a".repeat(70)), &config),
+            vec!["Subject is 76 characters long (at most 72)"]
+        );
+    }
+}
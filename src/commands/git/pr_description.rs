@@ -0,0 +1,475 @@
+use crate::utils::config::load_config;
+use crate::utils::dart::group_by_package;
+use crate::utils::git::{
+    get_commit_log, get_current_branch, get_diff, get_file_at_revision, get_git_root,
+    get_merge_base, is_git_repo,
+};
+use crate::utils::llm::{
+    generate, load_template, prepare_diff, render_template, DEFAULT_GENERATOR,
+};
+use anyhow::Result;
+use serde_yaml::Value;
+use std::path::Path;
+
+/// Pull request templates looked up at the git root, in order.
+const PR_TEMPLATE_PATHS: &[&str] = &[
+    ".github/pull_request_template.md",
+    ".github/PULL_REQUEST_TEMPLATE.md",
+];
+
+/// Sections filled in when the repository has no pull request template.
+const DEFAULT_PR_TEMPLATE: &str = "\
+## Summary
+
+## Changes
+
+## Testing
+";
+
+/// Prompt used unless `pr-description.template` names another one.
+const DEFAULT_TEMPLATE: &str = "\
+Write a GitHub pull request description for the changes of branch {{branch}}
+against {{base_branch}}.
+
+Fill in the sections of the pull request template below. Keep its headings
+and their order, replace the placeholder comments with content, and leave
+checklists unchecked unless the changes show the item is done.
+
+Reply with the markdown description only, without code fences or commentary.
+
+Pull request template:
+{{pr_template}}
+
+Summary of the changes:
+{{summary}}
+
+Changed files:
+{{stat}}
+
+Diff:
+{{diff}}
+";
+
+/// Pubspec sections whose entries are reported as dependency changes.
+const DEPENDENCY_SECTIONS: &[&str] = &["dependencies", "dev_dependencies", "dependency_overrides"];
+
+/// What changed on a branch since its merge-base with the base branch.
+#[derive(Debug)]
+struct BranchChanges {
+    branch: String,
+    base_branch: String,
+    /// Commits as `<short sha> <subject>`, oldest first.
+    commits: Vec<String>,
+    packages: Vec<PackageChange>,
+    /// Number of changed files outside any Dart package.
+    other_files: usize,
+    dependencies: Vec<DependencyChange>,
+}
+
+/// A Dart package with changed files.
+#[derive(Debug)]
+struct PackageChange {
+    /// Path relative to the git root, `.` for the root itself.
+    path: String,
+    name: Option<String>,
+    files: usize,
+}
+
+/// A dependency added, removed or changed in a pubspec.
+#[derive(Debug, PartialEq)]
+struct DependencyChange {
+    pubspec: String,
+    section: String,
+    name: String,
+    old: Option<String>,
+    new: Option<String>,
+}
+
+/// Describes the branch's changes since the merge-base with `base_branch`
+/// as a pull request description and prints it as markdown. Without
+/// `no_llm`, a generator fills in the repository's pull request template;
+/// with it, a structured summary is printed instead. Secrets in the diff are
+/// redacted before it is passed to the generator.
+pub fn run(
+    base_branch: &str,
+    no_llm: bool,
+    generator: Option<String>,
+    show_redactions: bool,
+    verbose: bool,
+) -> Result<()> {
+    if !is_git_repo(None::<&str>) {
+        anyhow::bail!("Not in a git repository");
+    }
+
+    let root = get_git_root(None::<&str>)?;
+    let changes = branch_changes(&root, base_branch)?;
+    if changes.commits.is_empty() {
+        anyhow::bail!("No commits on {} since {}", changes.branch, base_branch);
+    }
+
+    let summary = summarize(&changes);
+    if no_llm {
+        print!("{}", summary);
+        return Ok(());
+    }
+
+    let config = load_config(&root)?;
+    let range = format!("{}...HEAD", base_branch);
+    let stat = get_diff(&root, &["--stat", &range])?;
+    let diff = prepare_diff(
+        &get_diff(&root, &[&range])?,
+        &config.llm,
+        show_redactions,
+        verbose,
+    );
+
+    let pr_template = PR_TEMPLATE_PATHS
+        .iter()
+        .find_map(|path| {
+            let text = std::fs::read_to_string(root.join(path)).ok()?;
+            if verbose {
+                eprintln!("Filling in {}", path);
+            }
+            Some(text)
+        })
+        .unwrap_or_else(|| DEFAULT_PR_TEMPLATE.to_string());
+
+    let template = load_template(
+        &root,
+        config.pr_description.template.as_deref(),
+        DEFAULT_TEMPLATE,
+    )?;
+    let prompt = render_template(
+        &template,
+        &[
+            ("branch", &changes.branch),
+            ("base_branch", base_branch),
+            ("pr_template", pr_template.trim_end()),
+            ("summary", summary.trim_end()),
+            ("commits", &changes.commits.join("\n")),
+            ("stat", stat.trim_end()),
+            ("diff", diff.trim_end()),
+        ],
+    );
+
+    let generator = generator
+        .or(config.llm.command.clone())
+        .unwrap_or_else(|| DEFAULT_GENERATOR.to_string());
+    if verbose {
+        eprintln!(
+            "Generating a PR description with \"{}\" ({} byte prompt)",
+            generator,
+            prompt.len()
+        );
+    }
+
+    println!("{}", generate(&generator, &prompt, &root)?);
+    Ok(())
+}
+
+/// Collects the commits, changed packages and dependency changes of the
+/// current branch since its merge-base with `base_branch`.
+fn branch_changes(root: &Path, base_branch: &str) -> Result<BranchChanges> {
+    let merge_base = get_merge_base(root, base_branch, "HEAD")?;
+    let commits = get_commit_log(root, &format!("{}..HEAD", merge_base))?;
+    let files: Vec<String> = get_diff(root, &["--name-only", &merge_base, "HEAD"])?
+        .lines()
+        .filter(|line| !line.is_empty())
+        .map(str::to_string)
+        .collect();
+
+    let grouped = group_by_package(root, &files);
+    let packaged: usize = grouped.values().map(Vec::len).sum();
+    let packages = grouped
+        .into_iter()
+        .map(|(package, package_files)| {
+            let path = package
+                .strip_prefix(root)
+                .map(|p| p.to_string_lossy().replace('\\', "/"))
+                .unwrap_or_else(|_| package.display().to_string());
+            PackageChange {
+                path: if path.is_empty() {
+                    ".".to_string()
+                } else {
+                    path
+                },
+                name: package_name(&package.join("pubspec.yaml")),
+                files: package_files.len(),
+            }
+        })
+        .collect();
+
+    let dependencies = files
+        .iter()
+        .filter(|file| file.rsplit('/').next() == Some("pubspec.yaml"))
+        .flat_map(|file| {
+            dependency_changes(
+                file,
+                get_file_at_revision(root, &merge_base, file).as_deref(),
+                get_file_at_revision(root, "HEAD", file).as_deref(),
+            )
+        })
+        .collect();
+
+    Ok(BranchChanges {
+        branch: get_current_branch(Some(root)).unwrap_or_else(|_| "HEAD".to_string()),
+        base_branch: base_branch.to_string(),
+        commits,
+        packages,
+        other_files: files.len() - packaged,
+        dependencies,
+    })
+}
+
+/// Reads the `name` of a pubspec.
+fn package_name(pubspec: &Path) -> Option<String> {
+    let text = std::fs::read_to_string(pubspec).ok()?;
+    let value: Value = serde_yaml::from_str(&text).ok()?;
+    value.get("name")?.as_str().map(str::to_string)
+}
+
+/// Compares the dependencies of two versions of a pubspec, either of which
+/// may be missing or unparsable.
+fn dependency_changes(
+    pubspec: &str,
+    old: Option<&str>,
+    new: Option<&str>,
+) -> Vec<DependencyChange> {
+    let parse = |text: Option<&str>| -> Value {
+        text.and_then(|text| serde_yaml::from_str(text).ok())
+            .unwrap_or(Value::Null)
+    };
+    let (old, new) = (parse(old), parse(new));
+    let mut changes = Vec::new();
+
+    for section in DEPENDENCY_SECTIONS {
+        let entries = |pubspec: &Value| -> Vec<(String, Value)> {
+            pubspec
+                .get(section)
+                .and_then(Value::as_mapping)
+                .map(|mapping| {
+                    mapping
+                        .iter()
+                        .filter_map(|(k, v)| Some((k.as_str()?.to_string(), v.clone())))
+                        .collect()
+                })
+                .unwrap_or_default()
+        };
+        let (before, after) = (entries(&old), entries(&new));
+        let lookup = |entries: &[(String, Value)], name: &str| {
+            entries
+                .iter()
+                .find(|(n, _)| n == name)
+                .map(|(_, v)| v.clone())
+        };
+
+        let mut names: Vec<&String> = before.iter().chain(after.iter()).map(|(n, _)| n).collect();
+        names.sort();
+        names.dedup();
+
+        for name in names {
+            let (old, new) = (lookup(&before, name), lookup(&after, name));
+            if old != new {
+                changes.push(DependencyChange {
+                    pubspec: pubspec.to_string(),
+                    section: section.to_string(),
+                    name: name.clone(),
+                    old: old.as_ref().map(render_constraint),
+                    new: new.as_ref().map(render_constraint),
+                });
+            }
+        }
+    }
+
+    changes
+}
+
+/// Renders a dependency's constraint on one line: `^1.0.0`, `any`, or its
+/// source like `{path: ../core}`.
+fn render_constraint(value: &Value) -> String {
+    match value {
+        Value::Null => "any".to_string(),
+        Value::String(s) => s.clone(),
+        Value::Bool(b) => b.to_string(),
+        Value::Number(n) => n.to_string(),
+        Value::Sequence(items) => format!(
+            "[{}]",
+            items
+                .iter()
+                .map(render_constraint)
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+        Value::Mapping(mapping) => format!(
+            "{{{}}}",
+            mapping
+                .iter()
+                .map(|(k, v)| format!("{}: {}", render_constraint(k), render_constraint(v)))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+        Value::Tagged(tagged) => render_constraint(&tagged.value),
+    }
+}
+
+/// Formats the changes as a markdown summary: the commits, the changed
+/// packages and the dependency changes.
+fn summarize(changes: &BranchChanges) -> String {
+    let mut text = format!(
+        "## Summary\n\n{} commit{} on `{}` since `{}`.\n",
+        changes.commits.len(),
+        if changes.commits.len() == 1 { "" } else { "s" },
+        changes.branch,
+        changes.base_branch
+    );
+
+    text.push_str("\n## Commits\n\n");
+    for commit in &changes.commits {
+        text.push_str(&format!("- {}\n", commit));
+    }
+
+    text.push_str("\n## Changed packages\n\n");
+    if changes.packages.is_empty() {
+        text.push_str("None\n");
+    }
+    for package in &changes.packages {
+        let name = match &package.name {
+            Some(name) => format!("`{}` (`{}`)", name, package.path),
+            None => format!("`{}`", package.path),
+        };
+        text.push_str(&format!("- {}: {}\n", name, files(package.files)));
+    }
+    if changes.other_files > 0 {
+        text.push_str(&format!(
+            "- Outside packages: {}\n",
+            files(changes.other_files)
+        ));
+    }
+
+    text.push_str("\n## Dependency changes\n\n");
+    if changes.dependencies.is_empty() {
+        text.push_str("None\n");
+    }
+    for change in &changes.dependencies {
+        let what = match (&change.old, &change.new) {
+            (None, Some(new)) => format!("added {}", new),
+            (Some(old), None) => format!("removed {}", old),
+            (Some(old), Some(new)) => format!("{} → {}", old, new),
+            (None, None) => continue,
+        };
+        text.push_str(&format!(
+            "- `{}`: `{}` {} ({})\n",
+            change.pubspec, change.name, what, change.section
+        ));
+    }
+
+    text
+}
+
+fn files(count: usize) -> String {
+    format!("{} file{}", count, if count == 1 { "" } else { "s" })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dependency_changes() {
+        let old = "\
+name: app
+dependencies:
+  http: ^1.0.0
+  core:
+    path: ../core
+  intl:
+dev_dependencies:
+  lints: ^2.0.0
+";
+        let new = "\
+name: app
+dependencies:
+  http: ^1.2.0
+  core:
+    path: ../core
+  intl:
+  collection: ^1.18.0
+";
+
+        let change =
+            |section: &str, name: &str, old: Option<&str>, new: Option<&str>| DependencyChange {
+                pubspec: "app/pubspec.yaml".to_string(),
+                section: section.to_string(),
+                name: name.to_string(),
+                old: old.map(str::to_string),
+                new: new.map(str::to_string),
+            };
+
+        assert_eq!(
+            dependency_changes("app/pubspec.yaml", Some(old), Some(new)),
+            vec![
+                change("dependencies", "collection", None, Some("^1.18.0")),
+                change("dependencies", "http", Some("^1.0.0"), Some("^1.2.0")),
+                change("dev_dependencies", "lints", Some("^2.0.0"), None),
+            ]
+        );
+        assert_eq!(
+            dependency_changes(
+                "app/pubspec.yaml",
+                None,
+                Some("dependencies:\n  core:\n    path: ../core\n")
+            ),
+            vec![change(
+                "dependencies",
+                "core",
+                None,
+                Some("{path: ../core}")
+            )]
+        );
+        assert!(dependency_changes("app/pubspec.yaml", Some(old), Some(old)).is_empty());
+    }
+
+    #[test]
+    fn test_summarize() {
+        let changes = BranchChanges {
+            branch: "feature/login".to_string(),
+            base_branch: "main".to_string(),
+            commits: vec!["abc1234 feat: add login".to_string()],
+            packages: vec![PackageChange {
+                path: "packages/auth".to_string(),
+                name: Some("auth".to_string()),
+                files: 2,
+            }],
+            other_files: 1,
+            dependencies: vec![DependencyChange {
+                pubspec: "packages/auth/pubspec.yaml".to_string(),
+                section: "dependencies".to_string(),
+                name: "http".to_string(),
+                old: None,
+                new: Some("^1.2.0".to_string()),
+            }],
+        };
+
+        assert_eq!(
+            summarize(&changes),
+            "\
+## Summary
+
+1 commit on `feature/login` since `main`.
+
+## Commits
+
+- abc1234 feat: add login
+
+## Changed packages
+
+- `auth` (`packages/auth`): 2 files
+- Outside packages: 1 file
+
+## Dependency changes
+
+- `packages/auth/pubspec.yaml`: `http` added ^1.2.0 (dependencies)
+"
+        );
+    }
+}
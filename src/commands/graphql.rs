@@ -0,0 +1,195 @@
+use crate::commands::hook::explain::CheckPlan;
+use crate::commands::hook::graphql::is_graphql_file;
+use crate::commands::hook::outcome::{Finding, Severity};
+use crate::commands::hook::CheckOutcome;
+use crate::utils::config::{load_config, CONFIG_FILE_NAME};
+use crate::utils::dart::find_file_package_root;
+use crate::utils::files::list_files;
+use crate::utils::git::{get_git_root, is_git_repo, ChangeScope};
+use crate::utils::graphql::{self, Document, Schema};
+use anyhow::{Context, Result};
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+
+/// Directories never searched for GraphQL documents.
+const SKIPPED_DIRS: &[&str] = &[".dart_tool", ".git", "build"];
+
+pub fn validate(
+    files: Vec<String>,
+    schema: Option<String>,
+    scope: &ChangeScope,
+    verbose: bool,
+) -> CheckOutcome {
+    CheckOutcome::run("GraphQL validation", || {
+        validation_errors(files, schema, scope, verbose)
+    })
+}
+
+/// Plans validation of the changed operation files for `collate --explain`.
+pub fn validate_plan(scope: &ChangeScope) -> Result<CheckPlan> {
+    if !is_git_repo(None::<&str>) {
+        anyhow::bail!("Not in a git repository");
+    }
+
+    let root = get_git_root(None::<&str>)?;
+    let files: Vec<String> = scope
+        .changed_files(Some(&root))?
+        .into_iter()
+        .filter(|f| is_graphql_file(f) && root.join(f).is_file())
+        .collect();
+    let plan = CheckPlan::for_files(&root, files);
+
+    if plan.files.is_empty() {
+        return Ok(plan.note("Nothing to check: no changed GraphQL files"));
+    }
+
+    match load_config(&root)?.graphql.schema {
+        Some(schema) => Ok(plan.note(format!("Validated in-process against {}", schema))),
+        None => anyhow::bail!(
+            "No GraphQL schema configured (set graphql.schema in {})",
+            CONFIG_FILE_NAME
+        ),
+    }
+}
+
+/// Validates the target operation files and returns a finding per error.
+fn validation_errors(
+    files: Vec<String>,
+    schema: Option<String>,
+    scope: &ChangeScope,
+    verbose: bool,
+) -> Result<Vec<Finding>> {
+    if !is_git_repo(None::<&str>) {
+        anyhow::bail!("Not in a git repository");
+    }
+
+    let root = get_git_root(None::<&str>)?.canonicalize()?;
+    let config = load_config(&root)?;
+
+    let targets: BTreeSet<PathBuf> = if files.is_empty() {
+        scope
+            .changed_files(Some(&root))?
+            .into_iter()
+            .filter(|f| is_graphql_file(f))
+            .filter_map(|f| root.join(f).canonicalize().ok())
+            .collect()
+    } else {
+        files
+            .iter()
+            .map(|f| {
+                Path::new(f)
+                    .canonicalize()
+                    .with_context(|| format!("{} not found", f))
+            })
+            .collect::<Result<_>>()?
+    };
+
+    if targets.is_empty() {
+        if verbose {
+            eprintln!("No changed GraphQL files");
+        }
+        return Ok(Vec::new());
+    }
+
+    let schema_path = match (schema, config.graphql.schema) {
+        (Some(path), _) => PathBuf::from(path),
+        (None, Some(path)) => root.join(path),
+        (None, None) => anyhow::bail!(
+            "No GraphQL schema configured (pass --schema or set graphql.schema in {})",
+            CONFIG_FILE_NAME
+        ),
+    };
+    let schema_path = schema_path
+        .canonicalize()
+        .with_context(|| format!("GraphQL schema {} not found", schema_path.display()))?;
+
+    let source = std::fs::read_to_string(&schema_path)
+        .with_context(|| format!("Failed to read {}", schema_path.display()))?;
+    let schema_document = graphql::parse(&source).map_err(|e| {
+        anyhow::anyhow!(
+            "{}:{}:{}: {}",
+            relative(&root, &schema_path).display(),
+            e.pos.line,
+            e.pos.column,
+            e
+        )
+    })?;
+
+    // Fragments may live in any document of the package, so every GraphQL file
+    // of the affected packages is loaded; errors are only reported for targets.
+    let mut paths = targets.clone();
+    for target in &targets {
+        if let Some(package) = find_file_package_root(target) {
+            for file in list_files(&package, SKIPPED_DIRS)? {
+                if is_graphql_file(&file.to_string_lossy()) {
+                    paths.insert(file.canonicalize()?);
+                }
+            }
+        }
+    }
+    paths.remove(&schema_path);
+
+    let mut schema_documents = vec![schema_document];
+    let mut documents: Vec<Document> = Vec::new();
+    let mut sources = Vec::new();
+    let mut errors = Vec::new();
+
+    for path in paths {
+        let source = std::fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+
+        match graphql::parse(&source) {
+            Ok(document) if document.is_schema() => schema_documents.push(document),
+            Ok(document) => {
+                documents.push(document);
+                sources.push(path);
+            }
+            Err(e) if targets.contains(&path) => {
+                errors.push(Finding::new("syntax", Severity::Error, e.to_string()).at(
+                    relative(&root, &path).display().to_string(),
+                    Some(e.pos.line),
+                    Some(e.pos.column),
+                ))
+            }
+            Err(_) => {}
+        }
+    }
+
+    let schema = Schema::from_documents(&schema_documents);
+
+    for diagnostic in graphql::validate(&schema, &documents) {
+        let path = &sources[diagnostic.document];
+        if targets.contains(path) {
+            errors.push(
+                Finding::new(diagnostic.rule, Severity::Error, diagnostic.message).at(
+                    relative(&root, path).display().to_string(),
+                    Some(diagnostic.pos.line),
+                    Some(diagnostic.pos.column),
+                ),
+            );
+        }
+    }
+
+    if verbose {
+        eprintln!(
+            "Validated {} GraphQL file(s) against {}",
+            targets.len(),
+            relative(&root, &schema_path).display()
+        );
+    }
+
+    if verbose {
+        if errors.is_empty() {
+            eprintln!("✓ GraphQL operations are valid");
+        } else {
+            eprintln!("{} GraphQL validation error(s)", errors.len());
+        }
+    }
+
+    Ok(errors)
+}
+
+/// Returns `path` relative to the git root when it is inside it.
+fn relative<'a>(root: &Path, path: &'a Path) -> &'a Path {
+    path.strip_prefix(root).unwrap_or(path)
+}
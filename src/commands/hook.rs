@@ -1,88 +1,655 @@
+pub mod agent;
 pub mod analysis;
+pub mod annotations;
+pub mod autofix;
+pub mod cache;
+pub mod commit_msg;
+pub mod custom;
 pub mod dcm;
+pub mod explain;
 pub mod fix;
 pub mod format;
 pub mod graphql;
+pub mod install;
+pub mod outcome;
+pub mod pre_push;
+pub mod report;
+pub mod runner;
+pub mod secrets;
+pub mod skip;
+pub mod snapshot;
 
+pub use annotations::Annotations;
+use autofix::Fixer;
+use cache::{Cache, CacheSpec};
+use explain::{CheckPlan, Schedule};
+pub use outcome::{finish, CheckOutcome};
+pub use report::ReportSpec;
+pub use runner::TimeoutSpec;
+use runner::{run_tasks, Task};
+use skip::{PushedCommits, SkipDecision, Skips};
+use snapshot::StagedSnapshot;
+
+use crate::utils::config::{load_config, SkipConfig, CONFIG_FILE_NAME};
+use crate::utils::dart::{group_by_package, is_generated_dart_file};
+use crate::utils::git::{get_git_root, is_git_repo, ChangeScope};
 use anyhow::Result;
+use clap::Args;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Name under which problems with the staged snapshot are reported.
+const SNAPSHOT_CHECK_NAME: &str = "staged snapshot";
+
+/// Name under which problems running the fixers are reported.
+const FIX_CHECK_NAME: &str = "auto-fix";
+
+/// Files configuring the Dart tools in each package.
+const DART_PACKAGE_CONFIG: &[&str] = &["analysis_options.yaml", "pubspec.yaml", "pubspec.lock"];
+
+/// The check selection and runner flags shared by the commands running the
+/// collated checks.
+#[derive(Args, Debug, Clone, Default)]
+pub struct CollateArgs {
+    /// Run dart format check
+    #[arg(long)]
+    pub dart_format: bool,
+    /// Run dart analysis check
+    #[arg(long)]
+    pub dart_analysis: bool,
+    /// Run DCM analyze check
+    #[arg(long)]
+    pub dcm_analyze: bool,
+    /// Run GraphQL check
+    #[arg(long)]
+    pub graphql: bool,
+    /// Run GraphQL operation validation
+    #[arg(long)]
+    pub graphql_validate: bool,
+    /// Run git codeowners check
+    #[arg(long)]
+    pub codeowners: bool,
+    /// Run the secrets check on added lines
+    #[arg(long)]
+    pub secrets: bool,
+    /// Run the check with this name from mobdev.yaml (repeatable)
+    #[arg(long = "check", value_name = "NAME")]
+    pub checks: Vec<String>,
+    /// Maximum number of checks to run concurrently (defaults to the number of CPUs)
+    #[arg(short, long)]
+    pub jobs: Option<usize>,
+    /// Run every check, ignoring passes recorded in the cache
+    #[arg(long)]
+    pub no_cache: bool,
+    /// Stop checks after SECONDS, or one check with NAME=SECONDS (repeatable; 0 disables)
+    #[arg(long = "timeout", value_name = "[NAME=]SECONDS")]
+    pub timeouts: Vec<TimeoutSpec>,
+}
+
+impl CollateArgs {
+    /// Whether no check was selected, in which case every check runs.
+    fn run_all(&self) -> bool {
+        !self.dart_format
+            && !self.dart_analysis
+            && !self.dcm_analyze
+            && !self.graphql
+            && !self.graphql_validate
+            && !self.codeowners
+            && !self.secrets
+            && self.checks.is_empty()
+    }
+}
+
+/// What `collate` checks, and how.
+#[derive(Debug, Clone, Default)]
+pub struct CollateOptions {
+    pub scope: ChangeScope,
+    pub args: CollateArgs,
+    /// Check the staged content, setting aside unstaged changes.
+    pub snapshot: bool,
+    /// Run the fixers before checking.
+    pub fix: bool,
+    /// Stop at the first check that does not pass.
+    pub fail_fast: bool,
+    /// Describe the checks instead of running them.
+    pub explain: bool,
+    pub verbose: bool,
+    /// The pushed commits, whose trailers may skip checks, in `hook pre-push`.
+    pub pushed: Option<PushedCommits>,
+}
+
+pub fn collate(options: &CollateOptions) -> Vec<CheckOutcome> {
+    let scope = &options.scope;
+    let args = &options.args;
+    let verbose = options.verbose;
+    let jobs = args.jobs.unwrap_or_else(runner::default_jobs);
 
-#[allow(clippy::too_many_arguments)]
-pub fn collate(
-    staged: bool,
-    unstaged: bool,
-    all: bool,
-    base_branch: &str,
-    dart_format: bool,
-    dart_analysis: bool,
-    dcm_analyze: bool,
-    graphql: bool,
-    codeowners: bool,
-    verbose: bool,
-) -> Result<()> {
     if verbose {
         eprintln!("Running collated hook checks...");
     }
 
-    let mut failures = Vec::new();
+    let mut tasks = Vec::new();
 
     // Run checks if flags are set or if no flags are set (run all by default)
-    let run_all = !dart_format && !dart_analysis && !dcm_analyze && !graphql && !codeowners;
+    let run_all = args.run_all();
 
-    if run_all || dart_format {
-        if verbose {
-            eprintln!("Running dart format check...");
-        }
-        if format::check(staged, unstaged, all, base_branch, false).is_err() {
-            failures.push("dart format");
-        }
+    let config = crate::utils::git::get_git_root(None::<&str>)
+        .ok()
+        .map(load_config);
+
+    if run_all || args.dart_format {
+        tasks.push(
+            Task::new("dart format", false, move || format::check(scope, false))
+                .plan(move || format::plan(scope))
+                .cached(dart_cache_spec(&["dart", "--version"], false)),
+        );
     }
 
-    if run_all || dart_analysis {
-        if verbose {
-            eprintln!("Running dart analysis check...");
-        }
-        if analysis::check(staged, unstaged, all, base_branch, false).is_err() {
-            failures.push("dart analysis");
-        }
+    if run_all || args.dart_analysis {
+        tasks.push(
+            Task::new("dart analysis", false, move || {
+                analysis::check(scope, false)
+            })
+            .plan(move || analysis::plan(scope))
+            .cached(dart_cache_spec(&["dart", "--version"], true)),
+        );
     }
 
-    if run_all || dcm_analyze {
-        if verbose {
-            eprintln!("Running DCM analyze check...");
+    if run_all || args.dcm_analyze {
+        tasks.push(
+            Task::new("DCM analyze", false, move || {
+                dcm::analyze_check(scope, false)
+            })
+            .plan(move || dcm::analyze_plan(scope))
+            .cached(dart_cache_spec(&["dcm", "--version"], true)),
+        );
+    }
+
+    // Unconfigured GraphQL checks do not apply when running everything; they
+    // only report the missing configuration when asked for.
+    let graphql_config = match &config {
+        Some(Ok(config)) => Some(&config.graphql),
+        _ => None,
+    };
+
+    if args.graphql || (run_all && graphql_config.is_some_and(|g| g.command.is_some())) {
+        tasks.push(
+            Task::new(graphql::CHECK_NAME, false, move || {
+                graphql::check(scope, false)
+            })
+            .plan(move || graphql::plan(scope)),
+        );
+    }
+
+    let schema = graphql_config.and_then(|g| g.schema.clone());
+    if args.graphql_validate || (run_all && schema.is_some()) {
+        tasks.push(
+            Task::new("GraphQL validation", false, move || {
+                crate::commands::graphql::validate(Vec::new(), None, scope, false)
+            })
+            .plan(move || crate::commands::graphql::validate_plan(scope))
+            .cached(CacheSpec {
+                version_command: &[],
+                inputs: graphql::is_graphql_file,
+                package_inputs: Some(graphql::is_graphql_file),
+                package_config: &[],
+                config_files: schema.into_iter().map(PathBuf::from).collect(),
+            }),
+        );
+    }
+
+    if run_all || args.codeowners {
+        tasks.push(
+            Task::new("codeowners", false, || {
+                crate::commands::git::codeowners::check(false)
+            })
+            .plan(|| {
+                Ok(CheckPlan::default().note(
+                    "Runs in-process, comparing .github/CODEOWNERS with the OWNERS files and pubspec codeowners keys",
+                ))
+            }),
+        );
+    }
+
+    if run_all || args.secrets {
+        tasks.push(
+            Task::new(secrets::CHECK_NAME, false, move || {
+                secrets::check(scope, false)
+            })
+            .plan(move || secrets::plan(scope)),
+        );
+    }
+
+    let (default_timeout, mut configured_timeouts) = match &config {
+        Some(Ok(config)) => (config.collate.timeout, config.collate.timeouts.clone()),
+        _ => (None, BTreeMap::new()),
+    };
+    let skip_config = match &config {
+        Some(Ok(config)) => config.collate.skip.clone(),
+        _ => SkipConfig::default(),
+    };
+
+    match config {
+        Some(Ok(config)) => {
+            for name in &args.checks {
+                if !config.checks.iter().any(|c| &c.name == name) {
+                    let message = format!("No check named \"{}\" in {}", name, CONFIG_FILE_NAME);
+                    tasks.push(error_task(name, message));
+                }
+            }
+
+            for definition in config.checks {
+                if let Some(timeout) = definition.timeout {
+                    configured_timeouts.insert(definition.name.clone(), timeout);
+                }
+                if run_all || args.checks.contains(&definition.name) {
+                    let planned = definition.clone();
+                    tasks.push(
+                        Task::new(
+                            definition.name.clone(),
+                            definition.modifies_files,
+                            move || custom::check(&definition, scope, false),
+                        )
+                        .plan(move || custom::plan(&planned, scope)),
+                    );
+                }
+            }
+        }
+        Some(Err(e)) => {
+            tasks.push(error_task(CONFIG_FILE_NAME, format!("{:#}", e)));
         }
-        if dcm::analyze_check(staged, unstaged, all, base_branch, false).is_err() {
-            failures.push("DCM analyze");
+        None => {
+            for name in &args.checks {
+                tasks.push(error_task(name, "Not in a git repository".to_string()));
+            }
         }
     }
 
-    if run_all || graphql {
-        if verbose {
-            eprintln!("Running GraphQL check...");
+    for name in args.timeouts.iter().filter_map(|t| t.check.as_deref()) {
+        let fixing = options.fix && name == FIX_CHECK_NAME;
+        if !fixing && !tasks.iter().any(|t| t.name == name) {
+            eprintln!(
+                "Ignoring --timeout for \"{}\": no such check selected",
+                name
+            );
         }
-        if graphql::check(staged, unstaged, all, base_branch, false).is_err() {
-            failures.push("GraphQL");
+    }
+    let tasks: Vec<Task> = tasks
+        .into_iter()
+        .map(|task| {
+            let timeout = check_timeout(
+                &task.name,
+                &args.timeouts,
+                &configured_timeouts,
+                default_timeout,
+            );
+            task.timeout(timeout)
+        })
+        .collect();
+    let fix_timeout = check_timeout(
+        FIX_CHECK_NAME,
+        &args.timeouts,
+        &configured_timeouts,
+        default_timeout,
+    );
+
+    let skips = match get_git_root(None::<&str>) {
+        Ok(root) => Skips::resolve(
+            &root,
+            &skip_config,
+            &scope.base_branch,
+            scope.all || (!scope.staged && !scope.unstaged),
+            options.pushed.as_ref(),
+        ),
+        Err(_) => Skips::default(),
+    };
+    let names: Vec<&str> = tasks.iter().map(|t| t.name.as_str()).collect();
+    for request in skips.unknown(&names) {
+        eprintln!(
+            "Ignoring skip of \"{}\" ({}): no such check selected",
+            request.name, request.source
+        );
+    }
+    let mut skipped = Vec::new();
+    let tasks: Vec<Task> = tasks
+        .into_iter()
+        .map(|task| match skips.decide(&task.name) {
+            Some(SkipDecision::Skip(message)) => {
+                skipped.push(task.name.clone());
+                let name = task.name.clone();
+                let mut replacement = Task::new(task.name, false, move || {
+                    CheckOutcome::skipped(&name, message)
+                })
+                .timeout(task.timeout);
+                replacement.plan = task.plan;
+                replacement
+            }
+            Some(SkipDecision::Refuse(message)) => {
+                eprintln!("{}", message);
+                task
+            }
+            None => task,
+        })
+        .collect();
+
+    if verbose {
+        let names: Vec<&str> = tasks.iter().map(|t| t.name.as_str()).collect();
+        eprintln!("Checks: {} (jobs: {})", names.join(", "), jobs);
+    }
+
+    // A skipped check's fixer is skipped too.
+    let fixers: Vec<Fixer> = [
+        (
+            run_all || args.dart_format,
+            "dart format",
+            Fixer::DartFormat,
+        ),
+        (
+            run_all || args.dart_analysis,
+            "dart analysis",
+            Fixer::DartFix,
+        ),
+        (run_all || args.dcm_analyze, "DCM analyze", Fixer::DcmFix),
+    ]
+    .into_iter()
+    .filter(|(_, check, _)| !skipped.iter().any(|name| name == check))
+    .filter_map(|(selected, _, fixer)| selected.then_some(fixer))
+    .collect();
+
+    if options.explain {
+        let fixers = if options.fix { fixers } else { Vec::new() };
+        print!("{}", explain_tasks(tasks, &skips, options, jobs, &fixers));
+        return Vec::new();
+    }
+
+    // Fix before taking the snapshot, so that fixes to unstaged lines stay in
+    // the working tree; the checks then verify the result.
+    let mut fix_outcomes = Vec::new();
+    if options.fix {
+        match autofix::fix(scope, &fixers, fix_timeout, verbose) {
+            Ok(run) => {
+                autofix::print_summary(&run.files, verbose);
+                for (fixer, e) in run.failures {
+                    let name = format!("{} (fix)", fixer.name());
+                    fix_outcomes.push(CheckOutcome::run(&name, || Err(e)));
+                }
+            }
+            Err(e) => fix_outcomes.push(CheckOutcome::run(FIX_CHECK_NAME, || Err(e))),
         }
     }
 
-    if run_all || codeowners {
+    // Set aside unstaged changes first, so that the cache and the checks
+    // both see the staged content.
+    let snapshot = if options.snapshot {
+        let taken = crate::utils::git::get_git_root(None::<&str>)
+            .and_then(|root| StagedSnapshot::take(&root, verbose));
+        match taken {
+            Ok(snapshot) => snapshot,
+            Err(e) => {
+                fix_outcomes.push(CheckOutcome::run(SNAPSHOT_CHECK_NAME, || Err(e)));
+                return fix_outcomes;
+            }
+        }
+    } else {
+        None
+    };
+
+    let cache = if args.no_cache {
+        None
+    } else {
+        Cache::open().ok()
+    };
+    let changed = match &cache {
+        Some(_) => scope.changed_files(None::<&str>).unwrap_or_default(),
+        None => Vec::new(),
+    };
+
+    let mut keys = Vec::new();
+    let mut hits = 0;
+    let tasks: Vec<Task> = tasks
+        .into_iter()
+        .map(|task| {
+            let key = match (&cache, &task.cache) {
+                (Some(cache), Some(spec)) => cache.key(&task.name, spec, &changed).unwrap_or(None),
+                _ => None,
+            };
+
+            match key {
+                Some(key) if cache.as_ref().is_some_and(|c| c.is_hit(&key)) => {
+                    hits += 1;
+                    keys.push(None);
+                    let name = task.name.clone();
+                    Task::new(task.name, false, move || CheckOutcome::cached(&name))
+                        .timeout(task.timeout)
+                }
+                key => {
+                    keys.push(key);
+                    task
+                }
+            }
+        })
+        .collect();
+
+    let outcomes = run_tasks(tasks, jobs, options.fail_fast);
+
+    if let Some(cache) = &cache {
+        let misses = keys.iter().filter(|k| k.is_some()).count();
         if verbose {
-            eprintln!("Running codeowners check...");
+            eprintln!("Cache: {} hit(s), {} miss(es)", hits, misses);
         }
-        if crate::commands::git::codeowners::check(false).is_err() {
-            failures.push("codeowners");
+
+        for (outcome, key) in outcomes.iter().zip(&keys) {
+            if let (true, Some(key)) = (outcome.is_success(), key) {
+                if let Err(e) = cache.record(key) {
+                    if verbose {
+                        eprintln!("Failed to cache {}: {:#}", outcome.name, e);
+                    }
+                }
+            }
         }
     }
 
-    if !failures.is_empty() {
-        if verbose {
-            eprintln!("Failed checks: {}", failures.join(", "));
+    let mut outcomes = fix_outcomes.into_iter().chain(outcomes).collect::<Vec<_>>();
+    if let Some(snapshot) = snapshot {
+        if let Err(e) = snapshot.restore(verbose) {
+            outcomes.push(CheckOutcome::run(SNAPSHOT_CHECK_NAME, || Err(e)));
         }
-        std::process::exit(1);
     }
 
-    if verbose {
-        eprintln!("All checks passed ✓");
+    outcomes
+}
+
+/// Describes what `collate` would do with `tasks`, without running
+/// anything.
+fn explain_tasks(
+    tasks: Vec<Task>,
+    skips: &Skips,
+    options: &CollateOptions,
+    jobs: usize,
+    fixers: &[Fixer],
+) -> String {
+    let root = match get_git_root(None::<&str>) {
+        Ok(root) => root,
+        Err(_) => PathBuf::from("."),
+    };
+
+    let scope = &options.scope;
+    let description = if scope.files.is_some() {
+        "the given files".to_string()
+    } else if scope.all || (!scope.staged && !scope.unstaged) {
+        format!(
+            "all changes: commits since {}, staged and unstaged",
+            scope.base_branch
+        )
+    } else if scope.staged {
+        "staged changes".to_string()
+    } else {
+        "unstaged changes".to_string()
+    };
+
+    let changed = scope.changed_files(None::<&str>).unwrap_or_default();
+
+    let mut text = format!("Scope: {}\n", description);
+    text.push_str(&format!("Changed files ({}):\n", changed.len()));
+    for file in &changed {
+        text.push_str(&format!("  {}\n", file));
+    }
+    text.push_str(&format!("Jobs: {}\n", jobs));
+    if !fixers.is_empty() {
+        let names: Vec<&str> = fixers.iter().map(|f| f.name()).collect();
+        text.push_str(&format!("Auto-fix: would run {} first\n", names.join(", ")));
+    }
+    if options.snapshot {
+        text.push_str("Snapshot: would set aside unstaged changes while checking\n");
+    }
+
+    let cache = if options.args.no_cache {
+        None
+    } else {
+        Cache::open_recorded().ok()
+    };
+    for task in tasks {
+        let cached = match (&cache, &task.cache) {
+            (Some(cache), Some(spec)) => cache
+                .key(&task.name, spec, &changed)
+                .unwrap_or(None)
+                .is_some_and(|key| cache.is_hit(&key)),
+            _ => false,
+        };
+        let plan = match task.plan {
+            Some(plan) => plan(),
+            None => Ok(CheckPlan::default()),
+        };
+        let skipped = match skips.decide(&task.name) {
+            Some(SkipDecision::Skip(message)) => Some(message),
+            _ => None,
+        };
+        let schedule = Schedule {
+            timeout: task.timeout,
+            cached,
+            skipped,
+        };
+
+        text.push('\n');
+        text.push_str(&explain::render(&task.name, &plan, &schedule, &root));
     }
 
-    Ok(())
+    text
+}
+
+/// A task reporting that a check cannot run.
+fn error_task<'a>(name: &str, message: String) -> Task<'a> {
+    let name = name.to_string();
+    let planned = message.clone();
+    Task::new(name.clone(), false, move || {
+        CheckOutcome::run(&name, || anyhow::bail!(message))
+    })
+    .plan(move || anyhow::bail!(planned))
+}
+
+/// Resolves the timeout of a check: `--timeout NAME=SECS`, then `--timeout
+/// SECS`, then the check's timeout in `mobdev.yaml`, then `collate.timeout`.
+/// Zero means no timeout.
+fn check_timeout(
+    name: &str,
+    flags: &[TimeoutSpec],
+    configured: &BTreeMap<String, u64>,
+    default: Option<u64>,
+) -> Option<Duration> {
+    let flag = |check: Option<&str>| {
+        flags
+            .iter()
+            .rev()
+            .find(|t| t.check.as_deref() == check)
+            .map(|t| t.seconds)
+    };
+
+    flag(Some(name))
+        .or_else(|| flag(None))
+        .or_else(|| configured.get(name).copied())
+        .or(default)
+        .filter(|&seconds| seconds > 0)
+        .map(Duration::from_secs)
+}
+
+/// Cache spec for checks running a Dart tool over the changed Dart files.
+/// With `whole_package`, the check reads every Dart file of the packages, as
+/// analysis does, and depends on a workspace lockfile at the root too.
+fn dart_cache_spec(version_command: &'static [&'static str], whole_package: bool) -> CacheSpec {
+    CacheSpec {
+        version_command,
+        inputs: |f| f.ends_with(".dart") && !is_generated_dart_file(f),
+        package_inputs: whole_package.then_some(|f: &str| f.ends_with(".dart")),
+        package_config: DART_PACKAGE_CONFIG,
+        config_files: if whole_package {
+            vec![PathBuf::from("pubspec.lock")]
+        } else {
+            Vec::new()
+        },
+    }
+}
+
+/// Plans a check that runs a Dart tool on the changed Dart files of each
+/// package, using `command` to build the command for a package.
+fn plan_dart_check(
+    scope: &ChangeScope,
+    command: fn(&Path, &Path, &[String]) -> std::process::Command,
+) -> Result<CheckPlan> {
+    if !is_git_repo(None::<&str>) {
+        anyhow::bail!("Not in a git repository");
+    }
+
+    let root = get_git_root(None::<&str>)?;
+    let files = changed_dart_files(&root, scope)?;
+    let packages = group_by_package(&root, &files);
+    let mut plan = CheckPlan::for_files(&root, files.clone());
+
+    if files.is_empty() {
+        return Ok(plan.note("Nothing to check: no changed Dart files"));
+    }
+
+    for (package, package_files) in &packages {
+        plan = plan.command(package, &command(&root, package, package_files));
+    }
+
+    for file in &files {
+        if !packages.values().any(|f| f.contains(file)) {
+            plan = plan.note(format!("Skipped {}: not in a Dart package", file));
+        }
+    }
+
+    Ok(plan)
+}
+
+/// Returns the changed Dart files that still exist, skipping generated code.
+fn changed_dart_files(root: &Path, scope: &ChangeScope) -> Result<Vec<String>> {
+    Ok(scope
+        .changed_files(Some(root))?
+        .into_iter()
+        .filter(|f| f.ends_with(".dart") && !is_generated_dart_file(f))
+        .filter(|f| root.join(f).exists())
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_timeout_precedence() {
+        let configured = BTreeMap::from([("DCM analyze".to_string(), 600)]);
+        let flags = |specs: &[&str]| -> Vec<TimeoutSpec> {
+            specs.iter().map(|s| s.parse().unwrap()).collect()
+        };
+        let timeout = |name: &str, specs: &[&str]| {
+            check_timeout(name, &flags(specs), &configured, Some(300)).map(|d| d.as_secs())
+        };
+
+        assert_eq!(timeout("dart analysis", &[]), Some(300));
+        assert_eq!(timeout("DCM analyze", &[]), Some(600));
+        assert_eq!(timeout("DCM analyze", &["60"]), Some(60));
+        assert_eq!(timeout("DCM analyze", &["DCM analyze=90", "60"]), Some(90));
+        assert_eq!(timeout("dart analysis", &["0"]), None);
+    }
 }
@@ -0,0 +1,279 @@
+use super::autofix::{self, Fixer};
+use super::outcome::{CheckOutcome, CheckStatus};
+use super::FIX_CHECK_NAME;
+use crate::utils::config::load_config;
+use crate::utils::dart::is_generated_dart_file;
+use crate::utils::git::{get_changed_files, get_git_root, get_untracked_files, ChangeScope};
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::io::{IsTerminal, Read};
+use std::path::{Path, PathBuf};
+
+/// The fields of an agent hook event that mobdev uses. Events are passed as
+/// a JSON object on stdin; unknown fields are ignored.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct HookEvent {
+    /// Directory the agent is working in.
+    pub cwd: Option<PathBuf>,
+    pub hook_event_name: Option<String>,
+    pub tool_name: Option<String>,
+    /// Input of the tool that ran, for tool events.
+    pub tool_input: Value,
+    /// Whether the agent is already continuing because a stop hook blocked.
+    pub stop_hook_active: bool,
+}
+
+impl HookEvent {
+    /// Reads the event from stdin.
+    pub fn read() -> Result<Self> {
+        let stdin = std::io::stdin();
+        if stdin.is_terminal() {
+            anyhow::bail!("hook agent reads the hook event from stdin; run it from an agent hook");
+        }
+
+        let mut input = String::new();
+        stdin
+            .lock()
+            .read_to_string(&mut input)
+            .context("Failed to read the hook event from stdin")?;
+
+        Self::parse(&input)
+    }
+
+    pub fn parse(input: &str) -> Result<Self> {
+        serde_json::from_str(input).context("Invalid hook event")
+    }
+
+    /// Paths of the files written by the tool, from `file_path` (Write, Edit,
+    /// MultiEdit) or `notebook_path` (NotebookEdit). Relative paths are
+    /// resolved against `cwd`.
+    pub fn edited_files(&self) -> Vec<PathBuf> {
+        let cwd = self.cwd.clone().unwrap_or_else(|| PathBuf::from("."));
+        ["file_path", "notebook_path"]
+            .iter()
+            .filter_map(|key| self.tool_input.get(key)?.as_str())
+            .map(|path| cwd.join(path))
+            .collect()
+    }
+
+    /// Makes the agent's directory the current one, so that checks run on
+    /// the agent's repository.
+    pub fn enter_cwd(&self) -> Result<()> {
+        if let Some(cwd) = &self.cwd {
+            std::env::set_current_dir(cwd)
+                .with_context(|| format!("Failed to enter {}", cwd.display()))?;
+        }
+        Ok(())
+    }
+}
+
+/// Handles a `PostToolUse` event: runs the fixers on the edited Dart files
+/// and prints, as hook output, which files changed so that the agent re-reads
+/// them. Only the working tree changes: the index is left as the agent or
+/// the user staged it. Fixers that cannot run are reported on stderr without
+/// failing.
+pub fn post_edit(dart_fix: bool, dcm_fix: bool, verbose: bool) -> Result<()> {
+    let event = HookEvent::read()?;
+    event.enter_cwd()?;
+
+    let root = get_git_root(None::<&str>)?;
+    let files = dart_files(&root, &event.edited_files());
+    if files.is_empty() {
+        if verbose {
+            eprintln!("No edited Dart files");
+        }
+        return Ok(());
+    }
+
+    let fixers: Vec<Fixer> = [
+        (true, Fixer::DartFormat),
+        (dart_fix, Fixer::DartFix),
+        (dcm_fix, Fixer::DcmFix),
+    ]
+    .into_iter()
+    .filter_map(|(selected, fixer)| selected.then_some(fixer))
+    .collect();
+
+    let timeout = load_config(&root)?.collate.check_timeout(FIX_CHECK_NAME);
+    let run = autofix::fix_files(&root, &files, &fixers, false, timeout, verbose)?;
+    for (fixer, e) in &run.failures {
+        eprintln!("{} failed: {:#}", fixer.name(), e);
+    }
+    if verbose {
+        autofix::print_summary(&run.files, verbose);
+    }
+
+    if !run.files.is_empty() {
+        let fixed: Vec<String> = run
+            .files
+            .iter()
+            .map(|f| format!("{} ({})", f.path, f.fixers.join(", ")))
+            .collect();
+        let output = json!({
+            "hookSpecificOutput": {
+                "hookEventName": "PostToolUse",
+                "additionalContext": format!(
+                    "mobdev fixed {}; re-read the file(s) before editing them again.",
+                    fixed.join(", ")
+                ),
+            }
+        });
+        println!("{}", output);
+    }
+
+    Ok(())
+}
+
+/// The edited files that are Dart files of the repository at `root`, relative
+/// to it. Generated and deleted files are skipped.
+fn dart_files(root: &Path, edited: &[PathBuf]) -> Vec<String> {
+    let root = root.canonicalize().unwrap_or_else(|_| root.to_path_buf());
+    edited
+        .iter()
+        .filter_map(|path| path.canonicalize().ok())
+        .filter_map(|path| {
+            path.strip_prefix(&root)
+                .ok()
+                .map(|p| p.to_string_lossy().replace('\\', "/"))
+        })
+        .filter(|file| file.ends_with(".dart") && !is_generated_dart_file(file))
+        .collect()
+}
+
+/// Handles a `Stop` event: runs the checks in the agent's directory with
+/// `collate` and prints the decision as hook output. The checks are given
+/// all changes since `base_branch`, including the untracked files the agent
+/// created.
+pub fn stop<F>(base_branch: &str, collate: F) -> Result<()>
+where
+    F: FnOnce(ChangeScope) -> Vec<CheckOutcome>,
+{
+    let event = HookEvent::read()?;
+    event.enter_cwd()?;
+
+    let root = get_git_root(None::<&str>)?;
+    let mut files = get_changed_files(Some(&root), base_branch, false, false, true)?;
+    files.extend(get_untracked_files(&root)?);
+    files.sort();
+    files.dedup();
+    let scope = ChangeScope::new(false, false, true, base_branch).with_files(files);
+
+    let outcomes = collate(scope);
+    println!("{}", stop_decision(&outcomes, event.stop_hook_active));
+    Ok(())
+}
+
+/// Turns the outcomes of the checks run when the agent stops into the hook
+/// output: a `block` decision listing the problems when a check failed, or an
+/// empty object letting the agent stop.
+///
+/// Only failed and timed out checks block, since the agent can act on them;
+/// checks that could not run are reported to the user. When the agent is
+/// already continuing because of an earlier block, it is let go with a
+/// message, so that it cannot loop forever on a problem it cannot fix.
+pub fn stop_decision(outcomes: &[CheckOutcome], stop_hook_active: bool) -> Value {
+    let blocking: Vec<&CheckOutcome> = outcomes
+        .iter()
+        .filter(|o| matches!(o.status, CheckStatus::Failed | CheckStatus::Timeout))
+        .collect();
+    let errors: Vec<String> = outcomes
+        .iter()
+        .filter(|o| o.status == CheckStatus::Error)
+        .flat_map(|o| o.messages.iter().map(move |m| format!("{}: {}", o.name, m)))
+        .collect();
+
+    let names: Vec<&str> = blocking.iter().map(|o| o.name.as_str()).collect();
+    let mut output = json!({});
+
+    if !blocking.is_empty() && stop_hook_active {
+        output["systemMessage"] = json!(format!("mobdev checks still fail: {}", names.join(", ")));
+    } else if !blocking.is_empty() {
+        let mut reason =
+            String::from("mobdev checks failed; fix these problems before stopping:\n");
+        for outcome in &blocking {
+            reason.push_str(&format!("\n{} ({}):\n", outcome.name, outcome.status));
+            for finding in &outcome.findings {
+                reason.push_str(&format!("  {}\n", finding));
+            }
+            for message in &outcome.messages {
+                reason.push_str(&format!("  {}\n", message));
+            }
+        }
+        output["decision"] = json!("block");
+        output["reason"] = json!(reason.trim_end());
+    } else if !errors.is_empty() {
+        output["systemMessage"] = json!(format!(
+            "mobdev checks could not run: {}",
+            errors.join("; ")
+        ));
+    }
+
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::hook::outcome::{Finding, Severity};
+
+    #[test]
+    fn test_edited_files() {
+        let event = HookEvent::parse(
+            r#"{
+                "session_id": "abc",
+                "cwd": "/repo",
+                "hook_event_name": "PostToolUse",
+                "tool_name": "Edit",
+                "tool_input": {"file_path": "app/lib/a.dart", "old_string": "a", "new_string": "b"},
+                "tool_response": {"success": true}
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(event.tool_name.as_deref(), Some("Edit"));
+        assert_eq!(
+            event.edited_files(),
+            vec![PathBuf::from("/repo/app/lib/a.dart")]
+        );
+
+        let event = HookEvent::parse(r#"{"hook_event_name": "Stop"}"#).unwrap();
+        assert!(event.edited_files().is_empty());
+        assert!(!event.stop_hook_active);
+    }
+
+    #[test]
+    fn test_stop_decision() {
+        let passed = CheckOutcome::run("dart format", || Ok(Vec::new()));
+        let failed = CheckOutcome::run("dart analysis", || {
+            Ok(vec![Finding::new(
+                "unused_import",
+                Severity::Warning,
+                "Unused import",
+            )
+            .at("lib/a.dart", Some(1), Some(8))])
+        });
+        let error = CheckOutcome::run("DCM analyze", || anyhow::bail!("dcm not found"));
+
+        assert_eq!(
+            stop_decision(std::slice::from_ref(&passed), false),
+            json!({})
+        );
+        assert_eq!(
+            stop_decision(&[passed.clone(), failed.clone(), error.clone()], false),
+            json!({
+                "decision": "block",
+                "reason": "mobdev checks failed; fix these problems before stopping:\n\ndart analysis (failed):\n  lib/a.dart:1:8: Unused import",
+            })
+        );
+        assert_eq!(
+            stop_decision(&[failed], true),
+            json!({ "systemMessage": "mobdev checks still fail: dart analysis" })
+        );
+        assert_eq!(
+            stop_decision(&[passed, error], false),
+            json!({ "systemMessage": "mobdev checks could not run: DCM analyze: dcm not found" })
+        );
+    }
+}
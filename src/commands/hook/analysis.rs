@@ -1,17 +1,169 @@
-use anyhow::Result;
-
-pub fn check(
-    _staged: bool,
-    _unstaged: bool,
-    _all: bool,
-    base_branch: &str,
-    verbose: bool,
-) -> Result<()> {
+use super::explain::CheckPlan;
+use super::outcome::{Finding, Severity};
+use super::{changed_dart_files, plan_dart_check, CheckOutcome};
+use crate::utils::dart::group_by_package;
+use crate::utils::git::{get_git_root, is_git_repo, ChangeScope};
+use crate::utils::shell::run_with_timeout;
+use anyhow::{Context, Result};
+use std::path::Path;
+use std::process::Command;
+
+pub fn check(scope: &ChangeScope, verbose: bool) -> CheckOutcome {
+    CheckOutcome::run("dart analysis", || analyze(scope, verbose))
+}
+
+/// Plans `check` for `collate --explain`.
+pub fn plan(scope: &ChangeScope) -> Result<CheckPlan> {
+    plan_dart_check(scope, command)
+}
+
+/// Builds the `dart analyze` command for files of a package.
+fn command(root: &Path, package: &Path, files: &[String]) -> Command {
+    let mut command = Command::new("dart");
+    command
+        .arg("analyze")
+        .arg("--format=machine")
+        .args(files.iter().map(|f| root.join(f)))
+        .current_dir(package);
+    command
+}
+
+/// Runs `dart analyze` on the changed Dart files of each package and returns
+/// the reported diagnostics.
+fn analyze(scope: &ChangeScope, verbose: bool) -> Result<Vec<Finding>> {
+    if !is_git_repo(None::<&str>) {
+        anyhow::bail!("Not in a git repository");
+    }
+
+    let root = get_git_root(None::<&str>)?;
+    let files = changed_dart_files(&root, scope)?;
+
+    if files.is_empty() {
+        if verbose {
+            eprintln!("No changed Dart files");
+        }
+        return Ok(Vec::new());
+    }
+
+    let mut findings = Vec::new();
+
+    for (package, files) in group_by_package(&root, &files) {
+        if verbose {
+            eprintln!("Analyzing {} file(s) in {}", files.len(), package.display());
+        }
+
+        let output = run_with_timeout(&mut command(&root, &package, &files), None)
+            .context("Failed to run dart analyze (is the Dart SDK installed?)")?;
+
+        // Diagnostics are written to stderr by older SDKs and stdout by newer ones.
+        let text = format!(
+            "{}{}",
+            String::from_utf8_lossy(&output.stdout),
+            String::from_utf8_lossy(&output.stderr)
+        );
+        let package_findings = parse_machine_output(&text, &root);
+
+        if !output.status.success() && package_findings.is_empty() {
+            anyhow::bail!(
+                "dart analyze failed in {}: {}",
+                package.display(),
+                text.trim()
+            );
+        }
+
+        findings.extend(package_findings);
+    }
+
     if verbose {
-        eprintln!("Dart analysis check not yet fully implemented");
-        eprintln!("Base branch: {}", base_branch);
+        if findings.is_empty() {
+            eprintln!("✓ No analysis issues");
+        } else {
+            eprintln!("{} analysis issue(s) found", findings.len());
+        }
     }
 
-    // Placeholder - would check if Dart files pass dart analyze
-    anyhow::bail!("Analysis check not yet implemented");
+    Ok(findings)
+}
+
+/// Parses `dart analyze --format=machine` output, which has one diagnostic
+/// per line as `SEVERITY|TYPE|CODE|FILE|LINE|COLUMN|LENGTH|MESSAGE`.
+/// File paths are made relative to `root` when possible.
+fn parse_machine_output(output: &str, root: &Path) -> Vec<Finding> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let fields = split_machine_line(line);
+            let [severity, _, code, file, line, column, _, message] = fields.as_slice() else {
+                return None;
+            };
+
+            let severity = match severity.as_str() {
+                "ERROR" => Severity::Error,
+                "WARNING" => Severity::Warning,
+                "INFO" => Severity::Info,
+                _ => return None,
+            };
+
+            let file = Path::new(file);
+            let file = file.strip_prefix(root).unwrap_or(file);
+
+            Some(
+                Finding::new(code.to_lowercase(), severity, message.as_str()).at(
+                    file.display().to_string(),
+                    line.parse().ok(),
+                    column.parse().ok(),
+                ),
+            )
+        })
+        .collect()
+}
+
+/// Splits a machine-format line on `|`, honouring backslash escapes.
+fn split_machine_line(line: &str) -> Vec<String> {
+    let mut fields = vec![String::new()];
+    let mut chars = line.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => {
+                if let Some(next) = chars.next() {
+                    fields.last_mut().unwrap().push(next);
+                }
+            }
+            '|' => fields.push(String::new()),
+            _ => fields.last_mut().unwrap().push(c),
+        }
+    }
+
+    fields
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_machine_output() {
+        let output = "\
+Analyzing app...
+ERROR|COMPILE_TIME_ERROR|UNDEFINED_IDENTIFIER|/repo/app/lib/main.dart|4|3|5|Undefined name 'foo'.
+WARNING|STATIC_WARNING|UNUSED_IMPORT|/repo/app/lib/a.dart|1|8|10|Unused import: 'b.dart'.
+INFO|LINT|PREFER_CONST|/elsewhere/c.dart|2|1|1|Use a \\| pipe.
+";
+        let findings = parse_machine_output(output, Path::new("/repo"));
+
+        assert_eq!(findings.len(), 3);
+        assert_eq!(
+            findings[0],
+            Finding::new(
+                "undefined_identifier",
+                Severity::Error,
+                "Undefined name 'foo'."
+            )
+            .at("app/lib/main.dart", Some(4), Some(3))
+        );
+        assert_eq!(findings[1].severity, Severity::Warning);
+        assert_eq!(findings[2].file.as_deref(), Some("/elsewhere/c.dart"));
+        assert_eq!(findings[2].message, "Use a | pipe.");
+    }
 }
@@ -0,0 +1,226 @@
+use super::outcome::{CheckOutcome, CheckStatus, Finding, Severity};
+use anyhow::{Context, Result};
+use std::io::Write;
+
+/// How check results are annotated for the CI system running them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum Annotations {
+    /// GitHub Actions workflow commands and step summary.
+    Github,
+    /// Plain output only.
+    None,
+}
+
+impl Annotations {
+    /// Resolves the annotation mode, defaulting to GitHub annotations when
+    /// running in GitHub Actions.
+    pub fn resolve(option: Option<Annotations>) -> Annotations {
+        option.unwrap_or_else(|| {
+            if std::env::var("GITHUB_ACTIONS").is_ok_and(|v| v == "true") {
+                Annotations::Github
+            } else {
+                Annotations::None
+            }
+        })
+    }
+}
+
+/// Returns the workflow commands annotating a check's findings and errors,
+/// or why it was skipped. Passed checks are not annotated.
+pub fn github_annotations(outcome: &CheckOutcome) -> Vec<String> {
+    match outcome.status {
+        CheckStatus::Passed | CheckStatus::Cancelled => Vec::new(),
+        CheckStatus::Failed => outcome
+            .findings
+            .iter()
+            .map(|f| finding_annotation(&outcome.name, f))
+            .collect(),
+        CheckStatus::Error | CheckStatus::Timeout => outcome
+            .messages
+            .iter()
+            .map(|m| {
+                format!(
+                    "::error title={}::{}",
+                    escape_property(&outcome.name),
+                    escape_data(m)
+                )
+            })
+            .collect(),
+        CheckStatus::Skipped => outcome
+            .messages
+            .iter()
+            .map(|m| {
+                format!(
+                    "::notice title={}::{}",
+                    escape_property(&outcome.name),
+                    escape_data(m)
+                )
+            })
+            .collect(),
+    }
+}
+
+fn finding_annotation(check: &str, finding: &Finding) -> String {
+    let command = match finding.severity {
+        Severity::Error => "error",
+        Severity::Warning => "warning",
+        Severity::Info => "notice",
+    };
+
+    let mut properties = Vec::new();
+    if let Some(file) = &finding.file {
+        properties.push(format!("file={}", escape_property(file)));
+        if let Some(line) = finding.line {
+            properties.push(format!("line={}", line));
+            if let Some(column) = finding.column {
+                properties.push(format!("col={}", column));
+            }
+        }
+    }
+    properties.push(format!(
+        "title={}",
+        escape_property(&format!("{} ({})", check, finding.rule))
+    ));
+
+    format!(
+        "::{} {}::{}",
+        command,
+        properties.join(","),
+        escape_data(&finding.message)
+    )
+}
+
+/// Appends a markdown summary of the outcomes to the file named by
+/// `$GITHUB_STEP_SUMMARY`, if set.
+pub fn write_step_summary(outcomes: &[CheckOutcome]) -> Result<()> {
+    let Some(path) = std::env::var_os("GITHUB_STEP_SUMMARY") else {
+        return Ok(());
+    };
+
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .with_context(|| format!("Failed to open step summary {}", path.to_string_lossy()))?;
+
+    file.write_all(step_summary(outcomes).as_bytes())
+        .context("Failed to write step summary")
+}
+
+/// Renders a markdown table of the outcomes, followed by the findings and
+/// errors of each check that did not pass, and the reasons checks were
+/// skipped.
+pub fn step_summary(outcomes: &[CheckOutcome]) -> String {
+    let mut md = String::from("### mobdev hook checks\n\n");
+    md.push_str("| Check | Status | Findings | Duration |\n");
+    md.push_str("|-------|--------|----------|----------|\n");
+
+    for outcome in outcomes {
+        let mark = match outcome.status {
+            CheckStatus::Passed => "✅",
+            CheckStatus::Failed => "❌",
+            CheckStatus::Error => "⚠️",
+            CheckStatus::Timeout => "⏱️",
+            CheckStatus::Cancelled => "⏹️",
+            CheckStatus::Skipped => "⏭️",
+        };
+        md.push_str(&format!(
+            "| {} | {} {} | {} | {:.2}s |\n",
+            outcome.name,
+            mark,
+            outcome.status,
+            outcome.findings.len(),
+            outcome.duration.as_secs_f64()
+        ));
+    }
+
+    for outcome in outcomes
+        .iter()
+        .filter(|o| !o.is_success() || o.status == CheckStatus::Skipped)
+    {
+        md.push_str(&format!(
+            "\n<details><summary>{}</summary>\n\n",
+            outcome.name
+        ));
+        for finding in &outcome.findings {
+            md.push_str(&format!("- `{}` ({})\n", finding, finding.rule));
+        }
+        for message in &outcome.messages {
+            md.push_str(&format!("- {}\n", message));
+        }
+        md.push_str("\n</details>\n");
+    }
+
+    md.push('\n');
+    md
+}
+
+/// Escapes the message of a workflow command.
+fn escape_data(text: &str) -> String {
+    text.replace('%', "%25")
+        .replace('\r', "%0D")
+        .replace('\n', "%0A")
+}
+
+/// Escapes a property value of a workflow command.
+fn escape_property(text: &str) -> String {
+    escape_data(text).replace(':', "%3A").replace(',', "%2C")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_github_annotations() {
+        let outcome = CheckOutcome::run("dart analysis", || {
+            Ok(vec![
+                Finding::new(
+                    "unused_import",
+                    Severity::Warning,
+                    "Unused import: 'a.dart'",
+                )
+                .at("lib/main.dart", Some(1), Some(8)),
+                Finding::new("todo", Severity::Info, "50% done\nsee below"),
+            ])
+        });
+
+        assert_eq!(
+            github_annotations(&outcome),
+            vec![
+                "::warning file=lib/main.dart,line=1,col=8,title=dart analysis (unused_import)::Unused import: 'a.dart'",
+                "::notice title=dart analysis (todo)::50%25 done%0Asee below",
+            ]
+        );
+
+        let outcome = CheckOutcome::run("DCM analyze", || anyhow::bail!("dcm: not found"));
+        assert_eq!(
+            github_annotations(&outcome),
+            vec!["::error title=DCM analyze::dcm: not found"]
+        );
+
+        let outcome = CheckOutcome::run("GraphQL", || Ok(Vec::new()));
+        assert!(github_annotations(&outcome).is_empty());
+    }
+
+    #[test]
+    fn test_step_summary() {
+        let outcomes = vec![
+            CheckOutcome::run("dart format", || Ok(Vec::new())),
+            CheckOutcome::run("GraphQL", || {
+                Ok(vec![Finding::new(
+                    "stale-fake",
+                    Severity::Error,
+                    "GraphQL fake is stale",
+                )
+                .at("app/lib/q.fake.dart", None, None)])
+            }),
+        ];
+
+        let md = step_summary(&outcomes);
+        assert!(md.contains("| dart format | ✅ passed | 0 |"));
+        assert!(md.contains("| GraphQL | ❌ failed | 1 |"));
+        assert!(md.contains("- `app/lib/q.fake.dart: GraphQL fake is stale` (stale-fake)"));
+        assert!(!md.contains("<summary>dart format</summary>"));
+    }
+}
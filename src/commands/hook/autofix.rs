@@ -0,0 +1,486 @@
+use super::changed_dart_files;
+use crate::utils::dart::group_by_package;
+use crate::utils::git::{
+    get_git_root, get_staged_content, is_git_repo, stage_content, ChangeScope,
+};
+use crate::utils::shell::{run_with_timeout, with_limits, CommandLimits};
+use anyhow::{Context, Result};
+use regex::Regex;
+use std::collections::{BTreeMap, HashSet};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::{Duration, Instant};
+
+/// A tool that fixes Dart files in place. Fixers run in declaration order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Fixer {
+    DartFormat,
+    DartFix,
+    DcmFix,
+}
+
+impl Fixer {
+    pub fn name(self) -> &'static str {
+        match self {
+            Fixer::DartFormat => "dart format",
+            Fixer::DartFix => "dart fix",
+            Fixer::DcmFix => "dcm fix",
+        }
+    }
+
+    /// Fixes files of a package, given relative to it.
+    fn apply(self, package: &Path, files: &[PathBuf]) -> Result<()> {
+        match self {
+            Fixer::DartFormat => run(
+                Command::new("dart").arg("format").args(files),
+                package,
+                "dart format (is the Dart SDK installed?)",
+            ),
+            // `dart fix` takes a single file or directory.
+            Fixer::DartFix => files.iter().try_for_each(|file| {
+                run(
+                    Command::new("dart").arg("fix").arg("--apply").arg(file),
+                    package,
+                    "dart fix (is the Dart SDK installed?)",
+                )
+            }),
+            Fixer::DcmFix => run(
+                Command::new("dcm").arg("fix").args(files),
+                package,
+                "dcm fix (is DCM installed?)",
+            ),
+        }
+    }
+}
+
+/// A file changed by the fixers.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FixedFile {
+    /// Path relative to the git root.
+    pub path: String,
+    /// Names of the fixers that changed the file, in order.
+    pub fixers: Vec<&'static str>,
+    pub added: usize,
+    pub removed: usize,
+    /// For a staged file, how many of its fixes (diff hunks) were staged, out
+    /// of how many. Fixes overlapping unstaged changes are left unstaged.
+    pub staged_fixes: Option<(usize, usize)>,
+}
+
+/// The result of running the fixers.
+pub struct FixRun {
+    pub files: Vec<FixedFile>,
+    /// Fixers that could not run, with the reason.
+    pub failures: Vec<(Fixer, anyhow::Error)>,
+}
+
+/// Runs the fixers on the changed Dart files, then stages the fixes of the
+/// files that were staged. Only the fixes are staged: unstaged changes in the
+/// same files stay unstaged.
+pub fn fix(
+    scope: &ChangeScope,
+    fixers: &[Fixer],
+    timeout: Option<Duration>,
+    verbose: bool,
+) -> Result<FixRun> {
+    if !is_git_repo(None::<&str>) {
+        anyhow::bail!("Not in a git repository");
+    }
+
+    let root = get_git_root(None::<&str>)?;
+    let files = changed_dart_files(&root, scope)?;
+    fix_files(&root, &files, fixers, true, timeout, verbose)
+}
+
+/// Runs the fixers on Dart files given relative to `root`. With `stage_fixes`,
+/// then stages the fixes of the files that were staged; without it the index
+/// is left alone. Files outside Dart packages are left alone.
+///
+/// A fixer still running after `timeout`, across all packages, is stopped
+/// and reported as failed.
+pub fn fix_files(
+    root: &Path,
+    files: &[String],
+    fixers: &[Fixer],
+    stage_fixes: bool,
+    timeout: Option<Duration>,
+    verbose: bool,
+) -> Result<FixRun> {
+    let packages = group_by_package(root, files);
+    let files: Vec<String> = packages.values().flatten().cloned().collect();
+
+    let originals = read_files(root, &files);
+    let mut contents = originals.clone();
+    let mut changed_by: BTreeMap<&str, Vec<&'static str>> = BTreeMap::new();
+    let mut failures = Vec::new();
+
+    for &fixer in fixers {
+        if verbose {
+            eprintln!("Running {} on {} file(s)", fixer.name(), files.len());
+        }
+
+        let limits = CommandLimits {
+            deadline: timeout.map(|t| (Instant::now() + t, t)),
+            cancelled: None,
+        };
+        let result = with_limits(limits, || {
+            packages.iter().try_for_each(|(package, package_files)| {
+                let relative: Vec<PathBuf> = package_files
+                    .iter()
+                    .map(|f| {
+                        let path = root.join(f);
+                        path.strip_prefix(package).unwrap_or(&path).to_path_buf()
+                    })
+                    .collect();
+                fixer.apply(package, &relative)
+            })
+        });
+        if let Err(e) = result {
+            failures.push((fixer, e));
+        }
+
+        let after = read_files(root, &files);
+        for (file, (before, after)) in files.iter().zip(contents.iter().zip(&after)) {
+            if before != after {
+                changed_by.entry(file).or_default().push(fixer.name());
+            }
+        }
+        contents = after;
+    }
+
+    let staged_files = if stage_fixes {
+        staged_files(root)?
+    } else {
+        HashSet::new()
+    };
+    let mut fixed = Vec::new();
+
+    for (i, file) in files.iter().enumerate() {
+        let (Some(original), Some(current)) = (&originals[i], &contents[i]) else {
+            continue;
+        };
+        if original == current {
+            continue;
+        }
+
+        let hunks = diff_hunks(root, original, current)?;
+        let staged_fixes = if staged_files.contains(file) {
+            Some((restage(root, file, original, current, &hunks)?, hunks.len()))
+        } else {
+            None
+        };
+
+        fixed.push(FixedFile {
+            path: file.clone(),
+            fixers: changed_by.remove(file.as_str()).unwrap_or_default(),
+            added: hunks.iter().map(|h| h.new_len).sum(),
+            removed: hunks.iter().map(|h| h.old_len).sum(),
+            staged_fixes,
+        });
+    }
+
+    Ok(FixRun {
+        files: fixed,
+        failures,
+    })
+}
+
+/// Prints what the fixers changed, to stderr.
+pub fn print_summary(files: &[FixedFile], verbose: bool) {
+    if files.is_empty() {
+        if verbose {
+            eprintln!("Nothing to fix");
+        }
+        return;
+    }
+
+    let width = files.iter().map(|f| f.path.len()).max().unwrap_or(0);
+    eprintln!("Fixed {} file(s):", files.len());
+    for file in files {
+        let staging = match file.staged_fixes {
+            None => String::new(),
+            Some((staged, total)) if staged == total => " (re-staged)".to_string(),
+            Some((0, _)) => " (not re-staged: overlaps unstaged changes)".to_string(),
+            Some((staged, total)) => format!(
+                " ({} of {} fixes re-staged; the rest overlap unstaged changes)",
+                staged, total
+            ),
+        };
+        eprintln!(
+            "  {:width$}  +{} -{}  {}{}",
+            file.path,
+            file.added,
+            file.removed,
+            file.fixers.join(", "),
+            staging,
+            width = width
+        );
+    }
+}
+
+/// A changed range between two versions of a file, in 0-based lines.
+#[derive(Debug, Clone, PartialEq)]
+struct Hunk {
+    old_start: usize,
+    old_len: usize,
+    new_start: usize,
+    new_len: usize,
+}
+
+/// Stages the fixes of a file, one hunk at a time, by merging the change from
+/// `original` to `fixed` into its staged content. Hunks that conflict with
+/// unstaged changes are skipped. Returns the number of hunks staged.
+fn restage(
+    root: &Path,
+    file: &str,
+    original: &[u8],
+    fixed: &[u8],
+    hunks: &[Hunk],
+) -> Result<usize> {
+    let (mode, staged) = get_staged_content(root, file)?;
+
+    if staged == original {
+        stage_content(root, file, &mode, fixed)?;
+        return Ok(hunks.len());
+    }
+
+    let original_lines: Vec<&[u8]> = original.split_inclusive(|&b| b == b'\n').collect();
+    let fixed_lines: Vec<&[u8]> = fixed.split_inclusive(|&b| b == b'\n').collect();
+    let mut index = staged;
+    let mut accepted = Vec::new();
+
+    for hunk in hunks {
+        accepted.push(hunk.clone());
+        let theirs = apply_hunks(&original_lines, &fixed_lines, &accepted);
+        match merge(root, &index, original, &theirs)? {
+            Some(merged) => index = merged,
+            None => {
+                accepted.pop();
+            }
+        }
+    }
+
+    if !accepted.is_empty() {
+        stage_content(root, file, &mode, &index)?;
+    }
+    Ok(accepted.len())
+}
+
+/// Rebuilds `original` with the given hunks, in order, taken from `fixed`.
+fn apply_hunks(original: &[&[u8]], fixed: &[&[u8]], hunks: &[Hunk]) -> Vec<u8> {
+    let mut result = Vec::new();
+    let mut position = 0;
+
+    for hunk in hunks {
+        original[position..hunk.old_start]
+            .iter()
+            .for_each(|line| result.extend_from_slice(line));
+        fixed[hunk.new_start..hunk.new_start + hunk.new_len]
+            .iter()
+            .for_each(|line| result.extend_from_slice(line));
+        position = hunk.old_start + hunk.old_len;
+    }
+    original[position..]
+        .iter()
+        .for_each(|line| result.extend_from_slice(line));
+
+    result
+}
+
+/// Three-way merges the change from `base` to `theirs` into `ours`. Returns
+/// `None` on conflicts.
+fn merge(root: &Path, ours: &[u8], base: &[u8], theirs: &[u8]) -> Result<Option<Vec<u8>>> {
+    let ours_file = temp_file(ours)?;
+    let base_file = temp_file(base)?;
+    let theirs_file = temp_file(theirs)?;
+
+    let output = Command::new("git")
+        .args(["merge-file", "-p", "-q"])
+        .arg(ours_file.path())
+        .arg(base_file.path())
+        .arg(theirs_file.path())
+        .current_dir(root)
+        .output()
+        .context("Failed to execute git merge-file")?;
+
+    match output.status.code() {
+        Some(0) => Ok(Some(output.stdout)),
+        // A positive exit code is the number of conflicts.
+        Some(1..=127) => Ok(None),
+        _ => anyhow::bail!(
+            "git merge-file failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ),
+    }
+}
+
+/// Diffs two versions of a file without context, returning the changed ranges.
+fn diff_hunks(root: &Path, before: &[u8], after: &[u8]) -> Result<Vec<Hunk>> {
+    let before_file = temp_file(before)?;
+    let after_file = temp_file(after)?;
+
+    let output = Command::new("git")
+        .args([
+            "diff",
+            "--no-index",
+            "--no-color",
+            "--no-ext-diff",
+            "-U0",
+            "--",
+        ])
+        .arg(before_file.path())
+        .arg(after_file.path())
+        .current_dir(root)
+        .output()
+        .context("Failed to execute git diff")?;
+
+    Ok(parse_hunks(&String::from_utf8_lossy(&output.stdout)))
+}
+
+/// Parses the hunk headers of a diff made with `-U0`.
+fn parse_hunks(diff: &str) -> Vec<Hunk> {
+    let header = Regex::new(r"^@@ -(\d+)(?:,(\d+))? \+(\d+)(?:,(\d+))? @@").unwrap();
+
+    // A range of zero lines starts after the given line, others at it.
+    let range = |start: &str, len: Option<regex::Match>| {
+        let start: usize = start.parse().unwrap_or(0);
+        let len: usize = len.map_or(1, |l| l.as_str().parse().unwrap_or(0));
+        if len == 0 {
+            (start, 0)
+        } else {
+            (start.saturating_sub(1), len)
+        }
+    };
+
+    diff.lines()
+        .filter_map(|line| header.captures(line))
+        .map(|caps| {
+            let (old_start, old_len) = range(&caps[1], caps.get(2));
+            let (new_start, new_len) = range(&caps[3], caps.get(4));
+            Hunk {
+                old_start,
+                old_len,
+                new_start,
+                new_len,
+            }
+        })
+        .collect()
+}
+
+fn staged_files(root: &Path) -> Result<HashSet<String>> {
+    let output = Command::new("git")
+        .args(["diff", "--cached", "--name-only", "-z"])
+        .current_dir(root)
+        .output()
+        .context("Failed to execute git diff")?;
+
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .split('\0')
+        .filter(|f| !f.is_empty())
+        .map(|f| f.to_string())
+        .collect())
+}
+
+/// Reads files relative to `root`; missing files read as `None`.
+fn read_files(root: &Path, files: &[String]) -> Vec<Option<Vec<u8>>> {
+    files
+        .iter()
+        .map(|f| std::fs::read(root.join(f)).ok())
+        .collect()
+}
+
+fn temp_file(content: &[u8]) -> Result<tempfile::NamedTempFile> {
+    let mut file = tempfile::NamedTempFile::new().context("Failed to create a temporary file")?;
+    file.write_all(content)
+        .context("Failed to write a temporary file")?;
+    Ok(file)
+}
+
+fn run(command: &mut Command, package: &Path, description: &str) -> Result<()> {
+    let output = run_with_timeout(command.current_dir(package), None)
+        .with_context(|| format!("Failed to run {}", description))?;
+
+    if !output.status.success() {
+        anyhow::bail!(
+            "{} failed in {}: {}",
+            description.split(" (").next().unwrap_or(description),
+            package.display(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn git(dir: &Path, args: &[&str]) {
+        let status = Command::new("git")
+            .args(["-c", "user.name=Test", "-c", "user.email=test@example.com"])
+            .args(args)
+            .current_dir(dir)
+            .output()
+            .unwrap()
+            .status;
+        assert!(status.success(), "git {:?} failed", args);
+    }
+
+    fn staged(dir: &Path, file: &str) -> String {
+        String::from_utf8(get_staged_content(dir, file).unwrap().1).unwrap()
+    }
+
+    #[test]
+    fn test_restage_keeps_unstaged_changes_out_of_the_index() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        git(root, &["init", "-q"]);
+        std::fs::write(root.join("a.dart"), "a;\nb;\nc;\nd;\ne;\nf;\n").unwrap();
+        git(root, &["add", "."]);
+        git(root, &["commit", "-q", "-m", "init"]);
+
+        // The first line is staged, the added last line is not.
+        std::fs::write(root.join("a.dart"), "a  ;\nb;\nc;\nd;\ne;\nf;\n").unwrap();
+        git(root, &["add", "a.dart"]);
+
+        let original = b"a  ;\nb;\nc;\nd;\ne;\nf;\ng  ;\n";
+        let fixed = b"a;\nb;\nc;\nd;\ne;\nf;\ng;\n";
+        let hunks = diff_hunks(root, original, fixed).unwrap();
+        assert_eq!(hunks.len(), 2);
+
+        // Only the fix of the staged line is staged.
+        assert_eq!(restage(root, "a.dart", original, fixed, &hunks).unwrap(), 1);
+        assert_eq!(staged(root, "a.dart"), "a;\nb;\nc;\nd;\ne;\nf;\n");
+    }
+
+    #[test]
+    fn test_parse_hunks() {
+        let diff = "@@ -2 +2 @@\n-b\n+B\n@@ -3,0 +4,2 @@\n+d\n+e\n@@ -5,2 +6,0 @@\n-x\n-y\n";
+
+        assert_eq!(
+            parse_hunks(diff),
+            vec![
+                Hunk {
+                    old_start: 1,
+                    old_len: 1,
+                    new_start: 1,
+                    new_len: 1
+                },
+                Hunk {
+                    old_start: 3,
+                    old_len: 0,
+                    new_start: 3,
+                    new_len: 2
+                },
+                Hunk {
+                    old_start: 4,
+                    old_len: 2,
+                    new_start: 6,
+                    new_len: 0
+                },
+            ]
+        );
+    }
+}
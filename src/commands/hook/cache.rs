@@ -0,0 +1,466 @@
+use crate::utils::config::CONFIG_FILE_NAME;
+use crate::utils::dart::{find_file_package_root, path_dependencies};
+use crate::utils::git::{get_git_path, get_git_root, get_worktree_files, hash_files, hash_text};
+use crate::utils::shell::run_with_timeout;
+use anyhow::{Context, Result};
+use std::cell::RefCell;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::Duration;
+
+/// Location of the cache inside the git directory.
+pub const CACHE_GIT_PATH: &str = "mobdev/cache";
+
+/// How long a tool may take to print its version.
+const VERSION_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// File in the cache directory with the tool versions seen by the last runs
+/// that recorded a pass, keyed by version command.
+const VERSIONS_FILE: &str = "versions.json";
+
+/// What a check's result depends on, beyond the check itself.
+#[derive(Debug, Clone)]
+pub struct CacheSpec {
+    /// Command printing the version of the tool the check runs, if any.
+    pub version_command: &'static [&'static str],
+    /// Selects the changed files the check reads.
+    pub inputs: fn(&str) -> bool,
+    /// Selects the files of each package containing an input that the result
+    /// also depends on, for checks of a whole package such as analysis.
+    pub package_inputs: Option<fn(&str) -> bool>,
+    /// Files that configure the check in each package containing an input,
+    /// e.g. `analysis_options.yaml`.
+    pub package_config: &'static [&'static str],
+    /// Other files that configure the check, relative to the git root.
+    pub config_files: Vec<PathBuf>,
+}
+
+/// The cache key of a check run: a hash of the check, the tool version, the
+/// configuration and the content of every input file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CacheKey(String);
+
+/// Records of passing check runs, stored under `.git/mobdev/cache`.
+///
+/// A check is skipped when a run over the same inputs, with the same tool
+/// and configuration, has passed before.
+pub struct Cache {
+    root: PathBuf,
+    dir: PathBuf,
+    versions: RefCell<HashMap<&'static [&'static str], Option<String>>>,
+    /// Whether tool versions are asked from the tools, rather than read from
+    /// the versions recorded with the passes.
+    probe: bool,
+}
+
+impl Cache {
+    /// Opens the cache of the repository containing the current directory.
+    pub fn open() -> Result<Self> {
+        Self::open_in_mode(true)
+    }
+
+    /// Opens the cache without running any tool: keys use the tool versions
+    /// recorded by the last runs, as for `collate --explain`.
+    pub fn open_recorded() -> Result<Self> {
+        Self::open_in_mode(false)
+    }
+
+    fn open_in_mode(probe: bool) -> Result<Self> {
+        let root = get_git_root(None::<&str>)?;
+        let dir = get_git_path(&root, CACHE_GIT_PATH)?;
+        Ok(Self {
+            root,
+            dir,
+            versions: RefCell::default(),
+            probe,
+        })
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Computes the key of a check over the given changed files. Returns
+    /// `None` when the check has no changed inputs or its tool cannot be run,
+    /// in which case the check is not cached.
+    pub fn key(
+        &self,
+        check: &str,
+        spec: &CacheSpec,
+        changed: &[String],
+    ) -> Result<Option<CacheKey>> {
+        let mut inputs: BTreeSet<String> = changed
+            .iter()
+            .filter(|f| (spec.inputs)(f) && self.root.join(f).is_file())
+            .cloned()
+            .collect();
+
+        if inputs.is_empty() {
+            return Ok(None);
+        }
+
+        let packages: BTreeSet<PathBuf> = inputs
+            .iter()
+            .filter_map(|input| find_file_package_root(self.root.join(input)))
+            .map(|package| {
+                package
+                    .strip_prefix(&self.root)
+                    .map(Path::to_path_buf)
+                    .unwrap_or(package)
+            })
+            .collect();
+
+        // A whole-package check also depends on the packages used through
+        // `path:` dependencies, such as a shared design system.
+        let packages = if spec.package_inputs.is_some() {
+            with_path_dependencies(&self.root, packages)
+        } else {
+            packages
+        };
+
+        if let Some(package_inputs) = spec.package_inputs {
+            for file in get_worktree_files(&self.root)? {
+                if package_inputs(&file) && packages.iter().any(|p| Path::new(&file).starts_with(p))
+                {
+                    inputs.insert(file);
+                }
+            }
+        }
+
+        let Some(version) = self.version(spec.version_command) else {
+            return Ok(None);
+        };
+
+        let mut config_files: BTreeSet<PathBuf> = spec.config_files.iter().cloned().collect();
+        config_files.insert(PathBuf::from(CONFIG_FILE_NAME));
+        for package in &packages {
+            for name in spec.package_config {
+                config_files.insert(package.join(name));
+            }
+        }
+        let config_files: Vec<PathBuf> = config_files
+            .into_iter()
+            .filter(|f| self.root.join(f).is_file())
+            .collect();
+
+        let config_hashes = hash_files(&self.root, &config_files)?;
+        let mut text = format!("check {}\nversion {}\n", check, version);
+        for (file, hash) in config_files.iter().zip(&config_hashes) {
+            text.push_str(&format!("config {} {}\n", hash, file.display()));
+        }
+
+        let input_paths: Vec<PathBuf> = inputs.iter().map(PathBuf::from).collect();
+        for (file, hash) in inputs.iter().zip(hash_files(&self.root, &input_paths)?) {
+            text.push_str(&format!("input {} {}\n", hash, file));
+        }
+
+        Ok(Some(CacheKey(hash_text(&self.root, &text)?)))
+    }
+
+    /// Whether a pass has been recorded for the key.
+    pub fn is_hit(&self, key: &CacheKey) -> bool {
+        self.entry_path(key).is_file()
+    }
+
+    /// Records a pass for the key, and the tool versions it was computed with.
+    pub fn record(&self, key: &CacheKey) -> Result<()> {
+        std::fs::create_dir_all(&self.dir)
+            .with_context(|| format!("Failed to create {}", self.dir.display()))?;
+
+        let path = self.entry_path(key);
+        std::fs::write(&path, "").with_context(|| format!("Failed to write {}", path.display()))?;
+
+        let previous = self.recorded_versions();
+        let mut recorded = previous.clone();
+        for (command, version) in self.versions.borrow().iter() {
+            if let (false, Some(version)) = (command.is_empty(), version) {
+                recorded.insert(command.join(" "), version.clone());
+            }
+        }
+        if recorded == previous {
+            return Ok(());
+        }
+        let path = self.dir.join(VERSIONS_FILE);
+        std::fs::write(&path, serde_json::to_string_pretty(&recorded)?)
+            .with_context(|| format!("Failed to write {}", path.display()))
+    }
+
+    /// Removes every recorded pass, returning the number of cache files removed.
+    pub fn clear(&self) -> Result<usize> {
+        if !self.dir.exists() {
+            return Ok(0);
+        }
+
+        let count = std::fs::read_dir(&self.dir)
+            .with_context(|| format!("Failed to read {}", self.dir.display()))?
+            .count();
+        std::fs::remove_dir_all(&self.dir)
+            .with_context(|| format!("Failed to remove {}", self.dir.display()))?;
+
+        Ok(count)
+    }
+
+    fn entry_path(&self, key: &CacheKey) -> PathBuf {
+        self.dir.join(&key.0)
+    }
+
+    /// The version printed by a version command, run at most once per cache.
+    fn version(&self, command: &'static [&'static str]) -> Option<String> {
+        self.versions
+            .borrow_mut()
+            .entry(command)
+            .or_insert_with(|| {
+                if self.probe || command.is_empty() {
+                    tool_version(command)
+                } else {
+                    self.recorded_versions().remove(&command.join(" "))
+                }
+            })
+            .clone()
+    }
+
+    fn recorded_versions(&self) -> BTreeMap<String, String> {
+        std::fs::read_to_string(self.dir.join(VERSIONS_FILE))
+            .ok()
+            .and_then(|text| serde_json::from_str(&text).ok())
+            .unwrap_or_default()
+    }
+}
+
+/// Adds to `packages`, given relative to `root`, the packages of the
+/// repository they depend on through `path:` dependencies, directly or not.
+fn with_path_dependencies(root: &Path, packages: BTreeSet<PathBuf>) -> BTreeSet<PathBuf> {
+    let canonical_root = root.canonicalize().unwrap_or_else(|_| root.to_path_buf());
+    let mut pending: Vec<PathBuf> = packages.iter().cloned().collect();
+    let mut packages = packages;
+
+    while let Some(package) = pending.pop() {
+        for dependency in path_dependencies(&root.join(&package)) {
+            let Ok(dependency) = dependency.canonicalize() else {
+                continue;
+            };
+            let Ok(relative) = dependency.strip_prefix(&canonical_root) else {
+                continue;
+            };
+            if packages.insert(relative.to_path_buf()) {
+                pending.push(relative.to_path_buf());
+            }
+        }
+    }
+
+    packages
+}
+
+/// Runs a version command, returning its output, or `None` if it fails or
+/// does not finish in time.
+fn tool_version(command: &[&str]) -> Option<String> {
+    let Some((program, args)) = command.split_first() else {
+        return Some(String::new());
+    };
+    let output = run_with_timeout(Command::new(program).args(args), Some(VERSION_TIMEOUT)).ok()?;
+
+    if !output.status.success() {
+        return None;
+    }
+
+    Some(format!(
+        "{}{}",
+        String::from_utf8_lossy(&output.stdout).trim(),
+        String::from_utf8_lossy(&output.stderr).trim()
+    ))
+}
+
+/// Removes all recorded passes of the current repository.
+pub fn clear(verbose: bool) -> Result<()> {
+    let cache = Cache::open()?;
+    let count = cache.clear()?;
+
+    if verbose {
+        eprintln!(
+            "Removed {} cache file(s) from {}",
+            count,
+            cache.dir().display()
+        );
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn git(dir: &Path, args: &[&str]) {
+        let status = Command::new("git")
+            .args(args)
+            .current_dir(dir)
+            .output()
+            .unwrap()
+            .status;
+        assert!(status.success());
+    }
+
+    fn cache_in(dir: &Path) -> Cache {
+        git(dir, &["init", "-q"]);
+        Cache {
+            root: dir.to_path_buf(),
+            dir: get_git_path(dir, CACHE_GIT_PATH).unwrap(),
+            versions: RefCell::default(),
+            probe: true,
+        }
+    }
+
+    fn spec() -> CacheSpec {
+        CacheSpec {
+            version_command: &["git", "--version"],
+            inputs: |f| f.ends_with(".dart"),
+            package_inputs: None,
+            package_config: &["analysis_options.yaml"],
+            config_files: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_cache_records_passes_per_run() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = cache_in(dir.path());
+        std::fs::write(dir.path().join("pubspec.yaml"), "name: app\n").unwrap();
+        std::fs::write(dir.path().join("a.dart"), "a").unwrap();
+        std::fs::write(dir.path().join("b.dart"), "b").unwrap();
+
+        let key = |changed: &[&str]| {
+            let changed: Vec<String> = changed.iter().map(|f| f.to_string()).collect();
+            cache.key("check", &spec(), &changed).unwrap().unwrap()
+        };
+        let a = key(&["a.dart"]);
+        assert!(!cache.is_hit(&a));
+
+        cache.record(&a).unwrap();
+        assert!(cache.is_hit(&a));
+        assert_eq!(key(&["a.dart", "c.txt"]), a);
+
+        // Passes of separate runs do not add up to a pass of both files.
+        cache.record(&key(&["b.dart"])).unwrap();
+        assert!(!cache.is_hit(&key(&["a.dart", "b.dart"])));
+
+        // Changing the configuration or the check invalidates the passes.
+        std::fs::write(dir.path().join("analysis_options.yaml"), "linter:\n").unwrap();
+        assert!(!cache.is_hit(&key(&["a.dart"])));
+        assert!(!cache.is_hit(
+            &cache
+                .key("other", &spec(), &["a.dart".into()])
+                .unwrap()
+                .unwrap()
+        ));
+
+        // Two passes and the recorded tool versions.
+        assert_eq!(cache.clear().unwrap(), 3);
+        assert!(!cache.is_hit(&a));
+    }
+
+    #[test]
+    fn test_recorded_versions_need_no_tool() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = cache_in(dir.path());
+        std::fs::write(dir.path().join("a.dart"), "a").unwrap();
+        let recorded = || Cache {
+            probe: false,
+            ..cache_in(dir.path())
+        };
+
+        // Nothing recorded yet, so no key without running the tool.
+        assert!(recorded()
+            .key("check", &spec(), &["a.dart".into()])
+            .unwrap()
+            .is_none());
+
+        let key = cache
+            .key("check", &spec(), &["a.dart".into()])
+            .unwrap()
+            .unwrap();
+        cache.record(&key).unwrap();
+        let found = recorded()
+            .key("check", &spec(), &["a.dart".into()])
+            .unwrap()
+            .unwrap();
+        assert_eq!(found, key);
+        assert!(recorded().is_hit(&found));
+    }
+
+    #[test]
+    fn test_cache_key_covers_whole_package() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = cache_in(dir.path());
+        for package in ["app", "core", "ui", "other"] {
+            std::fs::create_dir_all(dir.path().join(package).join("lib")).unwrap();
+            std::fs::write(dir.path().join(package).join("lib/main.dart"), package).unwrap();
+        }
+        std::fs::write(
+            dir.path().join("app/pubspec.yaml"),
+            "name: app\ndependencies:\n  core:\n    path: ../core\n",
+        )
+        .unwrap();
+        std::fs::write(
+            dir.path().join("core/pubspec.yaml"),
+            "name: core\ndependencies:\n  ui:\n    path: ../ui\n",
+        )
+        .unwrap();
+        std::fs::write(dir.path().join("ui/pubspec.yaml"), "name: ui\n").unwrap();
+        std::fs::write(dir.path().join("other/pubspec.yaml"), "name: other\n").unwrap();
+        std::fs::write(dir.path().join("app/lib/b.dart"), "b").unwrap();
+
+        let spec = CacheSpec {
+            package_inputs: Some(|f| f.ends_with(".dart")),
+            package_config: &["pubspec.yaml", "pubspec.lock"],
+            ..spec()
+        };
+        let key = || {
+            cache
+                .key("check", &spec, &["app/lib/main.dart".into()])
+                .unwrap()
+                .unwrap()
+        };
+        let mut last = key();
+
+        // Unrelated packages do not matter.
+        std::fs::write(dir.path().join("other/lib/main.dart"), "changed").unwrap();
+        assert_eq!(key(), last);
+
+        // Unchanged files of the package, its lockfile, and the packages it
+        // uses through path dependencies, directly or not, do.
+        for file in [
+            "app/lib/b.dart",
+            "app/pubspec.lock",
+            "core/lib/main.dart",
+            "ui/lib/main.dart",
+            "ui/pubspec.yaml",
+        ] {
+            std::fs::write(dir.path().join(file), "changed").unwrap();
+            let changed = key();
+            assert_ne!(changed, last, "{}", file);
+            last = changed;
+        }
+    }
+
+    #[test]
+    fn test_cache_key_requires_inputs_and_tool() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = cache_in(dir.path());
+        std::fs::write(dir.path().join("a.dart"), "a").unwrap();
+
+        assert!(cache
+            .key("check", &spec(), &["deleted.dart".into()])
+            .unwrap()
+            .is_none());
+
+        let missing_tool = CacheSpec {
+            version_command: &["mobdev-missing-tool", "--version"],
+            ..spec()
+        };
+        assert!(cache
+            .key("check", &missing_tool, &["a.dart".into()])
+            .unwrap()
+            .is_none());
+    }
+}
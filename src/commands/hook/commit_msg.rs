@@ -0,0 +1,79 @@
+use crate::commands::git::commit_msg::validate;
+use crate::commands::hook::outcome::{Finding, Severity};
+use crate::commands::hook::CheckOutcome;
+use crate::utils::config::load_config;
+use crate::utils::git::{get_git_root, is_git_repo};
+use anyhow::{Context, Result};
+use std::path::Path;
+
+/// Line below which `git commit --verbose` appends the diff.
+const SCISSORS: &str = "# ------------------------ >8 ------------------------";
+
+/// Subject prefixes of messages written by git itself, which are not
+/// checked against the commit conventions.
+const GENERATED_PREFIXES: &[&str] = &["Merge ", "Revert \"", "fixup! ", "squash! ", "amend! "];
+
+/// Checks the commit message in `file`, as git passes it to commit-msg
+/// hooks, against the commit conventions of `mobdev.yaml`.
+pub fn check(file: &Path, verbose: bool) -> CheckOutcome {
+    CheckOutcome::run("commit message", || check_message(file, verbose))
+}
+
+fn check_message(file: &Path, verbose: bool) -> Result<Vec<Finding>> {
+    if !is_git_repo(None::<&str>) {
+        anyhow::bail!("Not in a git repository");
+    }
+
+    let root = get_git_root(None::<&str>)?;
+    let config = load_config(&root)?;
+    let contents = std::fs::read_to_string(file)
+        .with_context(|| format!("Failed to read {}", file.display()))?;
+    let message = strip_comments(&contents);
+
+    if message.is_empty() {
+        // git aborts the commit itself.
+        return Ok(Vec::new());
+    }
+    if GENERATED_PREFIXES.iter().any(|p| message.starts_with(p)) {
+        if verbose {
+            eprintln!("Skipping a message written by git");
+        }
+        return Ok(Vec::new());
+    }
+
+    Ok(validate(&message, &config.commit_msg)
+        .into_iter()
+        .map(|problem| Finding::new("commit-conventions", Severity::Error, problem))
+        .collect())
+}
+
+/// Removes what git strips from a message before committing: comment lines,
+/// everything below the scissors line and surrounding blank lines.
+fn strip_comments(contents: &str) -> String {
+    contents
+        .lines()
+        .take_while(|line| *line != SCISSORS)
+        .filter(|line| !line.starts_with('#'))
+        .collect::<Vec<_>>()
+        .join("\n")
+        .trim()
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_strip_comments() {
+        let contents = format!(
+            "\nfeat: add stash support\n\nBody.\n# Please enter the commit message\n{}\ndiff --git a/x b/x\n",
+            SCISSORS
+        );
+        assert_eq!(
+            strip_comments(&contents),
+            "feat: add stash support\n\nBody."
+        );
+        assert_eq!(strip_comments("# Only comments\n\n"), "");
+    }
+}
@@ -0,0 +1,236 @@
+use super::explain::CheckPlan;
+use super::outcome::{Finding, Severity};
+use super::CheckOutcome;
+use crate::utils::config::{CheckScope, CustomCheck};
+use crate::utils::dart::find_file_package_root;
+use crate::utils::git::{get_git_root, is_git_repo, ChangeScope};
+use crate::utils::glob::{compile, Glob};
+use crate::utils::shell::{escape_shell_arg, run_with_timeout};
+use anyhow::Result;
+use regex::Regex;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Runs a check declared in `mobdev.yaml`.
+pub fn check(definition: &CustomCheck, scope: &ChangeScope, verbose: bool) -> CheckOutcome {
+    CheckOutcome::run(&definition.name, || run_check(definition, scope, verbose))
+}
+
+fn run_check(definition: &CustomCheck, scope: &ChangeScope, verbose: bool) -> Result<Vec<Finding>> {
+    if !is_git_repo(None::<&str>) {
+        anyhow::bail!("Not in a git repository");
+    }
+
+    let root = get_git_root(None::<&str>)?;
+    let files = selected_files(&root, definition, scope)?;
+
+    if files.is_empty() {
+        if verbose {
+            eprintln!("No changed files match {}", definition.name);
+        }
+        return Ok(Vec::new());
+    }
+
+    let mut findings = Vec::new();
+
+    for (dir, files) in plan_runs(&root, &files, definition.run) {
+        let command = command_line(definition, &files);
+
+        if verbose {
+            eprintln!("Running in {}: {}", dir.display(), command);
+        }
+
+        // The check's timeout is applied by the runner, across all runs.
+        let output = run_with_timeout(
+            Command::new("sh").arg("-c").arg(&command).current_dir(&dir),
+            None,
+        )?;
+
+        if !output.status.success() {
+            let text = format!(
+                "{}{}",
+                String::from_utf8_lossy(&output.stdout),
+                String::from_utf8_lossy(&output.stderr)
+            );
+            let mut run_findings = parse_output(&text, &definition.name, &dir, &root);
+            if run_findings.is_empty() {
+                run_findings.push(Finding::new(
+                    definition.name.as_str(),
+                    Severity::Error,
+                    format!("`{}` failed with {}", command, output.status),
+                ));
+            }
+            findings.extend(run_findings);
+        }
+    }
+
+    Ok(findings)
+}
+
+/// Plans `check` for `collate --explain`.
+pub fn plan(definition: &CustomCheck, scope: &ChangeScope) -> Result<CheckPlan> {
+    if !is_git_repo(None::<&str>) {
+        anyhow::bail!("Not in a git repository");
+    }
+
+    let root = get_git_root(None::<&str>)?;
+    let files = selected_files(&root, definition, scope)?;
+    let mut plan = CheckPlan::for_files(&root, files.clone());
+
+    if files.is_empty() {
+        return Ok(plan.note("Nothing to check: no changed files match the globs"));
+    }
+
+    for (dir, files) in plan_runs(&root, &files, definition.run) {
+        plan = plan.shell(&dir, command_line(definition, &files));
+    }
+
+    Ok(plan)
+}
+
+/// Returns the changed files the check runs on.
+fn selected_files(
+    root: &Path,
+    definition: &CustomCheck,
+    scope: &ChangeScope,
+) -> Result<Vec<String>> {
+    let include = compile(&definition.include)?;
+    let exclude = compile(&definition.exclude)?;
+
+    Ok(scope
+        .changed_files(Some(root))?
+        .into_iter()
+        .filter(|f| is_selected(f, &include, &exclude) && root.join(f).is_file())
+        .collect())
+}
+
+/// Fills in `{files}` in the check's command.
+fn command_line(definition: &CustomCheck, files: &[String]) -> String {
+    let args: Vec<String> = files.iter().map(|f| escape_shell_arg(f)).collect();
+    definition.command.replace("{files}", &args.join(" "))
+}
+
+/// Whether a file matches the include globs (all files if there are none)
+/// and none of the exclude globs.
+fn is_selected(file: &str, include: &[Glob], exclude: &[Glob]) -> bool {
+    (include.is_empty() || include.iter().any(|g| g.is_match(file)))
+        && !exclude.iter().any(|g| g.is_match(file))
+}
+
+/// Splits files, given relative to `root`, into command runs. Each run is a
+/// directory to run from and the files relative to it.
+fn plan_runs(root: &Path, files: &[String], scope: CheckScope) -> Vec<(PathBuf, Vec<String>)> {
+    match scope {
+        CheckScope::PerFile => files
+            .iter()
+            .map(|f| (root.to_path_buf(), vec![f.clone()]))
+            .collect(),
+        CheckScope::PerPackage => {
+            let mut runs: BTreeMap<PathBuf, Vec<String>> = BTreeMap::new();
+            for file in files {
+                let path = root.join(file);
+                let dir = find_file_package_root(&path).unwrap_or_else(|| root.to_path_buf());
+                let relative = path.strip_prefix(&dir).unwrap_or(&path);
+                runs.entry(dir)
+                    .or_default()
+                    .push(relative.display().to_string());
+            }
+            runs.into_iter().collect()
+        }
+    }
+}
+
+/// Turns the output of a failed command into findings. Lines shaped like
+/// `file:line[:column]: message` are located, with paths made relative to
+/// `root`; other non-empty lines become unlocated findings.
+fn parse_output(output: &str, rule: &str, dir: &Path, root: &Path) -> Vec<Finding> {
+    let located = Regex::new(r"^([^:\s][^:]*):(\d+)(?::(\d+))?:\s*(.*)$").unwrap();
+
+    output
+        .lines()
+        .map(str::trim_end)
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| match located.captures(line) {
+            Some(caps) => {
+                let path = dir.join(&caps[1]);
+                let path = path.strip_prefix(root).unwrap_or(&path);
+                Finding::new(rule, Severity::Error, &caps[4]).at(
+                    path.display().to_string(),
+                    caps[2].parse().ok(),
+                    caps.get(3).and_then(|c| c.as_str().parse().ok()),
+                )
+            }
+            None => Finding::new(rule, Severity::Error, line),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_selected() {
+        let include = compile(&["*.dart".to_string()]).unwrap();
+        let exclude = compile(&["*.g.dart".to_string()]).unwrap();
+
+        assert!(is_selected("app/lib/a.dart", &include, &exclude));
+        assert!(!is_selected("app/lib/a.g.dart", &include, &exclude));
+        assert!(!is_selected("README.md", &include, &exclude));
+        assert!(is_selected("README.md", &[], &[]));
+    }
+
+    #[test]
+    fn test_plan_runs_per_package() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        std::fs::create_dir_all(root.join("app/lib")).unwrap();
+        std::fs::write(root.join("app/pubspec.yaml"), "name: app\n").unwrap();
+
+        let files = vec![
+            "app/lib/a.dart".to_string(),
+            "app/lib/b.dart".to_string(),
+            "tool/c.sh".to_string(),
+        ];
+
+        assert_eq!(
+            plan_runs(root, &files, CheckScope::PerPackage),
+            vec![
+                (root.to_path_buf(), vec!["tool/c.sh".to_string()]),
+                (
+                    root.join("app"),
+                    vec!["lib/a.dart".to_string(), "lib/b.dart".to_string()]
+                ),
+            ]
+        );
+        assert_eq!(plan_runs(root, &files, CheckScope::PerFile).len(), 3);
+    }
+
+    #[test]
+    fn test_parse_output() {
+        let findings = parse_output(
+            "lib/a.dart:3:5: avoid print\nlib/b.dart:7: todo left\n\n2 problems\n",
+            "no-print",
+            Path::new("/repo/app"),
+            Path::new("/repo"),
+        );
+
+        assert_eq!(
+            findings,
+            vec![
+                Finding::new("no-print", Severity::Error, "avoid print").at(
+                    "app/lib/a.dart",
+                    Some(3),
+                    Some(5)
+                ),
+                Finding::new("no-print", Severity::Error, "todo left").at(
+                    "app/lib/b.dart",
+                    Some(7),
+                    None
+                ),
+                Finding::new("no-print", Severity::Error, "2 problems"),
+            ]
+        );
+    }
+}
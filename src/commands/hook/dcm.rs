@@ -1,4 +1,13 @@
-use anyhow::Result;
+use super::explain::CheckPlan;
+use super::outcome::{Finding, Severity};
+use super::{changed_dart_files, plan_dart_check, CheckOutcome};
+use crate::utils::dart::group_by_package;
+use crate::utils::git::{get_git_root, is_git_repo, ChangeScope};
+use crate::utils::shell::run_with_timeout;
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::path::Path;
+use std::process::Command;
 
 pub fn fix_check(
     _staged: bool,
@@ -6,26 +15,219 @@ pub fn fix_check(
     _all: bool,
     base_branch: &str,
     verbose: bool,
-) -> Result<()> {
+) -> CheckOutcome {
+    CheckOutcome::run("DCM fix", || {
+        if verbose {
+            eprintln!("DCM fix check not yet fully implemented");
+            eprintln!("Base branch: {}", base_branch);
+        }
+
+        anyhow::bail!("DCM fix check not yet implemented");
+    })
+}
+
+pub fn analyze_check(scope: &ChangeScope, verbose: bool) -> CheckOutcome {
+    CheckOutcome::run("DCM analyze", || analyze(scope, verbose))
+}
+
+/// Plans `analyze_check` for `collate --explain`.
+pub fn analyze_plan(scope: &ChangeScope) -> Result<CheckPlan> {
+    plan_dart_check(scope, analyze_command)
+}
+
+/// Builds the `dcm analyze` command for files of a package.
+fn analyze_command(root: &Path, package: &Path, files: &[String]) -> Command {
+    let mut command = Command::new("dcm");
+    command
+        .arg("analyze")
+        .arg("--reporter=gitlab")
+        .args(files.iter().map(|f| root.join(f)))
+        .current_dir(package);
+    command
+}
+
+/// Runs `dcm analyze` on the changed Dart files of each package and returns
+/// the reported issues.
+fn analyze(scope: &ChangeScope, verbose: bool) -> Result<Vec<Finding>> {
+    if !is_git_repo(None::<&str>) {
+        anyhow::bail!("Not in a git repository");
+    }
+
+    let root = get_git_root(None::<&str>)?;
+    let files = changed_dart_files(&root, scope)?;
+
+    if files.is_empty() {
+        if verbose {
+            eprintln!("No changed Dart files");
+        }
+        return Ok(Vec::new());
+    }
+
+    let mut findings = Vec::new();
+
+    for (package, files) in group_by_package(&root, &files) {
+        if verbose {
+            eprintln!(
+                "Running DCM on {} file(s) in {}",
+                files.len(),
+                package.display()
+            );
+        }
+
+        let output = run_with_timeout(&mut analyze_command(&root, &package, &files), None)
+            .context("Failed to run dcm analyze (is DCM installed?)")?;
+
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let package_findings = match parse_gitlab_report(&stdout, &package, &root) {
+            Ok(package_findings) => package_findings,
+            Err(e) => anyhow::bail!(
+                "dcm analyze failed in {}: {} ({})",
+                package.display(),
+                stderr.trim(),
+                e
+            ),
+        };
+
+        // DCM exits non-zero when it finds issues, but also when it cannot
+        // analyze at all, e.g. without a license or with a bad config.
+        if !output.status.success() && package_findings.is_empty() {
+            anyhow::bail!(
+                "dcm analyze failed in {}: {}",
+                package.display(),
+                stderr.trim()
+            );
+        }
+
+        findings.extend(package_findings);
+    }
+
     if verbose {
-        eprintln!("DCM fix check not yet fully implemented");
-        eprintln!("Base branch: {}", base_branch);
+        if findings.is_empty() {
+            eprintln!("✓ No DCM issues");
+        } else {
+            eprintln!("{} DCM issue(s) found", findings.len());
+        }
     }
 
-    anyhow::bail!("DCM fix check not yet implemented");
+    Ok(findings)
 }
 
-pub fn analyze_check(
-    _staged: bool,
-    _unstaged: bool,
-    _all: bool,
-    base_branch: &str,
-    verbose: bool,
-) -> Result<()> {
-    if verbose {
-        eprintln!("DCM analyze check not yet fully implemented");
-        eprintln!("Base branch: {}", base_branch);
+/// An issue in DCM's GitLab (Code Climate) report.
+#[derive(Deserialize)]
+struct GitlabIssue {
+    check_name: String,
+    description: String,
+    severity: String,
+    location: GitlabLocation,
+}
+
+#[derive(Deserialize)]
+struct GitlabLocation {
+    path: String,
+    lines: Option<GitlabLines>,
+    positions: Option<GitlabPositions>,
+}
+
+#[derive(Deserialize)]
+struct GitlabLines {
+    begin: usize,
+}
+
+#[derive(Deserialize)]
+struct GitlabPositions {
+    begin: GitlabPosition,
+}
+
+#[derive(Deserialize)]
+struct GitlabPosition {
+    line: usize,
+    column: Option<usize>,
+}
+
+/// Parses the output of `dcm analyze --reporter=gitlab`. Paths in the report
+/// are relative to `package` and are made relative to `root`.
+fn parse_gitlab_report(output: &str, package: &Path, root: &Path) -> Result<Vec<Finding>> {
+    let output = output.trim();
+    if output.is_empty() {
+        return Ok(Vec::new());
     }
 
-    anyhow::bail!("DCM analyze check not yet implemented");
+    let issues: Vec<GitlabIssue> =
+        serde_json::from_str(output).context("Unexpected dcm analyze output")?;
+
+    Ok(issues
+        .into_iter()
+        .map(|issue| {
+            let severity = match issue.severity.as_str() {
+                "blocker" | "critical" => Severity::Error,
+                "info" => Severity::Info,
+                _ => Severity::Warning,
+            };
+
+            let (line, column) = match (&issue.location.positions, &issue.location.lines) {
+                (Some(positions), _) => (Some(positions.begin.line), positions.begin.column),
+                (None, Some(lines)) => (Some(lines.begin), None),
+                (None, None) => (None, None),
+            };
+
+            let path = package.join(&issue.location.path);
+            let path = path.strip_prefix(root).unwrap_or(&path);
+
+            Finding::new(issue.check_name, severity, issue.description).at(
+                path.display().to_string(),
+                line,
+                column,
+            )
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_gitlab_report() {
+        let output = r#"[
+            {
+                "type": "issue",
+                "check_name": "avoid-unused-parameters",
+                "description": "Parameter is unused.",
+                "severity": "minor",
+                "fingerprint": "abc",
+                "location": { "path": "lib/a.dart", "lines": { "begin": 12 } }
+            },
+            {
+                "type": "issue",
+                "check_name": "no-empty-block",
+                "description": "Block is empty.",
+                "severity": "critical",
+                "fingerprint": "def",
+                "location": {
+                    "path": "lib/b.dart",
+                    "positions": { "begin": { "line": 3, "column": 7 } }
+                }
+            }
+        ]"#;
+
+        let findings =
+            parse_gitlab_report(output, Path::new("/repo/app"), Path::new("/repo")).unwrap();
+
+        assert_eq!(
+            findings[0],
+            Finding::new(
+                "avoid-unused-parameters",
+                Severity::Warning,
+                "Parameter is unused."
+            )
+            .at("app/lib/a.dart", Some(12), None)
+        );
+        assert_eq!(findings[1].severity, Severity::Error);
+        assert_eq!(findings[1].column, Some(7));
+        assert!(parse_gitlab_report("", Path::new("/"), Path::new("/"))
+            .unwrap()
+            .is_empty());
+        assert!(parse_gitlab_report("not json", Path::new("/"), Path::new("/")).is_err());
+    }
 }
@@ -0,0 +1,188 @@
+use crate::utils::dart::group_by_package;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::Duration;
+
+/// What a check would do, printed by `collate --explain`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CheckPlan {
+    /// Changed files the check would read, relative to the git root.
+    pub files: Vec<String>,
+    /// Packages containing the files, relative to the git root.
+    pub packages: Vec<String>,
+    /// Commands the check would run, with the directory each runs in.
+    pub commands: Vec<(PathBuf, String)>,
+    /// Anything else worth knowing, e.g. why the check would do nothing.
+    pub notes: Vec<String>,
+}
+
+impl CheckPlan {
+    /// A plan over `files`, with the Dart packages containing them.
+    pub fn for_files(root: &Path, files: Vec<String>) -> Self {
+        let packages = group_by_package(root, &files)
+            .into_keys()
+            .map(|package| relative(root, &package))
+            .collect();
+
+        Self {
+            files,
+            packages,
+            ..Self::default()
+        }
+    }
+
+    /// Adds a command run from `dir`.
+    pub fn command(mut self, dir: &Path, command: &Command) -> Self {
+        self.commands
+            .push((dir.to_path_buf(), describe_command(command)));
+        self
+    }
+
+    /// Adds a shell command line run from `dir`.
+    pub fn shell(mut self, dir: &Path, command: impl Into<String>) -> Self {
+        self.commands.push((dir.to_path_buf(), command.into()));
+        self
+    }
+
+    pub fn note(mut self, note: impl Into<String>) -> Self {
+        self.notes.push(note.into());
+        self
+    }
+}
+
+/// How a check would be scheduled, beyond what the check itself plans.
+pub struct Schedule {
+    pub timeout: Option<Duration>,
+    pub cached: bool,
+    /// Why the check would be skipped, if it would be.
+    pub skipped: Option<String>,
+}
+
+/// Renders the plan of one check.
+pub fn render(
+    name: &str,
+    plan: &anyhow::Result<CheckPlan>,
+    schedule: &Schedule,
+    root: &Path,
+) -> String {
+    let mut text = format!("{}\n", name);
+
+    let plan = match plan {
+        Ok(plan) => plan,
+        Err(e) => {
+            text.push_str(&format!("  Cannot run: {:#}\n", e));
+            return text;
+        }
+    };
+
+    if let Some(message) = &schedule.skipped {
+        text.push_str(&format!("  {}: the check would not run\n", message));
+    }
+
+    if schedule.cached {
+        text.push_str("  Cached: every input passed before, so the check would be skipped\n");
+    }
+
+    text.push_str(&format!("  Files ({}):\n", plan.files.len()));
+    for file in &plan.files {
+        text.push_str(&format!("    {}\n", file));
+    }
+
+    if !plan.packages.is_empty() {
+        let packages: Vec<&str> = plan
+            .packages
+            .iter()
+            .map(|p| if p.is_empty() { "." } else { p.as_str() })
+            .collect();
+        text.push_str(&format!("  Packages: {}\n", packages.join(", ")));
+    }
+
+    if !plan.commands.is_empty() {
+        text.push_str("  Commands:\n");
+        for (dir, command) in &plan.commands {
+            let dir = relative(root, dir);
+            if dir.is_empty() {
+                text.push_str(&format!("    {}\n", command));
+            } else {
+                text.push_str(&format!("    (in {}) {}\n", dir, command));
+            }
+        }
+    }
+
+    if let Some(timeout) = schedule.timeout {
+        text.push_str(&format!("  Timeout: {}s\n", timeout.as_secs()));
+    }
+
+    for note in &plan.notes {
+        text.push_str(&format!("  {}\n", note));
+    }
+
+    text
+}
+
+/// Renders a command as a shell command line.
+pub fn describe_command(command: &Command) -> String {
+    std::iter::once(command.get_program())
+        .chain(command.get_args())
+        .map(|arg| quote(&arg.to_string_lossy()))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Quotes a word for the shell, unless it only contains safe characters.
+fn quote(word: &str) -> String {
+    let safe = !word.is_empty()
+        && word
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "._/=:,@+-".contains(c));
+
+    if safe {
+        word.to_string()
+    } else {
+        crate::utils::shell::escape_shell_arg(word)
+    }
+}
+
+fn relative(root: &Path, path: &Path) -> String {
+    path.strip_prefix(root)
+        .unwrap_or(path)
+        .display()
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_describe_command() {
+        let mut command = Command::new("dart");
+        command.args(["format", "--output=none", "lib/my file.dart", "it's"]);
+
+        assert_eq!(
+            describe_command(&command),
+            "dart format --output=none 'lib/my file.dart' 'it'\\''s'"
+        );
+    }
+
+    #[test]
+    fn test_render() {
+        let root = Path::new("/repo");
+        let plan = CheckPlan {
+            files: vec!["app/lib/a.dart".to_string()],
+            packages: vec!["app".to_string()],
+            commands: vec![(root.join("app"), "dart analyze lib/a.dart".to_string())],
+            notes: Vec::new(),
+        };
+        let schedule = Schedule {
+            timeout: Some(Duration::from_secs(60)),
+            cached: false,
+            skipped: None,
+        };
+
+        assert_eq!(
+            render("dart analysis", &Ok(plan), &schedule, root),
+            "dart analysis\n  Files (1):\n    app/lib/a.dart\n  Packages: app\n  Commands:\n    (in app) dart analyze lib/a.dart\n  Timeout: 60s\n"
+        );
+    }
+}
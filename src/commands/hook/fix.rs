@@ -1,4 +1,4 @@
-use anyhow::Result;
+use super::CheckOutcome;
 
 pub fn check(
     _staged: bool,
@@ -6,12 +6,14 @@ pub fn check(
     _all: bool,
     base_branch: &str,
     verbose: bool,
-) -> Result<()> {
-    if verbose {
-        eprintln!("Dart fix check not yet fully implemented");
-        eprintln!("Base branch: {}", base_branch);
-    }
+) -> CheckOutcome {
+    CheckOutcome::run("dart fix", || {
+        if verbose {
+            eprintln!("Dart fix check not yet fully implemented");
+            eprintln!("Base branch: {}", base_branch);
+        }
 
-    // Placeholder - would check if Dart files pass dart fix
-    anyhow::bail!("Fix check not yet implemented");
+        // Placeholder - would check if Dart files pass dart fix
+        anyhow::bail!("Fix check not yet implemented");
+    })
 }
@@ -1,17 +1,131 @@
-use anyhow::Result;
-
-pub fn check(
-    _staged: bool,
-    _unstaged: bool,
-    _all: bool,
-    base_branch: &str,
-    verbose: bool,
-) -> Result<()> {
+use super::explain::CheckPlan;
+use super::outcome::{Finding, Severity};
+use super::{changed_dart_files, plan_dart_check, CheckOutcome};
+use crate::utils::dart::group_by_package;
+use crate::utils::git::{get_git_root, is_git_repo, ChangeScope};
+use crate::utils::shell::run_with_timeout;
+use anyhow::{Context, Result};
+use std::path::Path;
+use std::process::Command;
+
+pub fn check(scope: &ChangeScope, verbose: bool) -> CheckOutcome {
+    CheckOutcome::run("dart format", || check_formatting(scope, verbose))
+}
+
+/// Plans `check` for `collate --explain`.
+pub fn plan(scope: &ChangeScope) -> Result<CheckPlan> {
+    plan_dart_check(scope, command)
+}
+
+/// Builds the `dart format` command checking files of a package.
+fn command(root: &Path, package: &Path, files: &[String]) -> Command {
+    let mut command = Command::new("dart");
+    command
+        .arg("format")
+        .arg("--output=none")
+        .arg("--set-exit-if-changed")
+        .args(files.iter().map(|f| root.join(f)))
+        .current_dir(package);
+    command
+}
+
+/// Runs `dart format` without writing on the changed Dart files of each
+/// package and reports the files it would change.
+fn check_formatting(scope: &ChangeScope, verbose: bool) -> Result<Vec<Finding>> {
+    if !is_git_repo(None::<&str>) {
+        anyhow::bail!("Not in a git repository");
+    }
+
+    let root = get_git_root(None::<&str>)?;
+    let files = changed_dart_files(&root, scope)?;
+
+    if files.is_empty() {
+        if verbose {
+            eprintln!("No changed Dart files");
+        }
+        return Ok(Vec::new());
+    }
+
+    let mut findings = Vec::new();
+
+    for (package, files) in group_by_package(&root, &files) {
+        if verbose {
+            eprintln!(
+                "Checking formatting of {} file(s) in {}",
+                files.len(),
+                package.display()
+            );
+        }
+
+        let output = run_with_timeout(&mut command(&root, &package, &files), None)
+            .context("Failed to run dart format (is the Dart SDK installed?)")?;
+
+        // Exit code 1 means some files would change.
+        if !matches!(output.status.code(), Some(0) | Some(1)) {
+            anyhow::bail!(
+                "dart format failed in {}: {}",
+                package.display(),
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+        }
+
+        findings.extend(parse_changed(
+            &String::from_utf8_lossy(&output.stdout),
+            &package,
+            &root,
+        ));
+    }
+
     if verbose {
-        eprintln!("Dart format check not yet fully implemented");
-        eprintln!("Base branch: {}", base_branch);
+        if findings.is_empty() {
+            eprintln!("✓ All files formatted");
+        } else {
+            eprintln!("{} file(s) need formatting", findings.len());
+        }
     }
 
-    // Placeholder - would check if Dart files are properly formatted
-    anyhow::bail!("Format check not yet implemented");
+    Ok(findings)
+}
+
+/// Turns the `Changed <path>` lines of `dart format` into findings, with
+/// paths made relative to `root`.
+fn parse_changed(output: &str, package: &Path, root: &Path) -> Vec<Finding> {
+    output
+        .lines()
+        .filter_map(|line| line.strip_prefix("Changed "))
+        .map(|path| {
+            let path = package.join(path.trim());
+            let path = path.strip_prefix(root).unwrap_or(&path);
+            Finding::new(
+                "dart format",
+                Severity::Error,
+                "File is not formatted; run `dart format`",
+            )
+            .at(path.display().to_string(), None, None)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_changed() {
+        let output = "Changed /repo/app/lib/a.dart\nChanged lib/b.dart\nFormatted 3 files (2 changed) in 0.05 seconds.\n";
+
+        let files: Vec<Option<String>> =
+            parse_changed(output, Path::new("/repo/app"), Path::new("/repo"))
+                .into_iter()
+                .map(|f| f.file)
+                .collect();
+
+        assert_eq!(
+            files,
+            vec![
+                Some("app/lib/a.dart".to_string()),
+                Some("app/lib/b.dart".to_string())
+            ]
+        );
+    }
 }
@@ -1,17 +1,444 @@
-use anyhow::Result;
-
-pub fn check(
-    _staged: bool,
-    _unstaged: bool,
-    _all: bool,
-    base_branch: &str,
-    verbose: bool,
-) -> Result<()> {
+use super::explain::CheckPlan;
+use super::outcome::{Finding, Severity};
+use super::CheckOutcome;
+use crate::utils::config::{load_config, CONFIG_FILE_NAME};
+use crate::utils::dart::{find_file_package_root, group_by_package};
+use crate::utils::files::list_files;
+use crate::utils::git::{
+    get_git_root, get_staged_content, get_tracked_files, is_git_repo, ChangeScope,
+};
+use crate::utils::shell::run_with_timeout;
+use anyhow::{Context, Result};
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::Duration;
+
+/// Environment variable holding the directory regenerated fakes are written to.
+pub const OUTPUT_ENV_VAR: &str = "MOBDEV_GRAPHQL_OUTPUT";
+
+/// Name of the check, in `collate` and its reports.
+pub const CHECK_NAME: &str = "GraphQL";
+
+pub fn check(scope: &ChangeScope, verbose: bool) -> CheckOutcome {
+    CheckOutcome::run(CHECK_NAME, || stale_fakes(scope, verbose))
+}
+
+/// Plans `check` for `collate --explain`.
+pub fn plan(scope: &ChangeScope) -> Result<CheckPlan> {
+    if !is_git_repo(None::<&str>) {
+        anyhow::bail!("Not in a git repository");
+    }
+
+    let root = get_git_root(None::<&str>)?;
+    let graphql_files = changed_graphql_files(&root, scope)?;
+    let mut plan = CheckPlan::for_files(&root, graphql_files.clone());
+
+    if graphql_files.is_empty() {
+        return Ok(plan.note("Nothing to check: no changed GraphQL files"));
+    }
+
+    let command = fakes_command(&root)?;
+    let packages = group_by_package(&root, &graphql_files);
+    for package in packages.keys() {
+        plan = plan.shell(
+            package,
+            expand_command(&command, package, Path::new("<temporary directory>")),
+        );
+    }
+    for file in &graphql_files {
+        if !packages.values().any(|f| f.contains(file)) {
+            plan = plan.note(format!("Skipped {}: not in a Dart package", file));
+        }
+    }
+
+    Ok(plan.note(format!(
+        "Fakes are regenerated into a temporary directory and compared with the ones in {}",
+        if checks_index(scope) {
+            "the index"
+        } else {
+            "the working tree"
+        }
+    )))
+}
+
+/// Whether the fakes being committed are the staged ones, rather than those
+/// of the working tree.
+fn checks_index(scope: &ChangeScope) -> bool {
+    scope.staged && !scope.all
+}
+
+/// Returns the changed GraphQL files.
+fn changed_graphql_files(root: &Path, scope: &ChangeScope) -> Result<Vec<String>> {
+    Ok(scope
+        .changed_files(Some(root))?
+        .into_iter()
+        .filter(|f| is_graphql_file(f))
+        .collect())
+}
+
+/// Returns the configured fakes command.
+fn fakes_command(root: &Path) -> Result<String> {
+    load_config(root)?.graphql.command.with_context(|| {
+        format!(
+            "No GraphQL fakes command configured (set graphql.command in {})",
+            CONFIG_FILE_NAME
+        )
+    })
+}
+
+/// Regenerates the fakes of every package with changed GraphQL files and
+/// returns a finding for each stale or orphaned fake.
+fn stale_fakes(scope: &ChangeScope, verbose: bool) -> Result<Vec<Finding>> {
+    if !is_git_repo(None::<&str>) {
+        anyhow::bail!("Not in a git repository");
+    }
+
+    let root = get_git_root(None::<&str>)?;
+    let graphql_files = changed_graphql_files(&root, scope)?;
+
+    if graphql_files.is_empty() {
+        if verbose {
+            eprintln!("No changed GraphQL files");
+        }
+        return Ok(Vec::new());
+    }
+
+    let command = fakes_command(&root)?;
+    let timeout = load_config(&root)?.collate.check_timeout(CHECK_NAME);
+    let index = if checks_index(scope) {
+        Some(get_tracked_files(&root)?.into_iter().collect())
+    } else {
+        None
+    };
+
+    let mut packages = BTreeSet::new();
+    for file in &graphql_files {
+        match find_file_package_root(root.join(file)) {
+            Some(package) => {
+                packages.insert(package);
+            }
+            None => {
+                if verbose {
+                    eprintln!("Skipping {} (not in a Dart package)", file);
+                }
+            }
+        }
+    }
+
+    let mut stale = Vec::new();
+
+    for package in &packages {
+        if verbose {
+            eprintln!("Regenerating GraphQL fakes in {}", package.display());
+        }
+
+        let output = tempfile::tempdir().context("Failed to create temporary directory")?;
+        regenerate_fakes(&command, package, output.path(), timeout)?;
+
+        let committed = match &index {
+            Some(files) => Committed::Index {
+                root: &root,
+                package: package.strip_prefix(&root).unwrap_or(package),
+                files,
+            },
+            None => Committed::WorkingTree(package),
+        };
+        let relative = |fake: &Path| {
+            let path = package.join(fake);
+            path.strip_prefix(&root)
+                .unwrap_or(&path)
+                .display()
+                .to_string()
+        };
+        for fake in find_stale_fakes(&committed, output.path())? {
+            stale.push(
+                Finding::new("stale-fake", Severity::Error, "GraphQL fake is stale").at(
+                    relative(&fake),
+                    None,
+                    None,
+                ),
+            );
+        }
+        for fake in find_orphan_fakes(&committed, output.path())? {
+            stale.push(
+                Finding::new(
+                    "orphan-fake",
+                    Severity::Error,
+                    "GraphQL fake is no longer generated; delete it",
+                )
+                .at(relative(&fake), None, None),
+            );
+        }
+    }
+
     if verbose {
-        eprintln!("GraphQL check not yet fully implemented");
-        eprintln!("Base branch: {}", base_branch);
+        if stale.is_empty() {
+            eprintln!("✓ GraphQL fakes are up to date");
+        } else {
+            eprintln!(
+                "{} GraphQL fake(s) are stale; regenerate them with: {}",
+                stale.len(),
+                command
+            );
+        }
+    }
+
+    Ok(stale)
+}
+
+/// Checks if a file is a GraphQL schema or operation file.
+pub fn is_graphql_file(file: &str) -> bool {
+    file.ends_with(".graphql")
+}
+
+/// Fills in the `{output}` and `{package}` placeholders of the fakes command.
+fn expand_command(command: &str, package: &Path, output: &Path) -> String {
+    command
+        .replace("{output}", &output.to_string_lossy())
+        .replace("{package}", &package.to_string_lossy())
+}
+
+/// Runs the configured fakes command for a package, writing into `output`.
+fn regenerate_fakes(
+    command: &str,
+    package: &Path,
+    output: &Path,
+    timeout: Option<Duration>,
+) -> Result<()> {
+    let command = expand_command(command, package, output);
+
+    let result = run_with_timeout(
+        Command::new("sh")
+            .arg("-c")
+            .arg(&command)
+            .current_dir(package)
+            .env(OUTPUT_ENV_VAR, output),
+        timeout,
+    )
+    .with_context(|| format!("Failed to run GraphQL fakes command: {}", command))?;
+
+    if !result.status.success() {
+        anyhow::bail!(
+            "GraphQL fakes command failed in {}: {}",
+            package.display(),
+            String::from_utf8_lossy(&result.stderr).trim()
+        );
+    }
+
+    Ok(())
+}
+
+/// The fakes of a package being committed: the files of the index with
+/// `--staged`, so that a fake fixed but left unstaged still counts as stale,
+/// and of the working tree otherwise.
+enum Committed<'a> {
+    WorkingTree(&'a Path),
+    Index {
+        root: &'a Path,
+        /// The package, relative to `root`.
+        package: &'a Path,
+        /// Every file in the index, relative to `root`.
+        files: &'a BTreeSet<String>,
+    },
+}
+
+impl Committed<'_> {
+    /// Reads a file given relative to the package, if it is committed.
+    fn read(&self, relative: &Path) -> Option<Vec<u8>> {
+        match self {
+            Committed::WorkingTree(package) => std::fs::read(package.join(relative)).ok(),
+            Committed::Index {
+                root,
+                package,
+                files,
+            } => {
+                let path = package.join(relative).to_string_lossy().replace('\\', "/");
+                if !files.contains(&path) {
+                    return None;
+                }
+                get_staged_content(root, &path)
+                    .ok()
+                    .map(|(_, content)| content)
+            }
+        }
     }
 
-    // Placeholder - would check if GraphQL fakes are up to date
-    anyhow::bail!("GraphQL check not yet implemented");
+    /// Lists the files directly in a directory given relative to the
+    /// package, relative to the package.
+    fn files_in(&self, dir: &Path) -> Result<Vec<PathBuf>> {
+        match self {
+            Committed::WorkingTree(package) => {
+                let Ok(entries) = std::fs::read_dir(package.join(dir)) else {
+                    return Ok(Vec::new());
+                };
+                let mut files = Vec::new();
+                for entry in entries {
+                    let entry = entry?;
+                    if entry.file_type()?.is_file() {
+                        files.push(dir.join(entry.file_name()));
+                    }
+                }
+                Ok(files)
+            }
+            Committed::Index { package, files, .. } => {
+                let dir_in_root = package.join(dir);
+                Ok(files
+                    .iter()
+                    .map(Path::new)
+                    .filter(|file| file.parent() == Some(dir_in_root.as_path()))
+                    .filter_map(|file| file.strip_prefix(package).ok())
+                    .map(Path::to_path_buf)
+                    .collect())
+            }
+        }
+    }
+}
+
+/// Compares every regenerated file in `output` with the committed file at the
+/// same path relative to the package, returning the relative paths that
+/// differ.
+fn find_stale_fakes(committed: &Committed, output: &Path) -> Result<Vec<PathBuf>> {
+    let mut stale = Vec::new();
+
+    for generated in list_files(output, &[])? {
+        let relative = generated.strip_prefix(output)?.to_path_buf();
+        let expected = std::fs::read(&generated)
+            .with_context(|| format!("Failed to read {}", generated.display()))?;
+
+        if committed.read(&relative).as_ref() != Some(&expected) {
+            stale.push(relative);
+        }
+    }
+
+    stale.sort();
+    Ok(stale)
+}
+
+/// Finds the committed fakes that are no longer generated, e.g. because
+/// their operation was deleted: the committed files in a directory the
+/// command wrote into, with the compound suffix of a file it wrote there
+/// (such as `.fake.dart`), that it did not write. Returns paths relative to
+/// the package.
+fn find_orphan_fakes(committed: &Committed, output: &Path) -> Result<Vec<PathBuf>> {
+    let mut generated: BTreeSet<PathBuf> = BTreeSet::new();
+    let mut suffixes: BTreeSet<(PathBuf, String)> = BTreeSet::new();
+    for file in list_files(output, &[])? {
+        let relative = file.strip_prefix(output)?.to_path_buf();
+        let dir = relative.parent().unwrap_or(Path::new("")).to_path_buf();
+        if let Some(suffix) = fake_suffix(&relative) {
+            suffixes.insert((dir, suffix.to_string()));
+        }
+        generated.insert(relative);
+    }
+
+    let mut orphans = Vec::new();
+    let dirs: BTreeSet<&PathBuf> = suffixes.iter().map(|(dir, _)| dir).collect();
+    for dir in dirs {
+        for relative in committed.files_in(dir)? {
+            let orphan = !generated.contains(&relative)
+                && fake_suffix(&relative)
+                    .is_some_and(|s| suffixes.contains(&(dir.clone(), s.to_string())));
+            if orphan {
+                orphans.push(relative);
+            }
+        }
+    }
+
+    orphans.sort();
+    Ok(orphans)
+}
+
+/// The part of a file name from its first dot, when it has two or more
+/// extensions: `.fake.dart` for `user.fake.dart`, none for `user.dart`.
+fn fake_suffix(path: &Path) -> Option<&str> {
+    let name = path.file_name()?.to_str()?;
+    let suffix = &name[name.find('.')?..];
+    (suffix.matches('.').count() >= 2).then_some(suffix)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_graphql_file() {
+        assert!(is_graphql_file("lib/schema.graphql"));
+        assert!(!is_graphql_file("lib/schema.graphql.dart"));
+    }
+
+    #[test]
+    fn test_find_stale_fakes() {
+        let package = tempfile::tempdir().unwrap();
+        let output = tempfile::tempdir().unwrap();
+
+        std::fs::create_dir_all(package.path().join("lib")).unwrap();
+        std::fs::create_dir_all(output.path().join("lib")).unwrap();
+
+        std::fs::write(package.path().join("lib/same.fake.dart"), "a").unwrap();
+        std::fs::write(output.path().join("lib/same.fake.dart"), "a").unwrap();
+        std::fs::write(package.path().join("lib/changed.fake.dart"), "a").unwrap();
+        std::fs::write(output.path().join("lib/changed.fake.dart"), "b").unwrap();
+        std::fs::write(output.path().join("lib/new.fake.dart"), "c").unwrap();
+
+        std::fs::write(package.path().join("lib/deleted.fake.dart"), "d").unwrap();
+        std::fs::write(package.path().join("lib/main.dart"), "e").unwrap();
+        std::fs::write(package.path().join("lib/model.g.dart"), "f").unwrap();
+
+        let committed = Committed::WorkingTree(package.path());
+        let stale = find_stale_fakes(&committed, output.path()).unwrap();
+        assert_eq!(
+            stale,
+            vec![
+                PathBuf::from("lib/changed.fake.dart"),
+                PathBuf::from("lib/new.fake.dart"),
+            ]
+        );
+
+        // Only files like the generated ones, in the same directories.
+        let orphans = find_orphan_fakes(&committed, output.path()).unwrap();
+        assert_eq!(orphans, vec![PathBuf::from("lib/deleted.fake.dart")]);
+    }
+
+    #[test]
+    fn test_committed_index() {
+        let repo = tempfile::tempdir().unwrap();
+        let root = repo.path();
+        let git = |args: &[&str]| {
+            let status = Command::new("git")
+                .args(args)
+                .current_dir(root)
+                .status()
+                .unwrap();
+            assert!(status.success(), "git {:?} failed", args);
+        };
+        git(&["init", "-q"]);
+        std::fs::create_dir_all(root.join("app/lib")).unwrap();
+        std::fs::write(root.join("app/lib/a.fake.dart"), "staged").unwrap();
+        std::fs::write(root.join("app/lib/b.fake.dart"), "staged").unwrap();
+        git(&["add", "."]);
+        std::fs::write(root.join("app/lib/a.fake.dart"), "unstaged").unwrap();
+        std::fs::remove_file(root.join("app/lib/b.fake.dart")).unwrap();
+        std::fs::write(root.join("app/lib/c.fake.dart"), "untracked").unwrap();
+
+        let files = get_tracked_files(root).unwrap().into_iter().collect();
+        let committed = Committed::Index {
+            root,
+            package: Path::new("app"),
+            files: &files,
+        };
+
+        assert_eq!(
+            committed.read(Path::new("lib/a.fake.dart")),
+            Some(b"staged".to_vec())
+        );
+        assert_eq!(committed.read(Path::new("lib/c.fake.dart")), None);
+        assert_eq!(
+            committed.files_in(Path::new("lib")).unwrap(),
+            vec![
+                PathBuf::from("lib/a.fake.dart"),
+                PathBuf::from("lib/b.fake.dart"),
+            ]
+        );
+    }
 }
@@ -0,0 +1,363 @@
+use crate::utils::config::{load_config, HooksConfig};
+use crate::utils::git::{get_git_path, get_git_root, is_git_repo};
+use anyhow::{Context, Result};
+use std::path::{Path, PathBuf};
+
+/// Git hooks managed by `hook install`, with the command each runs when
+/// `mobdev.yaml` does not set one. post-checkout has no default: it runs on
+/// every checkout, and what it should do (bootstrapping packages,
+/// regenerating code) is specific to each repository.
+const HOOKS: &[(&str, Option<&str>)] = &[
+    (
+        "pre-commit",
+        Some("mobdev hook collate --staged --snapshot"),
+    ),
+    ("pre-push", Some("mobdev hook pre-push \"$@\"")),
+    ("commit-msg", Some("mobdev hook commit-msg \"$1\"")),
+    ("post-checkout", None),
+];
+
+/// The command configured for each managed hook, if any.
+type HookCommands = Vec<(&'static str, Option<String>)>;
+
+/// Marks hook scripts written by mobdev.
+const MARKER: &str = "# Installed by mobdev hook install";
+
+/// Suffix of the backup of a hook that mobdev replaced.
+const BACKUP_SUFFIX: &str = ".mobdev-backup";
+
+/// Runs the backup of a replaced hook, replaying standard input (the pushed
+/// refs of a pre-push hook) to the command that follows.
+const CHAIN_BACKUP: &str = r#"if [ -x "$0.mobdev-backup" ]; then
+  stdin=$(mktemp) || exit 1
+  trap 'rm -f "$stdin"' EXIT
+  cat > "$stdin"
+  "$0.mobdev-backup" "$@" < "$stdin" || exit $?
+  exec < "$stdin"
+fi
+"#;
+
+/// State of an installed hook compared to the configuration.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HookState {
+    /// Configured but not installed.
+    Missing,
+    /// Installed and up to date.
+    Installed,
+    /// Installed by mobdev, but differs from the configuration.
+    OutOfDate,
+    /// Not configured and not installed.
+    NotConfigured,
+    /// A hook not written by mobdev.
+    Foreign,
+}
+
+impl std::fmt::Display for HookState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            HookState::Missing => write!(f, "not installed"),
+            HookState::Installed => write!(f, "installed"),
+            HookState::OutOfDate => write!(f, "out of date"),
+            HookState::NotConfigured => write!(f, "not configured"),
+            HookState::Foreign => write!(f, "foreign hook"),
+        }
+    }
+}
+
+/// Installs the configured hooks, or removes them with `uninstall`.
+pub fn install(uninstall: bool, verbose: bool) -> Result<()> {
+    let (hooks_dir, commands) = hooks_and_commands()?;
+
+    if verbose {
+        eprintln!("Hooks directory: {}", hooks_dir.display());
+    }
+
+    for (hook, command) in commands {
+        let path = hooks_dir.join(hook);
+        match command {
+            Some(command) if !uninstall => install_hook(&path, hook, &command, verbose)?,
+            _ => remove_hook(&path, hook, verbose)?,
+        }
+    }
+
+    Ok(())
+}
+
+/// Prints the state of each managed hook.
+pub fn status(verbose: bool) -> Result<()> {
+    let (hooks_dir, commands) = hooks_and_commands()?;
+
+    if verbose {
+        eprintln!("Hooks directory: {}", hooks_dir.display());
+    }
+
+    for (hook, command) in commands {
+        let path = hooks_dir.join(hook);
+        let state = hook_state(&path, hook, command.as_deref());
+        if backup_path(&path).exists() {
+            println!("{}: {} (foreign hook backed up)", hook, state);
+        } else {
+            println!("{}: {}", hook, state);
+        }
+    }
+
+    Ok(())
+}
+
+/// Returns the hooks directory and the command configured for each hook.
+fn hooks_and_commands() -> Result<(PathBuf, HookCommands)> {
+    if !is_git_repo(None::<&str>) {
+        anyhow::bail!("Not in a git repository");
+    }
+
+    let root = get_git_root(None::<&str>)?;
+    let config = load_config(&root)?;
+    // Honors core.hooksPath, and resolves to the shared hooks in worktrees.
+    let hooks_dir = get_git_path(&root, "hooks")?;
+
+    Ok((hooks_dir, hook_commands(&config.hooks)))
+}
+
+/// Resolves the command of each managed hook. An empty command disables a hook.
+fn hook_commands(config: &HooksConfig) -> HookCommands {
+    HOOKS
+        .iter()
+        .map(|(hook, default)| {
+            let configured = match *hook {
+                "pre-commit" => &config.pre_commit,
+                "pre-push" => &config.pre_push,
+                "commit-msg" => &config.commit_msg,
+                _ => &config.post_checkout,
+            };
+            let command = match configured {
+                Some(command) => Some(command.trim().to_string()).filter(|c| !c.is_empty()),
+                None => default.map(str::to_string),
+            };
+            (*hook, command)
+        })
+        .collect()
+}
+
+/// Renders the script of a hook running `command`. A backed-up foreign hook
+/// runs first, with the same arguments and standard input, and stops the
+/// hook when it fails.
+pub fn hook_script(hook: &str, command: &str) -> String {
+    format!(
+        "#!/bin/sh\n{}; re-run it after changing mobdev.yaml.\n# Hook: {}\n{}{}\n",
+        MARKER, hook, CHAIN_BACKUP, command
+    )
+}
+
+fn hook_state(path: &Path, hook: &str, command: Option<&str>) -> HookState {
+    let Ok(contents) = std::fs::read_to_string(path) else {
+        return if path.exists() {
+            HookState::Foreign
+        } else if command.is_some() {
+            HookState::Missing
+        } else {
+            HookState::NotConfigured
+        };
+    };
+
+    if !contents.contains(MARKER) {
+        return HookState::Foreign;
+    }
+
+    match command {
+        Some(command) if contents == hook_script(hook, command) => HookState::Installed,
+        _ => HookState::OutOfDate,
+    }
+}
+
+fn backup_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(BACKUP_SUFFIX);
+    path.with_file_name(name)
+}
+
+fn install_hook(path: &Path, hook: &str, command: &str, verbose: bool) -> Result<()> {
+    if hook_state(path, hook, Some(command)) == HookState::Foreign {
+        let backup = backup_path(path);
+        if backup.exists() {
+            anyhow::bail!(
+                "Cannot back up {}: {} already exists",
+                path.display(),
+                backup.display()
+            );
+        }
+        std::fs::rename(path, &backup)
+            .with_context(|| format!("Failed to back up {}", path.display()))?;
+        if verbose {
+            eprintln!("Backed up {} to {}", hook, backup.display());
+        }
+    }
+
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)
+            .with_context(|| format!("Failed to create {}", dir.display()))?;
+    }
+    std::fs::write(path, hook_script(hook, command))
+        .with_context(|| format!("Failed to write {}", path.display()))?;
+    make_executable(path)?;
+
+    if verbose {
+        eprintln!("Installed {}: {}", hook, command);
+    }
+
+    Ok(())
+}
+
+/// Removes a hook installed by mobdev and restores the hook it replaced.
+/// Foreign hooks are left alone.
+fn remove_hook(path: &Path, hook: &str, verbose: bool) -> Result<()> {
+    if !path.exists() || hook_state(path, hook, None) == HookState::Foreign {
+        return Ok(());
+    }
+
+    std::fs::remove_file(path).with_context(|| format!("Failed to remove {}", path.display()))?;
+    if verbose {
+        eprintln!("Removed {}", hook);
+    }
+
+    let backup = backup_path(path);
+    if backup.exists() {
+        std::fs::rename(&backup, path)
+            .with_context(|| format!("Failed to restore {}", backup.display()))?;
+        if verbose {
+            eprintln!("Restored the previous {} hook", hook);
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(unix)]
+fn make_executable(path: &Path) -> Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+
+    std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o755))
+        .with_context(|| format!("Failed to make {} executable", path.display()))
+}
+
+#[cfg(not(unix))]
+fn make_executable(_path: &Path) -> Result<()> {
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hook_commands() {
+        let config = HooksConfig {
+            pre_push: Some(String::new()),
+            post_checkout: Some("melos bootstrap".to_string()),
+            ..HooksConfig::default()
+        };
+
+        assert_eq!(
+            hook_commands(&config),
+            vec![
+                (
+                    "pre-commit",
+                    Some("mobdev hook collate --staged --snapshot".to_string())
+                ),
+                ("pre-push", None),
+                (
+                    "commit-msg",
+                    Some("mobdev hook commit-msg \"$1\"".to_string())
+                ),
+                ("post-checkout", Some("melos bootstrap".to_string())),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_install_backs_up_and_restores_foreign_hooks() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("hooks/pre-commit");
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(&path, "#!/bin/sh\nmake lint\n").unwrap();
+        assert_eq!(
+            hook_state(&path, "pre-commit", Some("a")),
+            HookState::Foreign
+        );
+
+        install_hook(&path, "pre-commit", "a", false).unwrap();
+        assert_eq!(
+            hook_state(&path, "pre-commit", Some("a")),
+            HookState::Installed
+        );
+        assert_eq!(
+            hook_state(&path, "pre-commit", Some("b")),
+            HookState::OutOfDate
+        );
+        assert_eq!(hook_state(&path, "pre-commit", None), HookState::OutOfDate);
+
+        // Reinstalling replaces our own hook without touching the backup.
+        install_hook(&path, "pre-commit", "b", false).unwrap();
+        assert_eq!(
+            std::fs::read_to_string(backup_path(&path)).unwrap(),
+            "#!/bin/sh\nmake lint\n"
+        );
+
+        remove_hook(&path, "pre-commit", false).unwrap();
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            "#!/bin/sh\nmake lint\n"
+        );
+        assert!(!backup_path(&path).exists());
+
+        // Foreign hooks are never removed.
+        remove_hook(&path, "pre-commit", false).unwrap();
+        assert!(path.exists());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_installed_hook_runs_backed_up_hook_first() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("pre-push");
+        let log = dir.path().join("log");
+        std::fs::write(
+            &path,
+            format!(
+                "#!/bin/sh\necho \"backup $1 $(cat)\" >> {}\nexit \"$FAIL\"\n",
+                log.display()
+            ),
+        )
+        .unwrap();
+        make_executable(&path).unwrap();
+
+        let command = format!("echo \"mobdev $1 $(cat)\" >> {}", log.display());
+        install_hook(&path, "pre-push", &command, false).unwrap();
+
+        let run = |fail: &str| {
+            use std::io::Write;
+
+            let mut child = std::process::Command::new(&path)
+                .arg("origin")
+                .env("FAIL", fail)
+                .stdin(std::process::Stdio::piped())
+                .spawn()
+                .unwrap();
+            child.stdin.take().unwrap().write_all(b"refs").unwrap();
+            child.wait().unwrap()
+        };
+
+        assert!(run("0").success());
+        assert_eq!(
+            std::fs::read_to_string(&log).unwrap(),
+            "backup origin refs\nmobdev origin refs\n"
+        );
+
+        // A failing backup fails the hook without running mobdev.
+        std::fs::remove_file(&log).unwrap();
+        assert_eq!(run("3").code(), Some(3));
+        assert_eq!(
+            std::fs::read_to_string(&log).unwrap(),
+            "backup origin refs\n"
+        );
+    }
+}
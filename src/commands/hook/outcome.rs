@@ -0,0 +1,364 @@
+use super::annotations::{github_annotations, write_step_summary, Annotations};
+use super::report::{write_report, ReportSpec};
+use crate::utils::shell::Interrupted;
+use anyhow::Result;
+use serde::Serialize;
+use std::time::{Duration, Instant};
+
+/// Final status of a hook check.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CheckStatus {
+    /// The check ran and found no problems.
+    Passed,
+    /// The check ran and found problems.
+    Failed,
+    /// The check could not run, e.g. a tool is missing.
+    Error,
+    /// The check ran past its timeout and was stopped.
+    Timeout,
+    /// The check was stopped or not started because another check failed
+    /// with `--fail-fast`.
+    Cancelled,
+    /// The check was not run because it was asked to be skipped.
+    Skipped,
+}
+
+impl std::fmt::Display for CheckStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CheckStatus::Passed => write!(f, "passed"),
+            CheckStatus::Failed => write!(f, "failed"),
+            CheckStatus::Error => write!(f, "error"),
+            CheckStatus::Timeout => write!(f, "timeout"),
+            CheckStatus::Cancelled => write!(f, "cancelled"),
+            CheckStatus::Skipped => write!(f, "skipped"),
+        }
+    }
+}
+
+/// Severity of a finding. Only `Info` findings let a check pass.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Info,
+    Warning,
+    Error,
+}
+
+/// A problem reported by a check, optionally located in a file.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Finding {
+    /// Path relative to the git root.
+    pub file: Option<String>,
+    pub line: Option<usize>,
+    pub column: Option<usize>,
+    pub rule: String,
+    pub severity: Severity,
+    pub message: String,
+}
+
+impl Finding {
+    pub fn new(rule: impl Into<String>, severity: Severity, message: impl Into<String>) -> Self {
+        Self {
+            file: None,
+            line: None,
+            column: None,
+            rule: rule.into(),
+            severity,
+            message: message.into(),
+        }
+    }
+
+    /// Sets the file, and optionally the line and column, of the finding.
+    pub fn at(
+        mut self,
+        file: impl Into<String>,
+        line: Option<usize>,
+        column: Option<usize>,
+    ) -> Self {
+        self.file = Some(file.into());
+        self.line = line;
+        self.column = column;
+        self
+    }
+}
+
+impl std::fmt::Display for Finding {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(file) = &self.file {
+            write!(f, "{}", file)?;
+            if let Some(line) = self.line {
+                write!(f, ":{}", line)?;
+                if let Some(column) = self.column {
+                    write!(f, ":{}", column)?;
+                }
+            }
+            write!(f, ": ")?;
+        }
+        write!(f, "{}", self.message)
+    }
+}
+
+/// The result of running a single hook check.
+#[derive(Debug, Clone)]
+pub struct CheckOutcome {
+    pub name: String,
+    pub status: CheckStatus,
+    /// Problems found by the check.
+    pub findings: Vec<Finding>,
+    /// Notes about the run, such as the error that stopped the check.
+    pub messages: Vec<String>,
+    pub duration: Duration,
+    /// Whether the result was taken from the cache instead of running the check.
+    pub cached: bool,
+}
+
+impl CheckOutcome {
+    /// Runs a check and times it. The check returns the findings it produced;
+    /// it fails if any finding is a warning or an error, and an error means
+    /// the check could not run, unless a command it ran was interrupted.
+    pub fn run<F>(name: &str, check: F) -> Self
+    where
+        F: FnOnce() -> Result<Vec<Finding>>,
+    {
+        let start = Instant::now();
+        let result = check();
+        let duration = start.elapsed();
+
+        let (status, findings, messages) = match result {
+            Ok(findings) if findings.iter().all(|f| f.severity == Severity::Info) => {
+                (CheckStatus::Passed, findings, Vec::new())
+            }
+            Ok(findings) => (CheckStatus::Failed, findings, Vec::new()),
+            Err(e) => match e.chain().find_map(|c| c.downcast_ref::<Interrupted>()) {
+                Some(reason @ Interrupted::TimedOut(_)) => {
+                    (CheckStatus::Timeout, Vec::new(), vec![reason.to_string()])
+                }
+                Some(reason @ Interrupted::Cancelled) => {
+                    (CheckStatus::Cancelled, Vec::new(), vec![reason.to_string()])
+                }
+                None => (CheckStatus::Error, Vec::new(), vec![format!("{:#}", e)]),
+            },
+        };
+
+        Self {
+            name: name.to_string(),
+            status,
+            findings,
+            messages,
+            duration,
+            cached: false,
+        }
+    }
+
+    /// The outcome of a check skipped because its pass is cached.
+    pub fn cached(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            status: CheckStatus::Passed,
+            findings: Vec::new(),
+            messages: Vec::new(),
+            duration: Duration::ZERO,
+            cached: true,
+        }
+    }
+
+    /// The outcome of a check not started because another check failed.
+    pub fn cancelled(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            status: CheckStatus::Cancelled,
+            findings: Vec::new(),
+            messages: vec![Interrupted::Cancelled.to_string()],
+            duration: Duration::ZERO,
+            cached: false,
+        }
+    }
+
+    /// The outcome of a check not run because it was asked to be skipped,
+    /// with a message recording who asked and why.
+    pub fn skipped(name: &str, message: String) -> Self {
+        Self {
+            name: name.to_string(),
+            status: CheckStatus::Skipped,
+            findings: Vec::new(),
+            messages: vec![message],
+            duration: Duration::ZERO,
+            cached: false,
+        }
+    }
+
+    /// Whether the check did not fail the run. Skipped checks count as
+    /// successes.
+    pub fn is_success(&self) -> bool {
+        matches!(self.status, CheckStatus::Passed | CheckStatus::Skipped)
+    }
+}
+
+/// Prints the outcomes of one or more checks, writes the requested reports,
+/// and turns the outcomes into a result.
+///
+/// Findings of failed checks go to stdout, one per line, and errors go to
+/// stderr. With `verbose`, a per-check summary is printed to stderr. With
+/// GitHub annotations, each check's output is wrapped in a log group followed
+/// by its workflow commands, and a summary is added to the job's step summary.
+pub fn finish(
+    outcomes: &[CheckOutcome],
+    reports: &[ReportSpec],
+    annotations: Option<Annotations>,
+    verbose: bool,
+) -> Result<()> {
+    let github = Annotations::resolve(annotations) == Annotations::Github;
+
+    for outcome in outcomes {
+        if github {
+            println!("::group::{} ({})", outcome.name, outcome.status);
+        }
+
+        match outcome.status {
+            CheckStatus::Passed => {}
+            CheckStatus::Failed => {
+                for finding in &outcome.findings {
+                    println!("{}", finding);
+                }
+            }
+            CheckStatus::Error
+            | CheckStatus::Timeout
+            | CheckStatus::Cancelled
+            | CheckStatus::Skipped => {
+                for message in &outcome.messages {
+                    eprintln!("{}: {}", outcome.name, message);
+                }
+            }
+        }
+
+        if github {
+            println!("::endgroup::");
+            for annotation in github_annotations(outcome) {
+                println!("{}", annotation);
+            }
+        }
+    }
+
+    if github {
+        write_step_summary(outcomes)?;
+    }
+
+    for report in reports {
+        write_report(report, outcomes)?;
+        if verbose {
+            eprintln!(
+                "Wrote {} report to {}",
+                report.format,
+                report.path.display()
+            );
+        }
+    }
+
+    if verbose && outcomes.len() > 1 {
+        eprintln!("Summary:");
+        for outcome in outcomes {
+            let mark = match outcome.status {
+                CheckStatus::Skipped => "-",
+                _ if outcome.is_success() => "✓",
+                _ => "✗",
+            };
+            if outcome.cached {
+                eprintln!("  {} {} ({}, cached)", mark, outcome.name, outcome.status);
+            } else {
+                eprintln!(
+                    "  {} {} ({}, {:.2}s)",
+                    mark,
+                    outcome.name,
+                    outcome.status,
+                    outcome.duration.as_secs_f64()
+                );
+            }
+        }
+    }
+
+    // Cancelled checks are not failures of their own.
+    let failed: Vec<&str> = outcomes
+        .iter()
+        .filter(|o| !o.is_success() && o.status != CheckStatus::Cancelled)
+        .map(|o| o.name.as_str())
+        .collect();
+
+    match failed.as_slice() {
+        [] if outcomes.iter().any(|o| o.status == CheckStatus::Cancelled) => {
+            anyhow::bail!("Checks were cancelled")
+        }
+        [] => {
+            if verbose {
+                eprintln!("All checks passed ✓");
+            }
+            Ok(())
+        }
+        [name] if outcomes.len() == 1 => anyhow::bail!("{} check failed", name),
+        _ => anyhow::bail!("Failed checks: {}", failed.join(", ")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_run_status() {
+        assert_eq!(
+            CheckOutcome::run("a", || Ok(Vec::new())).status,
+            CheckStatus::Passed
+        );
+        assert_eq!(
+            CheckOutcome::run("b", || Ok(vec![Finding::new("r", Severity::Warning, "w")])).status,
+            CheckStatus::Failed
+        );
+        assert_eq!(
+            CheckOutcome::run("c", || Ok(vec![Finding::new("r", Severity::Info, "i")])).status,
+            CheckStatus::Passed
+        );
+
+        let outcome = CheckOutcome::run("d", || anyhow::bail!("dart not found"));
+        assert_eq!(outcome.status, CheckStatus::Error);
+        assert_eq!(outcome.messages, vec!["dart not found"]);
+
+        let outcome = CheckOutcome::run("e", || {
+            Err(
+                anyhow::Error::new(Interrupted::TimedOut(Duration::from_secs(5)))
+                    .context("Failed to run dcm analyze"),
+            )
+        });
+        assert_eq!(outcome.status, CheckStatus::Timeout);
+        assert_eq!(outcome.messages, vec!["Timed out after 5s"]);
+    }
+
+    #[test]
+    fn test_finding_display() {
+        let finding = Finding::new("unused_import", Severity::Warning, "Unused import");
+        assert_eq!(finding.to_string(), "Unused import");
+        assert_eq!(
+            finding
+                .clone()
+                .at("lib/a.dart", Some(3), Some(8))
+                .to_string(),
+            "lib/a.dart:3:8: Unused import"
+        );
+        assert_eq!(
+            finding.at("lib/a.dart", None, None).to_string(),
+            "lib/a.dart: Unused import"
+        );
+    }
+
+    #[test]
+    fn test_finish_reports_all_failures() {
+        let outcomes = vec![
+            CheckOutcome::run("a", || Ok(Vec::new())),
+            CheckOutcome::run("b", || anyhow::bail!("boom")),
+            CheckOutcome::run("c", || Ok(vec![Finding::new("r", Severity::Error, "e")])),
+        ];
+
+        let error = finish(&outcomes, &[], Some(Annotations::None), false).unwrap_err();
+        assert_eq!(error.to_string(), "Failed checks: b, c");
+    }
+}
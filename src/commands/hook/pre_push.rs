@@ -0,0 +1,84 @@
+use crate::utils::git::{
+    get_default_remote_branch, get_git_root, get_pushed_files, get_pushed_ranges, is_git_repo,
+    parse_pushed_refs,
+};
+use anyhow::{Context, Result};
+use std::io::{IsTerminal, Read};
+
+/// What a push changes.
+#[derive(Debug, Default)]
+pub struct PushedChanges {
+    /// Files changed by the pushed commits.
+    pub files: Vec<String>,
+    /// `git log` revision arguments selecting the pushed commits.
+    pub ranges: Vec<String>,
+    /// Remote refs being updated, e.g. `refs/heads/main`.
+    pub branches: Vec<String>,
+}
+
+/// Reads the refs being pushed from stdin, as git passes them to pre-push
+/// hooks, and returns what the pushed commits change.
+///
+/// New branches are compared with their merge-base with the default branch
+/// of `remote`, or with `base_branch` if the remote has none.
+pub fn pushed_changes(
+    remote: Option<&str>,
+    base_branch: &str,
+    verbose: bool,
+) -> Result<PushedChanges> {
+    if !is_git_repo(None::<&str>) {
+        anyhow::bail!("Not in a git repository");
+    }
+
+    let stdin = std::io::stdin();
+    if stdin.is_terminal() {
+        anyhow::bail!(
+            "hook pre-push reads the pushed refs from stdin; run it from a pre-push hook"
+        );
+    }
+
+    let mut input = String::new();
+    stdin
+        .lock()
+        .read_to_string(&mut input)
+        .context("Failed to read pushed refs from stdin")?;
+
+    let root = get_git_root(None::<&str>)?;
+    let refs = parse_pushed_refs(&input)?;
+    let remote = remote.unwrap_or("origin");
+    let default_branch =
+        get_default_remote_branch(&root, remote).unwrap_or_else(|| base_branch.to_string());
+
+    if verbose {
+        for pushed in &refs {
+            if pushed.is_deletion() {
+                eprintln!("Skipping deletion of {}", pushed.remote_ref);
+            } else if pushed.is_new() {
+                eprintln!(
+                    "Pushing new {} (compared with {})",
+                    pushed.remote_ref, default_branch
+                );
+            } else {
+                eprintln!("Pushing {} to {}", pushed.local_ref, pushed.remote_ref);
+            }
+        }
+    }
+
+    let files = get_pushed_files(&root, &refs, Some(&default_branch))?;
+    let ranges = get_pushed_ranges(&root, &refs, Some(&default_branch));
+    let branches = refs
+        .iter()
+        .filter(|r| !r.is_deletion())
+        .map(|r| r.remote_ref.clone())
+        .collect();
+
+    if verbose {
+        eprintln!("{} file(s) changed by the push", files.len());
+    }
+
+    Ok(PushedChanges {
+        files,
+        ranges,
+        branches,
+    })
+}
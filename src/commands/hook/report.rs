@@ -0,0 +1,322 @@
+use super::outcome::{CheckOutcome, CheckStatus, Severity};
+use anyhow::{Context, Result};
+use serde_json::json;
+use std::collections::BTreeSet;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+/// Machine-readable report formats for hook checks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReportFormat {
+    Json,
+    Junit,
+    Sarif,
+}
+
+impl std::fmt::Display for ReportFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ReportFormat::Json => write!(f, "json"),
+            ReportFormat::Junit => write!(f, "junit"),
+            ReportFormat::Sarif => write!(f, "sarif"),
+        }
+    }
+}
+
+/// A report to write, given on the command line as `<format>=<path>`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReportSpec {
+    pub format: ReportFormat,
+    pub path: PathBuf,
+}
+
+impl FromStr for ReportSpec {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (format, path) = s
+            .split_once('=')
+            .ok_or_else(|| format!("expected <format>=<path>, got \"{}\"", s))?;
+
+        let format = match format {
+            "json" => ReportFormat::Json,
+            "junit" => ReportFormat::Junit,
+            "sarif" => ReportFormat::Sarif,
+            other => {
+                return Err(format!(
+                    "unknown report format \"{}\" (expected json, junit or sarif)",
+                    other
+                ))
+            }
+        };
+
+        if path.is_empty() {
+            return Err("report path must not be empty".to_string());
+        }
+
+        Ok(Self {
+            format,
+            path: PathBuf::from(path),
+        })
+    }
+}
+
+/// Writes the outcomes of a run in the requested format.
+pub fn write_report(spec: &ReportSpec, outcomes: &[CheckOutcome]) -> Result<()> {
+    let contents = match spec.format {
+        ReportFormat::Json => to_json(outcomes),
+        ReportFormat::Junit => to_junit(outcomes),
+        ReportFormat::Sarif => to_sarif(outcomes),
+    };
+
+    if let Some(parent) = spec.path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        std::fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create {}", parent.display()))?;
+    }
+
+    std::fs::write(&spec.path, contents)
+        .with_context(|| format!("Failed to write report {}", spec.path.display()))
+}
+
+/// Renders a JSON report with the status, duration and findings of each check.
+pub fn to_json(outcomes: &[CheckOutcome]) -> String {
+    let status = if outcomes.iter().all(|o| o.is_success()) {
+        CheckStatus::Passed
+    } else {
+        CheckStatus::Failed
+    };
+
+    let checks: Vec<_> = outcomes
+        .iter()
+        .map(|o| {
+            json!({
+                "name": o.name,
+                "status": o.status,
+                "duration_ms": o.duration.as_millis() as u64,
+                "cached": o.cached,
+                "messages": o.messages,
+                "findings": o.findings,
+            })
+        })
+        .collect();
+
+    let report = json!({ "status": status, "checks": checks });
+    serde_json::to_string_pretty(&report).expect("report serializes") + "\n"
+}
+
+/// Renders a JUnit XML report with one test case per check.
+pub fn to_junit(outcomes: &[CheckOutcome]) -> String {
+    let failures = outcomes
+        .iter()
+        .filter(|o| o.status == CheckStatus::Failed)
+        .count();
+    let errors = outcomes
+        .iter()
+        .filter(|o| matches!(o.status, CheckStatus::Error | CheckStatus::Timeout))
+        .count();
+    let skipped = outcomes
+        .iter()
+        .filter(|o| matches!(o.status, CheckStatus::Cancelled | CheckStatus::Skipped))
+        .count();
+    let time: f64 = outcomes.iter().map(|o| o.duration.as_secs_f64()).sum();
+
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    xml.push_str(&format!(
+        "<testsuites name=\"mobdev\" tests=\"{}\" failures=\"{}\" errors=\"{}\" time=\"{:.3}\">\n",
+        outcomes.len(),
+        failures,
+        errors,
+        time
+    ));
+    xml.push_str(&format!(
+        "  <testsuite name=\"mobdev hook\" tests=\"{}\" failures=\"{}\" errors=\"{}\" skipped=\"{}\" time=\"{:.3}\">\n",
+        outcomes.len(),
+        failures,
+        errors,
+        skipped,
+        time
+    ));
+
+    for outcome in outcomes {
+        xml.push_str(&format!(
+            "    <testcase name=\"{}\" classname=\"mobdev.hook\" time=\"{:.3}\"",
+            escape_xml(&outcome.name),
+            outcome.duration.as_secs_f64()
+        ));
+
+        let body: Vec<String> = outcome
+            .findings
+            .iter()
+            .map(|f| f.to_string())
+            .chain(outcome.messages.iter().cloned())
+            .collect();
+
+        match outcome.status {
+            CheckStatus::Passed => xml.push_str("/>\n"),
+            CheckStatus::Failed => xml.push_str(&format!(
+                ">\n      <failure message=\"{} finding(s)\" type=\"failed\">{}</failure>\n    </testcase>\n",
+                outcome.findings.len(),
+                escape_xml(&body.join("\n"))
+            )),
+            CheckStatus::Error | CheckStatus::Timeout => xml.push_str(&format!(
+                ">\n      <error message=\"{}\" type=\"{}\">{}</error>\n    </testcase>\n",
+                escape_xml(outcome.messages.first().map(String::as_str).unwrap_or("")),
+                outcome.status,
+                escape_xml(&body.join("\n"))
+            )),
+            CheckStatus::Cancelled | CheckStatus::Skipped => xml.push_str(&format!(
+                ">\n      <skipped message=\"{}\"/>\n    </testcase>\n",
+                escape_xml(outcome.messages.first().map(String::as_str).unwrap_or(""))
+            )),
+        }
+    }
+
+    xml.push_str("  </testsuite>\n</testsuites>\n");
+    xml
+}
+
+/// Renders a SARIF 2.1.0 log with one result per finding.
+pub fn to_sarif(outcomes: &[CheckOutcome]) -> String {
+    let findings = outcomes.iter().flat_map(|o| o.findings.iter());
+
+    let rules: BTreeSet<&str> = findings.clone().map(|f| f.rule.as_str()).collect();
+    let rules: Vec<_> = rules.into_iter().map(|id| json!({ "id": id })).collect();
+
+    let results: Vec<_> = findings
+        .map(|f| {
+            let level = match f.severity {
+                Severity::Error => "error",
+                Severity::Warning => "warning",
+                Severity::Info => "note",
+            };
+
+            let mut result = json!({
+                "ruleId": f.rule,
+                "level": level,
+                "message": { "text": f.message },
+            });
+
+            if let Some(file) = &f.file {
+                let mut location = json!({
+                    "physicalLocation": {
+                        "artifactLocation": { "uri": file },
+                    }
+                });
+                if let Some(line) = f.line {
+                    let mut region = json!({ "startLine": line });
+                    if let Some(column) = f.column {
+                        region["startColumn"] = json!(column);
+                    }
+                    location["physicalLocation"]["region"] = region;
+                }
+                result["locations"] = json!([location]);
+            }
+
+            result
+        })
+        .collect();
+
+    let log = json!({
+        "$schema": "https://json.schemastore.org/sarif-2.1.0.json",
+        "version": "2.1.0",
+        "runs": [{
+            "tool": {
+                "driver": {
+                    "name": "mobdev",
+                    "version": env!("CARGO_PKG_VERSION"),
+                    "informationUri": env!("CARGO_PKG_REPOSITORY"),
+                    "rules": rules,
+                }
+            },
+            "results": results,
+        }],
+    });
+
+    serde_json::to_string_pretty(&log).expect("report serializes") + "\n"
+}
+
+/// Escapes text for use in XML attributes and content.
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::hook::outcome::Finding;
+
+    fn outcomes() -> Vec<CheckOutcome> {
+        vec![
+            CheckOutcome::run("dart format", || Ok(Vec::new())),
+            CheckOutcome::run("dart analysis", || {
+                Ok(vec![Finding::new(
+                    "unused_import",
+                    Severity::Warning,
+                    "Unused import: 'a.dart'",
+                )
+                .at("lib/main.dart", Some(1), Some(8))])
+            }),
+            CheckOutcome::run("DCM analyze", || anyhow::bail!("dcm <not found>")),
+        ]
+    }
+
+    #[test]
+    fn test_parse_report_spec() {
+        assert_eq!(
+            "sarif=out/results.sarif".parse::<ReportSpec>().unwrap(),
+            ReportSpec {
+                format: ReportFormat::Sarif,
+                path: PathBuf::from("out/results.sarif"),
+            }
+        );
+        assert!("xml=report.xml".parse::<ReportSpec>().is_err());
+        assert!("json".parse::<ReportSpec>().is_err());
+        assert!("json=".parse::<ReportSpec>().is_err());
+    }
+
+    #[test]
+    fn test_json_report() {
+        let report: serde_json::Value = serde_json::from_str(&to_json(&outcomes())).unwrap();
+
+        assert_eq!(report["status"], "failed");
+        assert_eq!(report["checks"][0]["status"], "passed");
+        assert_eq!(report["checks"][1]["findings"][0]["line"], 1);
+        assert_eq!(report["checks"][1]["findings"][0]["severity"], "warning");
+        assert_eq!(report["checks"][2]["status"], "error");
+    }
+
+    #[test]
+    fn test_junit_report() {
+        let xml = to_junit(&outcomes());
+
+        assert!(xml.contains("tests=\"3\" failures=\"1\" errors=\"1\""));
+        assert!(xml.contains("<testcase name=\"dart format\" classname=\"mobdev.hook\""));
+        assert!(xml.contains("lib/main.dart:1:8: Unused import: &apos;a.dart&apos;"));
+        assert!(xml.contains("<error message=\"dcm &lt;not found&gt;\""));
+    }
+
+    #[test]
+    fn test_sarif_report() {
+        let log: serde_json::Value = serde_json::from_str(&to_sarif(&outcomes())).unwrap();
+        let result = &log["runs"][0]["results"][0];
+
+        assert_eq!(log["version"], "2.1.0");
+        assert_eq!(
+            log["runs"][0]["tool"]["driver"]["rules"][0]["id"],
+            "unused_import"
+        );
+        assert_eq!(result["level"], "warning");
+        assert_eq!(
+            result["locations"][0]["physicalLocation"]["artifactLocation"]["uri"],
+            "lib/main.dart"
+        );
+        assert_eq!(
+            result["locations"][0]["physicalLocation"]["region"]["startColumn"],
+            8
+        );
+    }
+}
@@ -0,0 +1,318 @@
+use super::cache::CacheSpec;
+use super::explain::CheckPlan;
+use super::outcome::CheckStatus;
+use super::CheckOutcome;
+use crate::utils::shell::{with_limits, CommandLimits};
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant};
+
+/// Plans a check without running it.
+type PlanFn<'a> = Box<dyn FnOnce() -> anyhow::Result<CheckPlan> + Send + 'a>;
+
+/// A check scheduled by `collate`.
+pub struct Task<'a> {
+    pub name: String,
+    /// Whether the check modifies files in the working tree. Such checks
+    /// never run concurrently with any other check.
+    pub writes_files: bool,
+    /// What the check's result depends on, if it can be cached.
+    pub cache: Option<CacheSpec>,
+    /// How long the check may run before its commands are killed.
+    pub timeout: Option<Duration>,
+    /// Describes what the check would do, for `collate --explain`.
+    pub plan: Option<PlanFn<'a>>,
+    run: Box<dyn FnOnce() -> CheckOutcome + Send + 'a>,
+}
+
+impl<'a> Task<'a> {
+    pub fn new<F>(name: impl Into<String>, writes_files: bool, run: F) -> Self
+    where
+        F: FnOnce() -> CheckOutcome + Send + 'a,
+    {
+        Self {
+            name: name.into(),
+            writes_files,
+            cache: None,
+            timeout: None,
+            plan: None,
+            run: Box::new(run),
+        }
+    }
+
+    /// Makes the task's passes cacheable.
+    pub fn cached(mut self, spec: CacheSpec) -> Self {
+        self.cache = Some(spec);
+        self
+    }
+
+    /// Sets how the check describes what it would do.
+    pub fn plan<F>(mut self, plan: F) -> Self
+    where
+        F: FnOnce() -> anyhow::Result<CheckPlan> + Send + 'a,
+    {
+        self.plan = Some(Box::new(plan));
+        self
+    }
+
+    /// Limits how long the check may run.
+    pub fn timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.timeout = timeout;
+        self
+    }
+}
+
+/// A `--timeout` value: seconds for every check, or for the check named
+/// before `=`. Zero means no timeout.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TimeoutSpec {
+    pub check: Option<String>,
+    pub seconds: u64,
+}
+
+impl std::str::FromStr for TimeoutSpec {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (check, seconds) = match s.rsplit_once('=') {
+            Some((check, seconds)) => (Some(check.trim().to_string()), seconds),
+            None => (None, s),
+        };
+
+        let seconds = seconds.trim().parse().map_err(|_| {
+            format!(
+                "invalid timeout \"{}\": expected SECONDS or NAME=SECONDS",
+                s
+            )
+        })?;
+
+        Ok(Self { check, seconds })
+    }
+}
+
+/// Returns the default number of concurrent checks.
+pub fn default_jobs() -> usize {
+    std::thread::available_parallelism()
+        .map(|n| n.get())
+        .unwrap_or(1)
+}
+
+/// Runs tasks on up to `jobs` threads, starting them in order.
+///
+/// Outcomes are returned in the order of `tasks`, regardless of the order in
+/// which the checks finish. Tasks that write files hold an exclusive lock
+/// while running, so they are serialized with every other task. The commands
+/// of a task are killed once its timeout elapses. With `fail_fast`, the first
+/// check that does not pass cancels the running checks and the rest are not
+/// started.
+pub fn run_tasks(tasks: Vec<Task<'_>>, jobs: usize, fail_fast: bool) -> Vec<CheckOutcome> {
+    let count = tasks.len();
+    let jobs = jobs.clamp(1, count.max(1));
+
+    let queue = Mutex::new(tasks.into_iter().enumerate().collect::<VecDeque<_>>());
+    let results: Mutex<Vec<Option<CheckOutcome>>> = Mutex::new(vec![None; count]);
+    let files = RwLock::new(());
+    let cancelled = Arc::new(AtomicBool::new(false));
+
+    std::thread::scope(|scope| {
+        for _ in 0..jobs {
+            scope.spawn(|| loop {
+                let next = queue.lock().unwrap().pop_front();
+                let Some((index, task)) = next else {
+                    break;
+                };
+
+                if cancelled.load(Ordering::SeqCst) {
+                    results.lock().unwrap()[index] = Some(CheckOutcome::cancelled(&task.name));
+                    continue;
+                }
+
+                let limits = CommandLimits {
+                    deadline: task.timeout.map(|t| (Instant::now() + t, t)),
+                    cancelled: Some(Arc::clone(&cancelled)),
+                };
+                let outcome = with_limits(limits, || {
+                    if task.writes_files {
+                        let _guard = files.write().unwrap();
+                        (task.run)()
+                    } else {
+                        let _guard = files.read().unwrap();
+                        (task.run)()
+                    }
+                });
+
+                if fail_fast && !outcome.is_success() && outcome.status != CheckStatus::Cancelled {
+                    cancelled.store(true, Ordering::SeqCst);
+                }
+
+                results.lock().unwrap()[index] = Some(outcome);
+            });
+        }
+    });
+
+    results
+        .into_inner()
+        .unwrap()
+        .into_iter()
+        .map(|outcome| outcome.expect("every task produces an outcome"))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::shell::run_with_timeout;
+    use std::process::Command;
+    use std::sync::atomic::AtomicUsize;
+
+    fn sleeper(name: &'static str, millis: u64) -> Task<'static> {
+        Task::new(name, false, move || {
+            CheckOutcome::run(name, || {
+                std::thread::sleep(Duration::from_millis(millis));
+                Ok(Vec::new())
+            })
+        })
+    }
+
+    #[test]
+    fn test_run_tasks_keeps_order() {
+        let outcomes = run_tasks(
+            vec![
+                sleeper("slow", 100),
+                sleeper("fast", 0),
+                sleeper("medium", 50),
+            ],
+            3,
+            false,
+        );
+        let names: Vec<&str> = outcomes.iter().map(|o| o.name.as_str()).collect();
+        assert_eq!(names, vec!["slow", "fast", "medium"]);
+    }
+
+    #[test]
+    fn test_run_tasks_in_parallel() {
+        let spans = Mutex::new(Vec::new());
+        let task = || {
+            let spans = &spans;
+            Task::new("task", false, move || {
+                CheckOutcome::run("task", || {
+                    let start = Instant::now();
+                    std::thread::sleep(Duration::from_millis(100));
+                    spans.lock().unwrap().push((start, Instant::now()));
+                    Ok(Vec::new())
+                })
+            })
+        };
+        run_tasks((0..4).map(|_| task()).collect(), 4, false);
+
+        // Every task started before any of them finished.
+        let spans = spans.into_inner().unwrap();
+        let last_start = spans.iter().map(|(start, _)| start).max().unwrap();
+        let first_end = spans.iter().map(|(_, end)| end).min().unwrap();
+        assert_eq!(spans.len(), 4);
+        assert!(last_start < first_end);
+    }
+
+    #[test]
+    fn test_writing_tasks_run_alone() {
+        let running = AtomicUsize::new(0);
+        let overlap = AtomicUsize::new(0);
+
+        let task = |writes_files: bool| {
+            let running = &running;
+            let overlap = &overlap;
+            Task::new("task", writes_files, move || {
+                CheckOutcome::run("task", || {
+                    let others = running.fetch_add(1, Ordering::SeqCst);
+                    if writes_files && others > 0 {
+                        overlap.fetch_add(1, Ordering::SeqCst);
+                    }
+                    std::thread::sleep(Duration::from_millis(30));
+                    running.fetch_sub(1, Ordering::SeqCst);
+                    Ok(Vec::new())
+                })
+            })
+        };
+
+        let tasks = vec![
+            task(false),
+            task(true),
+            task(false),
+            task(true),
+            task(false),
+        ];
+        run_tasks(tasks, 4, false);
+        assert_eq!(overlap.load(Ordering::SeqCst), 0);
+    }
+
+    fn command(name: &'static str, script: &'static str) -> Task<'static> {
+        Task::new(name, false, move || {
+            CheckOutcome::run(name, || {
+                let output = run_with_timeout(Command::new("sh").args(["-c", script]), None)?;
+                if output.status.success() {
+                    Ok(Vec::new())
+                } else {
+                    anyhow::bail!("{} failed", name)
+                }
+            })
+        })
+    }
+
+    #[test]
+    fn test_run_tasks_times_out_checks() {
+        let outcomes = run_tasks(
+            vec![
+                command("hung", "sleep 30").timeout(Some(Duration::from_millis(200))),
+                command("quick", "true").timeout(Some(Duration::from_secs(30))),
+            ],
+            2,
+            false,
+        );
+
+        let statuses: Vec<CheckStatus> = outcomes.iter().map(|o| o.status).collect();
+        assert_eq!(statuses, vec![CheckStatus::Timeout, CheckStatus::Passed]);
+    }
+
+    #[test]
+    fn test_run_tasks_fail_fast() {
+        let outcomes = run_tasks(
+            vec![
+                command("slow", "sleep 30"),
+                command("broken", "sleep 0.2; exit 1"),
+                command("later", "true"),
+            ],
+            2,
+            true,
+        );
+
+        let statuses: Vec<CheckStatus> = outcomes.iter().map(|o| o.status).collect();
+        assert_eq!(
+            statuses,
+            vec![
+                CheckStatus::Cancelled,
+                CheckStatus::Error,
+                CheckStatus::Cancelled
+            ]
+        );
+    }
+
+    #[test]
+    fn test_timeout_spec() {
+        assert_eq!(
+            "90".parse::<TimeoutSpec>().unwrap(),
+            TimeoutSpec {
+                check: None,
+                seconds: 90
+            }
+        );
+        assert_eq!(
+            "DCM analyze=600".parse::<TimeoutSpec>().unwrap(),
+            TimeoutSpec {
+                check: Some("DCM analyze".to_string()),
+                seconds: 600
+            }
+        );
+        assert!("dcm=soon".parse::<TimeoutSpec>().is_err());
+    }
+}
@@ -0,0 +1,171 @@
+use super::explain::CheckPlan;
+use super::outcome::{Finding, Severity};
+use super::CheckOutcome;
+use crate::utils::config::{load_config, DEFAULT_SECRETS_ALLOWLIST};
+use crate::utils::git::{get_diff, get_git_root, get_merge_base, is_git_repo, ChangeScope};
+use crate::utils::secrets::{describe, fingerprint, is_pattern_match, scan_added_lines};
+use anyhow::{Context, Result};
+use std::collections::BTreeSet;
+use std::path::Path;
+
+/// Name of the check, in `collate` and its reports.
+pub const CHECK_NAME: &str = "secrets";
+
+pub fn check(scope: &ChangeScope, verbose: bool) -> CheckOutcome {
+    CheckOutcome::run(CHECK_NAME, || scan(scope, verbose))
+}
+
+/// Plans `check` for `collate --explain`.
+pub fn plan(scope: &ChangeScope) -> Result<CheckPlan> {
+    if !is_git_repo(None::<&str>) {
+        anyhow::bail!("Not in a git repository");
+    }
+
+    let root = get_git_root(None::<&str>)?;
+    let files = scope.changed_files(Some(&root))?;
+    let (allowlist, _) = allowlist_path(&root)?;
+    let plan = CheckPlan::for_files(&root, files.clone());
+
+    if files.is_empty() {
+        return Ok(plan.note("Nothing to check: no changed files"));
+    }
+
+    Ok(plan
+        .note(format!(
+            "Runs in-process on the lines added by git diff {}",
+            diff_args(&root, scope)?.join(" ")
+        ))
+        .note(format!("Allowlist: {}", allowlist)))
+}
+
+/// Scans the lines added in the changed files for secrets, leaving out the
+/// ones whose fingerprint is in the allowlist.
+fn scan(scope: &ChangeScope, verbose: bool) -> Result<Vec<Finding>> {
+    if !is_git_repo(None::<&str>) {
+        anyhow::bail!("Not in a git repository");
+    }
+
+    let root = get_git_root(None::<&str>)?;
+    let files = scope.changed_files(Some(&root))?;
+    if files.is_empty() {
+        if verbose {
+            eprintln!("No changed files");
+        }
+        return Ok(Vec::new());
+    }
+
+    let (allowlist_name, required) = allowlist_path(&root)?;
+    let allowlist = load_allowlist(&root.join(&allowlist_name), required)?;
+
+    let mut args = diff_args(&root, scope)?;
+    args.push("--unified=0".to_string());
+    args.push("--".to_string());
+    args.extend(files.iter().cloned());
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    let diff = get_diff(&root, &args)?;
+
+    if verbose {
+        eprintln!("Scanning the added lines of {} file(s)", files.len());
+    }
+
+    let mut allowed = 0;
+    let mut findings = Vec::new();
+    for secret in scan_added_lines(&diff) {
+        let fingerprint = fingerprint(&secret.text);
+        if allowlist.contains(&fingerprint) {
+            allowed += 1;
+            continue;
+        }
+
+        let severity = if is_pattern_match(secret.kind) {
+            Severity::Error
+        } else {
+            Severity::Info
+        };
+        let message = format!(
+            "Possible {} (fingerprint {}); remove it, or add the fingerprint to {} if it is not a secret",
+            describe(secret.kind),
+            fingerprint,
+            allowlist_name
+        );
+        findings.push(Finding::new(secret.kind, severity, message).at(
+            secret.file,
+            Some(secret.line),
+            Some(secret.column),
+        ));
+    }
+
+    if verbose {
+        if allowed > 0 {
+            eprintln!("{} allowlisted finding(s)", allowed);
+        }
+        if findings.is_empty() {
+            eprintln!("✓ No secrets found");
+        } else {
+            eprintln!("{} possible secret(s) found", findings.len());
+        }
+    }
+
+    Ok(findings)
+}
+
+/// The `git diff` arguments comparing the scope of the check: the index
+/// with `--staged`, the working tree with `--unstaged`, otherwise the
+/// working tree with the merge-base of `base_branch`.
+fn diff_args(root: &Path, scope: &ChangeScope) -> Result<Vec<String>> {
+    if scope.all || (!scope.staged && !scope.unstaged) {
+        Ok(vec![get_merge_base(root, &scope.base_branch, "HEAD")?])
+    } else if scope.staged {
+        Ok(vec!["--cached".to_string()])
+    } else {
+        Ok(Vec::new())
+    }
+}
+
+/// The allowlist file from `secrets.allowlist`, and whether it must exist:
+/// a configured allowlist must, the default one may be missing.
+fn allowlist_path(root: &Path) -> Result<(String, bool)> {
+    let config = load_config(root)?;
+    Ok(match config.secrets.allowlist {
+        Some(path) => (path, true),
+        None => (DEFAULT_SECRETS_ALLOWLIST.to_string(), false),
+    })
+}
+
+/// Reads an allowlist: one fingerprint per line, optionally followed by a
+/// note; blank lines and lines starting with `#` are ignored.
+fn load_allowlist(path: &Path, required: bool) -> Result<BTreeSet<String>> {
+    if !required && !path.exists() {
+        return Ok(BTreeSet::new());
+    }
+
+    let text = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read secrets allowlist {}", path.display()))?;
+    Ok(parse_allowlist(&text))
+}
+
+fn parse_allowlist(text: &str) -> BTreeSet<String> {
+    text.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .filter_map(|line| line.split_whitespace().next())
+        .map(|fingerprint| fingerprint.to_ascii_lowercase())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_allowlist() {
+        let allowlist = parse_allowlist(
+            "# Test fixtures\n\n0123456789ABCDEF  test/fixtures/keys.json\n  fedcba9876543210\n",
+        );
+
+        assert_eq!(
+            allowlist.into_iter().collect::<Vec<_>>(),
+            vec!["0123456789abcdef", "fedcba9876543210"]
+        );
+    }
+}
@@ -0,0 +1,257 @@
+use crate::utils::config::SkipConfig;
+use crate::utils::git::{get_commit_trailers, get_current_branch};
+use crate::utils::glob::Glob;
+use std::path::Path;
+
+/// Environment variable listing checks to skip, comma-separated.
+pub const SKIP_ENV: &str = "MOBDEV_SKIP";
+
+/// Commit trailer listing checks to skip, with an optional reason:
+/// `Mobdev-Skip: graphql, dcm (schema server is down)`.
+pub const SKIP_TRAILER: &str = "Mobdev-Skip";
+
+/// Skip name matching every check.
+const ALL: &str = "all";
+
+/// The commits and branches of a push, which replace the commits since the
+/// base branch and the current branch in `hook pre-push`.
+#[derive(Debug, Clone, Default)]
+pub struct PushedCommits {
+    /// `git log` revision arguments selecting the pushed commits.
+    pub ranges: Vec<String>,
+    /// Remote refs being updated, e.g. `refs/heads/main`.
+    pub branches: Vec<String>,
+}
+
+/// A request to skip one check.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SkipRequest {
+    /// Check name or alias, as written by the user.
+    pub name: String,
+    pub reason: Option<String>,
+    /// Where the request came from, e.g. `MOBDEV_SKIP`.
+    pub source: String,
+}
+
+/// What to do with a check that was asked to be skipped.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SkipDecision {
+    /// Skip the check; the message records why and who asked.
+    Skip(String),
+    /// Run the check anyway, because skipping it is forbidden on a branch.
+    Refuse(String),
+}
+
+/// The skip requests of a run and the rules restricting them.
+#[derive(Debug, Default)]
+pub struct Skips {
+    pub requests: Vec<SkipRequest>,
+    /// The protected branch the run is for, if any.
+    protected_branch: Option<String>,
+    /// Names of the checks that cannot be skipped on protected branches;
+    /// `None` forbids skipping any check.
+    forbid: Option<Vec<String>>,
+}
+
+impl Skips {
+    /// Collects the skip requests from `MOBDEV_SKIP` and from the trailers of
+    /// the checked commits: the `pushed` commits in `hook pre-push`, otherwise
+    /// the commits since `base_branch` when they are in scope.
+    pub fn resolve(
+        root: &Path,
+        config: &SkipConfig,
+        base_branch: &str,
+        commits: bool,
+        pushed: Option<&PushedCommits>,
+    ) -> Self {
+        let mut requests = std::env::var(SKIP_ENV)
+            .map(|value| parse(&value, SKIP_ENV))
+            .unwrap_or_default();
+
+        let (ranges, branches) = match pushed {
+            Some(pushed) => (pushed.ranges.clone(), pushed.branches.clone()),
+            None => {
+                let ranges = if commits {
+                    vec![format!("{}..HEAD", base_branch)]
+                } else {
+                    Vec::new()
+                };
+                let branches = get_current_branch(Some(root)).into_iter().collect();
+                (ranges, branches)
+            }
+        };
+
+        if !ranges.is_empty() {
+            for (commit, value) in
+                get_commit_trailers(root, &ranges, SKIP_TRAILER).unwrap_or_default()
+            {
+                let source = format!("{} trailer of {}", SKIP_TRAILER, commit);
+                requests.extend(parse(&value, &source));
+            }
+        }
+
+        let protected_branch = branches
+            .into_iter()
+            .find(|branch| is_protected(branch, &config.protected_branches));
+
+        Self {
+            requests,
+            protected_branch,
+            forbid: config.forbid.clone(),
+        }
+    }
+
+    /// Decides whether the check named `check` is skipped.
+    pub fn decide(&self, check: &str) -> Option<SkipDecision> {
+        let request = self.requests.iter().find(|r| names_check(&r.name, check))?;
+
+        if let Some(branch) = &self.protected_branch {
+            let forbidden = match &self.forbid {
+                Some(names) => names.iter().any(|name| names_check(name, check)),
+                None => true,
+            };
+            if forbidden {
+                return Some(SkipDecision::Refuse(format!(
+                    "Not skipping {} ({}): skipping it is forbidden on {}",
+                    check, request.source, branch
+                )));
+            }
+        }
+
+        Some(SkipDecision::Skip(match &request.reason {
+            Some(reason) => format!("Skipped by {}: {}", request.source, reason),
+            None => format!("Skipped by {}", request.source),
+        }))
+    }
+
+    /// The requested names that match none of `checks`.
+    pub fn unknown<'a>(&'a self, checks: &[&str]) -> Vec<&'a SkipRequest> {
+        self.requests
+            .iter()
+            .filter(|r| !checks.iter().any(|check| names_check(&r.name, check)))
+            .collect()
+    }
+}
+
+/// Parses a list of check names, optionally followed by a reason in
+/// parentheses: `graphql, dcm (schema server is down)`.
+pub fn parse(value: &str, source: &str) -> Vec<SkipRequest> {
+    let value = value.trim();
+    let (names, reason) = match value.find('(') {
+        Some(start) => {
+            let reason = value[start + 1..].trim_end().trim_end_matches(')').trim();
+            (
+                &value[..start],
+                Some(reason.to_string()).filter(|r| !r.is_empty()),
+            )
+        }
+        None => (value, None),
+    };
+
+    names
+        .split(',')
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .map(|name| SkipRequest {
+            name: name.to_string(),
+            reason: reason.clone(),
+            source: source.to_string(),
+        })
+        .collect()
+}
+
+/// Whether a skip name refers to a check: its name, the flag selecting it,
+/// or a short alias, ignoring case.
+pub fn names_check(name: &str, check: &str) -> bool {
+    let aliases: &[&str] = match check {
+        "dart format" => &["dart-format", "format"],
+        "dart analysis" => &["dart-analysis", "analysis"],
+        "DCM analyze" => &["dcm-analyze", "dcm"],
+        "GraphQL" => &["graphql"],
+        "GraphQL validation" => &["graphql-validate"],
+        _ => &[],
+    };
+
+    name.eq_ignore_ascii_case(ALL)
+        || name.eq_ignore_ascii_case(check)
+        || aliases.iter().any(|alias| name.eq_ignore_ascii_case(alias))
+}
+
+/// Whether a branch matches one of the protected branch globs. Remote refs
+/// like `refs/heads/main` are matched by their branch name.
+fn is_protected(branch: &str, patterns: &[String]) -> bool {
+    let branch = branch.strip_prefix("refs/heads/").unwrap_or(branch);
+    patterns.iter().any(|pattern| {
+        // Anchor the glob at the start, so that `main` only matches `main`.
+        Glob::new(&format!("/{}", pattern.trim_start_matches('/')))
+            .is_ok_and(|glob| glob.is_match(branch))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(
+            parse("graphql, dcm (schema server is down)", "trailer"),
+            vec![
+                SkipRequest {
+                    name: "graphql".to_string(),
+                    reason: Some("schema server is down".to_string()),
+                    source: "trailer".to_string(),
+                },
+                SkipRequest {
+                    name: "dcm".to_string(),
+                    reason: Some("schema server is down".to_string()),
+                    source: "trailer".to_string(),
+                },
+            ]
+        );
+        assert_eq!(parse(" , ", "env"), Vec::new());
+        assert_eq!(parse("no-print", "env")[0].reason, None);
+    }
+
+    #[test]
+    fn test_names_check() {
+        assert!(names_check("dcm", "DCM analyze"));
+        assert!(names_check("GraphQL", "GraphQL"));
+        assert!(!names_check("graphql", "GraphQL validation"));
+        assert!(names_check("graphql-validate", "GraphQL validation"));
+        assert!(names_check("all", "no-print"));
+        assert!(!names_check("no-print", "dart format"));
+    }
+
+    #[test]
+    fn test_decide_on_protected_branch() {
+        let skips = |branch: &str, forbid: Option<Vec<String>>| Skips {
+            requests: parse("dcm, no-print (flaky)", "MOBDEV_SKIP"),
+            protected_branch: is_protected(branch, &["main".to_string(), "release/*".to_string()])
+                .then(|| branch.to_string()),
+            forbid,
+        };
+
+        assert_eq!(
+            skips("feature/main", None).decide("DCM analyze"),
+            Some(SkipDecision::Skip(
+                "Skipped by MOBDEV_SKIP: flaky".to_string()
+            ))
+        );
+        assert_eq!(skips("main", None).decide("dart format"), None);
+        assert!(matches!(
+            skips("refs/heads/release/1.0", None).decide("DCM analyze"),
+            Some(SkipDecision::Refuse(_))
+        ));
+
+        let forbid = Some(vec!["dcm".to_string()]);
+        assert!(matches!(
+            skips("main", forbid.clone()).decide("DCM analyze"),
+            Some(SkipDecision::Refuse(_))
+        ));
+        assert!(matches!(
+            skips("main", forbid).decide("no-print"),
+            Some(SkipDecision::Skip(_))
+        ));
+    }
+}
@@ -0,0 +1,271 @@
+use crate::utils::git::get_git_path;
+use anyhow::{Context, Result};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Where unstaged changes are saved while checks run, inside the git directory.
+const PATCH_GIT_PATH: &str = "mobdev/unstaged.patch";
+
+/// Unstaged changes set aside so that checks see exactly what will be
+/// committed.
+///
+/// Taking the snapshot saves the unstaged changes of tracked files as a
+/// binary patch and resets those files to their staged content; restoring it
+/// re-applies the patch. Untracked files are left alone. The patch stays in
+/// the git directory until it has been applied, so an interrupted run can be
+/// recovered with `git apply`.
+pub struct StagedSnapshot {
+    root: PathBuf,
+    patch: PathBuf,
+    files: Vec<String>,
+    restored: bool,
+}
+
+impl StagedSnapshot {
+    /// Sets aside the unstaged changes of the repository at `root`. Returns
+    /// `None` when there are none.
+    pub fn take(root: &Path, verbose: bool) -> Result<Option<Self>> {
+        let root = root.to_path_buf();
+        let patch = get_git_path(&root, PATCH_GIT_PATH)?;
+
+        if patch.exists() {
+            anyhow::bail!(
+                "Unstaged changes from an interrupted run are saved in {}; restore them with `git apply {}` and delete the file",
+                patch.display(),
+                patch.display()
+            );
+        }
+
+        let files: Vec<String> = git(&root, &["diff", "--name-only", "-z"])?
+            .split('\0')
+            .filter(|f| !f.is_empty())
+            .map(|f| f.to_string())
+            .collect();
+
+        if files.is_empty() {
+            return Ok(None);
+        }
+
+        // Pin the path prefixes that `git apply` expects, whatever the user's
+        // diff configuration.
+        let diff = git_bytes(
+            &root,
+            &[
+                "-c",
+                "diff.noprefix=false",
+                "-c",
+                "diff.mnemonicPrefix=false",
+                "diff",
+                "--binary",
+                "--no-color",
+                "--no-ext-diff",
+                "--no-textconv",
+                "--src-prefix=a/",
+                "--dst-prefix=b/",
+            ],
+        )?;
+
+        if let Some(dir) = patch.parent() {
+            std::fs::create_dir_all(dir)
+                .with_context(|| format!("Failed to create {}", dir.display()))?;
+        }
+        std::fs::write(&patch, diff)
+            .with_context(|| format!("Failed to write {}", patch.display()))?;
+
+        let mut snapshot = Self {
+            root,
+            patch,
+            files,
+            restored: false,
+        };
+        if let Err(e) = snapshot.checkout_index() {
+            // Nothing was set aside, so there is nothing to restore.
+            snapshot.restored = true;
+            let _ = std::fs::remove_file(&snapshot.patch);
+            return Err(e);
+        }
+
+        if verbose {
+            eprintln!(
+                "Set aside unstaged changes in {} file(s); checking the staged snapshot",
+                snapshot.files.len()
+            );
+        }
+
+        Ok(Some(snapshot))
+    }
+
+    /// Re-applies the unstaged changes.
+    ///
+    /// If checks modified the same files and the patch no longer applies,
+    /// those modifications are discarded in favor of the unstaged changes.
+    pub fn restore(mut self, verbose: bool) -> Result<()> {
+        self.restored = true;
+        self.apply(verbose)
+    }
+
+    fn apply(&self, verbose: bool) -> Result<()> {
+        if self.try_apply().is_err() {
+            if verbose {
+                eprintln!("Unstaged changes conflict with changes made by the checks; discarding the latter");
+            }
+            self.checkout_index()?;
+            self.try_apply().with_context(|| {
+                format!(
+                    "Failed to restore unstaged changes; they are saved in {}",
+                    self.patch.display()
+                )
+            })?;
+        }
+
+        std::fs::remove_file(&self.patch)
+            .with_context(|| format!("Failed to remove {}", self.patch.display()))?;
+
+        if verbose {
+            eprintln!("Restored unstaged changes");
+        }
+
+        Ok(())
+    }
+
+    fn try_apply(&self) -> Result<()> {
+        // An empty patch means only mode changes or similar; nothing to apply.
+        if std::fs::metadata(&self.patch).map(|m| m.len()).unwrap_or(0) == 0 {
+            return Ok(());
+        }
+
+        let patch = self.patch.to_string_lossy().to_string();
+        git(&self.root, &["apply", "--whitespace=nowarn", &patch]).map(|_| ())
+    }
+
+    /// Resets the files with unstaged changes to their staged content.
+    fn checkout_index(&self) -> Result<()> {
+        // File names are paths, not patterns: `*.txt` only resets `*.txt`.
+        let mut args = vec!["--literal-pathspecs", "checkout", "--"];
+        args.extend(self.files.iter().map(String::as_str));
+        git(&self.root, &args).map(|_| ())
+    }
+}
+
+impl Drop for StagedSnapshot {
+    fn drop(&mut self) {
+        if !self.restored {
+            if let Err(e) = self.apply(false) {
+                eprintln!("{:#}", e);
+            }
+        }
+    }
+}
+
+fn git(root: &Path, args: &[&str]) -> Result<String> {
+    git_bytes(root, args).map(|out| String::from_utf8_lossy(&out).to_string())
+}
+
+fn git_bytes(root: &Path, args: &[&str]) -> Result<Vec<u8>> {
+    let output = Command::new("git")
+        .args(args)
+        .current_dir(root)
+        .output()
+        .context("Failed to execute git command")?;
+
+    if !output.status.success() {
+        anyhow::bail!(
+            "git {} failed: {}",
+            args.iter()
+                .find(|a| !a.starts_with('-') && !a.contains('='))
+                .unwrap_or(&""),
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+
+    Ok(output.stdout)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(dir: &Path, args: &[&str]) {
+        let status = Command::new("git")
+            .args(["-c", "user.name=Test", "-c", "user.email=test@example.com"])
+            .args(args)
+            .current_dir(dir)
+            .output()
+            .unwrap()
+            .status;
+        assert!(status.success(), "git {:?} failed", args);
+    }
+
+    fn read(dir: &Path, file: &str) -> String {
+        std::fs::read_to_string(dir.join(file)).unwrap()
+    }
+
+    /// Creates a repository where `a.txt` is partially staged and `b.txt`
+    /// has only unstaged changes.
+    fn partially_staged_repo() -> tempfile::TempDir {
+        let dir = tempfile::tempdir().unwrap();
+        run(dir.path(), &["init", "-q"]);
+        std::fs::write(dir.path().join("a.txt"), "one\ntwo\n").unwrap();
+        std::fs::write(dir.path().join("b.txt"), "b\n").unwrap();
+        run(dir.path(), &["add", "."]);
+        run(dir.path(), &["commit", "-q", "-m", "init"]);
+
+        std::fs::write(dir.path().join("a.txt"), "one staged\ntwo\n").unwrap();
+        run(dir.path(), &["add", "a.txt"]);
+        std::fs::write(dir.path().join("a.txt"), "one staged\ntwo unstaged\n").unwrap();
+        std::fs::write(dir.path().join("b.txt"), "b unstaged\n").unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_snapshot_round_trip() {
+        let repo = partially_staged_repo();
+        let dir = repo.path();
+
+        let snapshot = StagedSnapshot::take(dir, false).unwrap().unwrap();
+        assert_eq!(read(dir, "a.txt"), "one staged\ntwo\n");
+        assert_eq!(read(dir, "b.txt"), "b\n");
+        // The saved patch blocks a second snapshot until it is restored.
+        assert!(StagedSnapshot::take(dir, false).is_err());
+
+        // A check rewriting a file the unstaged changes also touch.
+        std::fs::write(dir.join("a.txt"), "one staged\ntwo formatted\n").unwrap();
+
+        snapshot.restore(false).unwrap();
+        assert_eq!(read(dir, "a.txt"), "one staged\ntwo unstaged\n");
+        assert_eq!(read(dir, "b.txt"), "b unstaged\n");
+        assert!(!get_git_path(dir, PATCH_GIT_PATH).unwrap().exists());
+        assert!(StagedSnapshot::take(dir, false).unwrap().is_some());
+    }
+
+    #[test]
+    fn test_snapshot_ignores_diff_config_and_pathspec_magic() {
+        let repo = partially_staged_repo();
+        let dir = repo.path();
+        run(dir, &["config", "diff.noprefix", "true"]);
+        run(dir, &["config", "diff.mnemonicPrefix", "true"]);
+
+        // As a pathspec, `[ab].txt` matches a.txt and b.txt, not itself.
+        std::fs::write(dir.join("[ab].txt"), "ab\n").unwrap();
+        run(dir, &["--literal-pathspecs", "add", "[ab].txt"]);
+        std::fs::write(dir.join("[ab].txt"), "ab unstaged\n").unwrap();
+
+        let snapshot = StagedSnapshot::take(dir, false).unwrap().unwrap();
+        assert_eq!(read(dir, "[ab].txt"), "ab\n");
+        assert_eq!(read(dir, "a.txt"), "one staged\ntwo\n");
+
+        snapshot.restore(false).unwrap();
+        assert_eq!(read(dir, "[ab].txt"), "ab unstaged\n");
+        assert_eq!(read(dir, "a.txt"), "one staged\ntwo unstaged\n");
+    }
+
+    #[test]
+    fn test_snapshot_restores_on_drop() {
+        let repo = partially_staged_repo();
+        let dir = repo.path();
+
+        drop(StagedSnapshot::take(dir, false).unwrap());
+        assert_eq!(read(dir, "a.txt"), "one staged\ntwo unstaged\n");
+        assert_eq!(read(dir, "b.txt"), "b unstaged\n");
+    }
+}
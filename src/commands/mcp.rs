@@ -0,0 +1,530 @@
+use anyhow::{Context, Result};
+use clap::builder::ArgAction;
+use serde_json::{json, Map, Value};
+use std::any::TypeId;
+use std::io::{BufRead, Write};
+use std::process::{Command, Stdio};
+
+/// Protocol versions the server speaks, newest first.
+const PROTOCOL_VERSIONS: &[&str] = &["2025-06-18", "2025-03-26", "2024-11-05"];
+
+/// JSON-RPC error codes.
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+
+/// Arguments never exposed to tools.
+const HIDDEN_ARGS: &[&str] = &["help", "version", "verbose"];
+
+/// A tool backed by a mobdev command. Its input schema is derived from the
+/// command's options, and calls run the command.
+struct Tool {
+    name: &'static str,
+    /// Subcommand path, e.g. `["git", "changed"]`.
+    command: &'static [&'static str],
+    /// Options of the command the tool does not expose.
+    hidden: &'static [&'static str],
+    /// Whether the command writes a JSON report, returned as the result.
+    report: bool,
+}
+
+const TOOLS: &[Tool] = &[
+    Tool {
+        name: "git_changed_files",
+        command: &["git", "changed"],
+        hidden: &[],
+        report: false,
+    },
+    Tool {
+        name: "codeowners_who",
+        command: &["git", "codeowners", "who"],
+        hidden: &[],
+        report: false,
+    },
+    Tool {
+        name: "dart_package",
+        command: &["dart", "package"],
+        hidden: &[],
+        report: false,
+    },
+    Tool {
+        name: "dart_changed_files",
+        command: &["dart", "changed"],
+        hidden: &[],
+        report: false,
+    },
+    Tool {
+        name: "dart_downstream",
+        command: &["dart", "changed", "downstream"],
+        hidden: &[],
+        report: false,
+    },
+    Tool {
+        name: "hook_check",
+        command: &["hook", "collate"],
+        hidden: &["fix", "snapshot", "explain", "report", "annotations"],
+        report: true,
+    },
+];
+
+/// A JSON-RPC error.
+#[derive(Debug)]
+struct RpcError {
+    code: i64,
+    message: String,
+}
+
+impl RpcError {
+    fn new(code: i64, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+        }
+    }
+}
+
+/// Serves the tools over stdio: one JSON-RPC message per line on stdin, one
+/// response per line on stdout. Returns when stdin is closed.
+pub fn serve(cli: clap::Command, verbose: bool) -> Result<()> {
+    let stdin = std::io::stdin();
+    let mut stdout = std::io::stdout();
+
+    if verbose {
+        eprintln!("mobdev MCP server ready on stdio");
+    }
+
+    for line in stdin.lock().lines() {
+        let line = line.context("Failed to read from stdin")?;
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        if let Some(response) = handle_message(&cli, &line, verbose) {
+            writeln!(stdout, "{}", response).context("Failed to write to stdout")?;
+            stdout.flush().context("Failed to write to stdout")?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Handles one message and returns the response, or `None` for
+/// notifications.
+fn handle_message(cli: &clap::Command, line: &str, verbose: bool) -> Option<Value> {
+    let message: Value = match serde_json::from_str(line) {
+        Ok(message) => message,
+        Err(e) => {
+            return Some(error_response(
+                Value::Null,
+                RpcError::new(PARSE_ERROR, format!("Parse error: {}", e)),
+            ))
+        }
+    };
+
+    let id = message.get("id").cloned();
+    let Some(method) = message.get("method").and_then(Value::as_str) else {
+        return Some(error_response(
+            id.unwrap_or(Value::Null),
+            RpcError::new(INVALID_REQUEST, "Invalid request: missing method"),
+        ));
+    };
+
+    if verbose {
+        eprintln!("<- {}", method);
+    }
+
+    // Notifications, such as `notifications/initialized`, get no response.
+    let id = id?;
+    let params = message.get("params").cloned().unwrap_or(Value::Null);
+
+    Some(match handle_request(cli, method, &params) {
+        Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+        Err(e) => error_response(id, e),
+    })
+}
+
+fn handle_request(cli: &clap::Command, method: &str, params: &Value) -> Result<Value, RpcError> {
+    match method {
+        "initialize" => {
+            let requested = params.get("protocolVersion").and_then(Value::as_str);
+            let version = requested
+                .filter(|v| PROTOCOL_VERSIONS.contains(v))
+                .unwrap_or(PROTOCOL_VERSIONS[0]);
+
+            Ok(json!({
+                "protocolVersion": version,
+                "capabilities": { "tools": {} },
+                "serverInfo": { "name": "mobdev", "version": env!("CARGO_PKG_VERSION") },
+            }))
+        }
+        "ping" => Ok(json!({})),
+        "tools/list" => {
+            let tools = TOOLS
+                .iter()
+                .map(|tool| describe_tool(cli, tool))
+                .collect::<Result<Vec<_>, _>>()?;
+            Ok(json!({ "tools": tools }))
+        }
+        "tools/call" => {
+            let name = params.get("name").and_then(Value::as_str).unwrap_or("");
+            let tool = TOOLS
+                .iter()
+                .find(|tool| tool.name == name)
+                .ok_or_else(|| RpcError::new(INVALID_PARAMS, format!("Unknown tool: {}", name)))?;
+            let arguments = match params.get("arguments") {
+                None | Some(Value::Null) => Map::new(),
+                Some(Value::Object(arguments)) => arguments.clone(),
+                Some(_) => {
+                    return Err(RpcError::new(INVALID_PARAMS, "arguments must be an object"))
+                }
+            };
+
+            let command = find_command(cli, tool)?;
+            let args = command_args(tool, command, &arguments)?;
+            Ok(call_tool(tool, args))
+        }
+        _ => Err(RpcError::new(
+            METHOD_NOT_FOUND,
+            format!("Method not found: {}", method),
+        )),
+    }
+}
+
+fn error_response(id: Value, error: RpcError) -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "id": id,
+        "error": { "code": error.code, "message": error.message },
+    })
+}
+
+fn find_command<'a>(cli: &'a clap::Command, tool: &Tool) -> Result<&'a clap::Command, RpcError> {
+    tool.command
+        .iter()
+        .try_fold(cli, |command, name| command.find_subcommand(name))
+        .ok_or_else(|| {
+            RpcError::new(
+                INVALID_PARAMS,
+                format!(
+                    "No command {} for tool {}",
+                    tool.command.join(" "),
+                    tool.name
+                ),
+            )
+        })
+}
+
+/// The options of a command exposed by a tool.
+fn exposed_args<'a>(tool: &Tool, command: &'a clap::Command) -> Vec<&'a clap::Arg> {
+    command
+        .get_arguments()
+        .filter(|arg| {
+            let id = arg.get_id().as_str();
+            !HIDDEN_ARGS.contains(&id) && !tool.hidden.contains(&id) && !arg.is_hide_set()
+        })
+        .collect()
+}
+
+/// Describes a tool for `tools/list`, with an input schema derived from the
+/// options of its command.
+fn describe_tool(cli: &clap::Command, tool: &Tool) -> Result<Value, RpcError> {
+    let command = find_command(cli, tool)?;
+
+    let mut properties = Map::new();
+    let mut required = Vec::new();
+    for arg in exposed_args(tool, command) {
+        let id = arg.get_id().as_str();
+        properties.insert(id.to_string(), arg_schema(arg));
+        if arg.is_required_set() {
+            required.push(id);
+        }
+    }
+
+    let description = command
+        .get_about()
+        .map(|about| about.to_string())
+        .unwrap_or_default();
+
+    Ok(json!({
+        "name": tool.name,
+        "description": format!("{} (mobdev {})", description, tool.command.join(" ")),
+        "inputSchema": {
+            "type": "object",
+            "properties": properties,
+            "required": required,
+            "additionalProperties": false,
+        },
+    }))
+}
+
+/// The JSON schema of an option's value.
+fn arg_schema(arg: &clap::Arg) -> Value {
+    let mut schema = Map::new();
+
+    let item_type = if is_integer(arg) { "integer" } else { "string" };
+    let mut item = json!({ "type": item_type });
+    let values: Vec<String> = arg
+        .get_possible_values()
+        .iter()
+        .filter(|v| !v.is_hide_set())
+        .map(|v| v.get_name().to_string())
+        .collect();
+    if !values.is_empty() {
+        item["enum"] = json!(values);
+    }
+
+    if is_flag(arg) {
+        schema.insert("type".to_string(), json!("boolean"));
+    } else if is_multiple(arg) {
+        schema.insert("type".to_string(), json!("array"));
+        schema.insert("items".to_string(), item);
+    } else {
+        schema = item.as_object().cloned().unwrap_or_default();
+        if let Some(default) = arg.get_default_values().first() {
+            schema.insert(
+                "default".to_string(),
+                json!(default.to_string_lossy().to_string()),
+            );
+        }
+    }
+
+    if let Some(help) = arg.get_help() {
+        schema.insert("description".to_string(), json!(help.to_string()));
+    }
+
+    Value::Object(schema)
+}
+
+fn is_flag(arg: &clap::Arg) -> bool {
+    matches!(arg.get_action(), ArgAction::SetTrue)
+}
+
+fn is_multiple(arg: &clap::Arg) -> bool {
+    matches!(arg.get_action(), ArgAction::Append)
+        || arg.get_num_args().is_some_and(|n| n.max_values() > 1)
+}
+
+fn is_integer(arg: &clap::Arg) -> bool {
+    let id = arg.get_value_parser().type_id();
+    [
+        TypeId::of::<usize>(),
+        TypeId::of::<u64>(),
+        TypeId::of::<u32>(),
+        TypeId::of::<i64>(),
+        TypeId::of::<i32>(),
+    ]
+    .iter()
+    .any(|t| id == *t)
+}
+
+/// Turns tool arguments into command-line arguments for the tool's command.
+fn command_args(
+    tool: &Tool,
+    command: &clap::Command,
+    arguments: &Map<String, Value>,
+) -> Result<Vec<String>, RpcError> {
+    let exposed = exposed_args(tool, command);
+    let mut args: Vec<String> = tool.command.iter().map(|s| s.to_string()).collect();
+    let mut positionals = Vec::new();
+
+    for (name, value) in arguments {
+        let arg = exposed
+            .iter()
+            .find(|arg| arg.get_id().as_str() == name)
+            .ok_or_else(|| RpcError::new(INVALID_PARAMS, format!("Unknown argument: {}", name)))?;
+        let invalid = || RpcError::new(INVALID_PARAMS, format!("Invalid value for {}", name));
+
+        let values: Vec<String> = if is_flag(arg) {
+            match value {
+                Value::Bool(true) => vec![String::new()],
+                Value::Bool(false) | Value::Null => Vec::new(),
+                _ => return Err(invalid()),
+            }
+        } else if is_multiple(arg) {
+            match value {
+                Value::Array(items) => items
+                    .iter()
+                    .map(|item| scalar(item).ok_or_else(invalid))
+                    .collect::<Result<_, _>>()?,
+                Value::Null => Vec::new(),
+                item => vec![scalar(item).ok_or_else(invalid)?],
+            }
+        } else {
+            match value {
+                Value::Null => Vec::new(),
+                item => vec![scalar(item).ok_or_else(invalid)?],
+            }
+        };
+
+        for value in values {
+            match arg.get_long() {
+                Some(long) if is_flag(arg) => args.push(format!("--{}", long)),
+                Some(long) => args.push(format!("--{}={}", long, value)),
+                None => positionals.push(value),
+            }
+        }
+    }
+
+    if !positionals.is_empty() {
+        args.push("--".to_string());
+        args.extend(positionals);
+    }
+
+    Ok(args)
+}
+
+fn scalar(value: &Value) -> Option<String> {
+    match value {
+        Value::String(s) => Some(s.clone()),
+        Value::Number(n) => Some(n.to_string()),
+        _ => None,
+    }
+}
+
+/// Runs a tool's command and turns its output into a tool result. Failures
+/// of the command are tool errors, except for checks that ran and wrote
+/// their report.
+fn call_tool(tool: &Tool, mut args: Vec<String>) -> Value {
+    let report = if tool.report {
+        match tempfile::NamedTempFile::new() {
+            Ok(file) => Some(file),
+            Err(e) => return tool_result(format!("Failed to create report file: {}", e), true),
+        }
+    } else {
+        None
+    };
+    if let Some(report) = &report {
+        args.push(format!("--report=json={}", report.path().display()));
+        args.push("--annotations=none".to_string());
+    }
+
+    let exe = match std::env::current_exe() {
+        Ok(exe) => exe,
+        Err(e) => return tool_result(format!("Failed to locate mobdev: {}", e), true),
+    };
+    let output = match Command::new(exe).args(&args).stdin(Stdio::null()).output() {
+        Ok(output) => output,
+        Err(e) => return tool_result(format!("Failed to run mobdev: {}", e), true),
+    };
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+
+    if let Some(report) = &report {
+        if let Ok(contents) = std::fs::read_to_string(report.path()) {
+            if !contents.trim().is_empty() {
+                return tool_result(contents, false);
+            }
+        }
+    }
+
+    if output.status.success() {
+        tool_result(stdout.into_owned(), false)
+    } else {
+        let text = [stdout.trim(), stderr.trim()]
+            .iter()
+            .filter(|s| !s.is_empty())
+            .cloned()
+            .collect::<Vec<_>>()
+            .join("\n");
+        tool_result(text, true)
+    }
+}
+
+fn tool_result(text: String, is_error: bool) -> Value {
+    json!({
+        "content": [{ "type": "text", "text": text }],
+        "isError": is_error,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::{Arg, Command};
+
+    fn command() -> Command {
+        Command::new("mobdev").subcommand(
+            Command::new("changed")
+                .about("Show changed files")
+                .arg(
+                    Arg::new("staged")
+                        .long("staged")
+                        .action(ArgAction::SetTrue)
+                        .help("Staged only"),
+                )
+                .arg(
+                    Arg::new("base_branch")
+                        .long("base-branch")
+                        .default_value("main"),
+                )
+                .arg(
+                    Arg::new("jobs")
+                        .long("jobs")
+                        .value_parser(clap::value_parser!(usize)),
+                )
+                .arg(Arg::new("checks").long("check").action(ArgAction::Append))
+                .arg(Arg::new("file").required(true))
+                .arg(
+                    Arg::new("verbose")
+                        .long("verbose")
+                        .action(ArgAction::SetTrue),
+                ),
+        )
+    }
+
+    const TOOL: Tool = Tool {
+        name: "changed",
+        command: &["changed"],
+        hidden: &[],
+        report: false,
+    };
+
+    #[test]
+    fn test_describe_tool() {
+        let tool = describe_tool(&command(), &TOOL).unwrap();
+
+        assert_eq!(tool["description"], "Show changed files (mobdev changed)");
+        assert_eq!(
+            tool["inputSchema"]["properties"],
+            json!({
+                "staged": { "type": "boolean", "description": "Staged only" },
+                "base_branch": { "type": "string", "default": "main" },
+                "jobs": { "type": "integer" },
+                "checks": { "type": "array", "items": { "type": "string" } },
+                "file": { "type": "string" },
+            })
+        );
+        assert_eq!(tool["inputSchema"]["required"], json!(["file"]));
+    }
+
+    #[test]
+    fn test_command_args() {
+        let cli = command();
+        let command = find_command(&cli, &TOOL).unwrap();
+        let arguments = json!({
+            "staged": true,
+            "jobs": 2,
+            "checks": ["a", "b"],
+            "file": "-lib/a.dart",
+        });
+
+        assert_eq!(
+            command_args(&TOOL, command, arguments.as_object().unwrap()).unwrap(),
+            vec![
+                "changed",
+                "--check=a",
+                "--check=b",
+                "--jobs=2",
+                "--staged",
+                "--",
+                "-lib/a.dart"
+            ]
+        );
+
+        let arguments = json!({ "verbose": true });
+        let error = command_args(&TOOL, command, arguments.as_object().unwrap()).unwrap_err();
+        assert_eq!(error.code, INVALID_PARAMS);
+    }
+}
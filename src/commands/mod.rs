@@ -2,5 +2,21 @@ pub mod check;
 pub mod dart;
 pub mod files;
 pub mod git;
+pub mod graphql;
 pub mod hook;
+pub mod mcp;
 pub mod upgrade;
+
+/// Error for commands that report failure through the exit code only. Any
+/// explanation has already been printed (typically only with `--verbose`),
+/// so `cli::run` exits with status 1 without printing anything else.
+#[derive(Debug)]
+pub struct SilentFailure;
+
+impl std::fmt::Display for SilentFailure {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "command failed")
+    }
+}
+
+impl std::error::Error for SilentFailure {}
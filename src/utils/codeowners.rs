@@ -0,0 +1,907 @@
+use anyhow::{Context, Result};
+use regex::Regex;
+use serde::Serialize;
+use serde_yaml::Value;
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::Path;
+use std::sync::OnceLock;
+
+/// Where GitHub looks for the CODEOWNERS file, relative to the repository
+/// root. The first one found is used.
+pub const CODEOWNERS_LOCATIONS: &[&str] = &[".github/CODEOWNERS", "CODEOWNERS", "docs/CODEOWNERS"];
+
+/// Where `generate` output goes, relative to the repository root.
+pub const GENERATED_CODEOWNERS: &str = ".github/CODEOWNERS";
+
+/// Name of the files declaring the owners of their directory.
+pub const OWNERS_FILE: &str = "OWNERS";
+
+/// Key of a pubspec declaring the owners of its package.
+const PUBSPEC_KEY: &str = "codeowners";
+
+const GENERATED_HEADER: &str = "\
+# Generated by `mobdev git codeowners generate` from the OWNERS files and the
+# `codeowners` key of pubspecs. Do not edit: change those and regenerate.
+";
+
+/// A line of a CODEOWNERS file: a pattern and the owners of the files it
+/// matches. A rule without owners makes the files unowned.
+#[derive(Debug, Clone)]
+pub struct Rule {
+    pub pattern: String,
+    pub owners: Vec<String>,
+    /// Line number in the file, from 1.
+    pub line: usize,
+    regex: Regex,
+}
+
+impl Rule {
+    /// Creates a rule from a pattern in gitignore style, as GitHub
+    /// interprets it.
+    pub fn new(pattern: &str, owners: Vec<String>, line: usize) -> Result<Self> {
+        Ok(Self {
+            pattern: pattern.to_string(),
+            owners,
+            line,
+            regex: pattern_regex(pattern)?,
+        })
+    }
+
+    /// Whether the rule applies to a file, given relative to the root.
+    pub fn is_match(&self, path: &str) -> bool {
+        self.regex.is_match(path.trim_start_matches("./"))
+    }
+}
+
+/// A problem in a CODEOWNERS line. GitHub ignores such lines.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ParseError {
+    pub line: usize,
+    pub message: String,
+}
+
+/// A parsed CODEOWNERS file.
+#[derive(Debug, Clone, Default)]
+pub struct Codeowners {
+    /// Path of the file relative to the root, when read from one.
+    pub path: Option<String>,
+    pub rules: Vec<Rule>,
+    /// Lines that were left out because they are invalid.
+    pub errors: Vec<ParseError>,
+}
+
+impl Codeowners {
+    /// Reads the CODEOWNERS file of the repository at `root`, from the first
+    /// of `CODEOWNERS_LOCATIONS` that exists.
+    pub fn find(root: &Path) -> Result<Option<Self>> {
+        for location in CODEOWNERS_LOCATIONS {
+            let path = root.join(location);
+            if path.is_file() {
+                let text = std::fs::read_to_string(&path)
+                    .with_context(|| format!("Failed to read {}", path.display()))?;
+                let mut codeowners = Self::parse(&text);
+                codeowners.path = Some(location.to_string());
+                return Ok(Some(codeowners));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Parses a CODEOWNERS file. Invalid lines are recorded in `errors` and
+    /// left out, as GitHub does.
+    pub fn parse(text: &str) -> Self {
+        let mut codeowners = Self::default();
+
+        for (index, raw) in text.lines().enumerate() {
+            let line = index + 1;
+            let mut fields = split_fields(strip_comment(raw)).into_iter();
+            let Some(pattern) = fields.next() else {
+                continue;
+            };
+
+            let mut owners = Vec::new();
+            let mut invalid = None;
+            for owner in fields {
+                if is_valid_owner(&owner) {
+                    owners.push(owner);
+                } else {
+                    invalid = Some(owner);
+                    break;
+                }
+            }
+
+            let error = match invalid {
+                Some(owner) => Some(format!("Invalid owner \"{}\"", owner)),
+                None => unsupported_syntax(&pattern),
+            };
+            if let Some(message) = error {
+                codeowners.errors.push(ParseError { line, message });
+                continue;
+            }
+
+            match Rule::new(&pattern, owners, line) {
+                Ok(rule) => codeowners.rules.push(rule),
+                Err(e) => codeowners.errors.push(ParseError {
+                    line,
+                    message: format!("{:#}", e),
+                }),
+            }
+        }
+
+        codeowners
+    }
+
+    /// The rule deciding the owners of a file: the last one matching it.
+    pub fn rule_for(&self, path: &str) -> Option<&Rule> {
+        self.rules.iter().rev().find(|rule| rule.is_match(path))
+    }
+
+    /// The owners of a file; empty when no rule with owners matches it.
+    pub fn owners_of(&self, path: &str) -> &[String] {
+        self.rule_for(path)
+            .map(|rule| rule.owners.as_slice())
+            .unwrap_or(&[])
+    }
+
+    /// Audits the rules against the files of the repository, and their
+    /// owners against a roster of known owners, if any.
+    pub fn audit(&self, files: &[String], roster: Option<&BTreeSet<String>>) -> Audit {
+        let mut matched = vec![false; self.rules.len()];
+        let mut decides = vec![false; self.rules.len()];
+        let mut shadowed_by = vec![BTreeSet::new(); self.rules.len()];
+        let mut unowned = Vec::new();
+
+        for file in files {
+            let hits: Vec<usize> = (0..self.rules.len())
+                .filter(|&i| self.rules[i].is_match(file))
+                .collect();
+            let Some((&decider, earlier)) = hits.split_last() else {
+                unowned.push(file.clone());
+                continue;
+            };
+
+            decides[decider] = true;
+            for &i in earlier {
+                matched[i] = true;
+                shadowed_by[i].insert(self.rules[decider].line);
+            }
+            matched[decider] = true;
+            if self.rules[decider].owners.is_empty() {
+                unowned.push(file.clone());
+            }
+        }
+
+        let mut dead_rules = Vec::new();
+        let mut shadowed_rules = Vec::new();
+        for (i, rule) in self.rules.iter().enumerate() {
+            if !matched[i] {
+                dead_rules.push(RuleSummary::of(rule));
+            } else if !decides[i] {
+                shadowed_rules.push(ShadowedRule {
+                    rule: RuleSummary::of(rule),
+                    shadowed_by: shadowed_by[i].iter().copied().collect(),
+                });
+            }
+        }
+
+        let unknown_owners = roster.map(|roster| {
+            let mut unknown: BTreeMap<&str, Vec<usize>> = BTreeMap::new();
+            for rule in &self.rules {
+                for owner in &rule.owners {
+                    if !roster.contains(&owner.to_lowercase()) {
+                        unknown.entry(owner).or_default().push(rule.line);
+                    }
+                }
+            }
+            unknown
+                .into_iter()
+                .map(|(owner, lines)| UnknownOwner {
+                    owner: owner.to_string(),
+                    lines,
+                })
+                .collect()
+        });
+
+        Audit {
+            files: files.len(),
+            unowned,
+            dead_rules,
+            shadowed_rules,
+            unknown_owners,
+            errors: self.errors.clone(),
+        }
+    }
+}
+
+/// A rule as reported by an audit.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RuleSummary {
+    pub line: usize,
+    pub pattern: String,
+    pub owners: Vec<String>,
+}
+
+impl RuleSummary {
+    fn of(rule: &Rule) -> Self {
+        Self {
+            line: rule.line,
+            pattern: rule.pattern.clone(),
+            owners: rule.owners.clone(),
+        }
+    }
+}
+
+/// A rule that matches files, all of which later rules decide.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ShadowedRule {
+    #[serde(flatten)]
+    pub rule: RuleSummary,
+    /// Lines of the rules deciding the files instead.
+    pub shadowed_by: Vec<usize>,
+}
+
+/// An owner missing from the roster, with the lines naming it.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct UnknownOwner {
+    pub owner: String,
+    pub lines: Vec<usize>,
+}
+
+/// What `Codeowners::audit` found.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Audit {
+    /// Number of files audited.
+    pub files: usize,
+    /// Files without owners: matched by no rule, or decided by a rule
+    /// without owners.
+    pub unowned: Vec<String>,
+    /// Rules matching no file.
+    pub dead_rules: Vec<RuleSummary>,
+    /// Rules whose files are all decided by later rules.
+    pub shadowed_rules: Vec<ShadowedRule>,
+    /// Owners missing from the roster; `None` without a roster.
+    pub unknown_owners: Option<Vec<UnknownOwner>>,
+    pub errors: Vec<ParseError>,
+}
+
+impl Audit {
+    pub fn is_clean(&self) -> bool {
+        self.unowned.is_empty()
+            && self.dead_rules.is_empty()
+            && self.shadowed_rules.is_empty()
+            && self.unknown_owners.iter().flatten().next().is_none()
+            && self.errors.is_empty()
+    }
+}
+
+/// Reads a roster: one owner per line, optionally followed by a note; blank
+/// lines and lines starting with `#` are ignored. Owners are compared
+/// without case, as GitHub does.
+pub fn parse_roster(text: &str) -> BTreeSet<String> {
+    text.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .filter_map(|line| line.split_whitespace().next())
+        .map(str::to_lowercase)
+        .collect()
+}
+
+/// A problem in an ownership declaration, in the file declaring it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SourceError {
+    pub file: String,
+    pub line: usize,
+    pub message: String,
+}
+
+impl std::fmt::Display for SourceError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}: {}", self.file, self.line, self.message)
+    }
+}
+
+/// A CODEOWNERS file generated from the ownership declarations.
+#[derive(Debug, Clone, Default)]
+pub struct Generated {
+    pub text: String,
+    /// Files declaring owners, relative to the root.
+    pub sources: Vec<String>,
+    /// Declarations left out because they are invalid.
+    pub errors: Vec<SourceError>,
+}
+
+/// The owners declared for one directory.
+#[derive(Debug, Default)]
+struct Declarations {
+    sources: Vec<String>,
+    /// Owners of the whole directory.
+    owners: Vec<String>,
+    /// Patterns relative to the directory, with their owners.
+    patterns: Vec<(String, Vec<String>)>,
+}
+
+/// Generates a CODEOWNERS file from the `OWNERS` files and the pubspecs with
+/// a `codeowners` key among `files`, given relative to `root`.
+///
+/// Directories come in path order, so that a parent comes before its
+/// subdirectories and the closest declaration wins. Within a directory, its
+/// owners come before its patterns, which override them.
+pub fn generate(root: &Path, files: &[String]) -> Result<Generated> {
+    let mut directories: BTreeMap<String, Declarations> = BTreeMap::new();
+    let mut errors = Vec::new();
+
+    for file in files {
+        let (dir, name) = file.rsplit_once('/').unwrap_or(("", file));
+        if name != OWNERS_FILE && name != "pubspec.yaml" {
+            continue;
+        }
+
+        let text = std::fs::read_to_string(root.join(file))
+            .with_context(|| format!("Failed to read {}", file))?;
+        let (declared, file_errors) = if name == OWNERS_FILE {
+            parse_owners_file(&text)
+        } else {
+            parse_pubspec(&text)
+        };
+        errors.extend(file_errors.into_iter().map(|e| SourceError {
+            file: file.clone(),
+            line: e.line,
+            message: e.message,
+        }));
+
+        let Some(declared) = declared else {
+            continue;
+        };
+        let declarations = directories.entry(dir.to_string()).or_default();
+        declarations.sources.push(file.clone());
+        for owner in declared.owners {
+            if !declarations.owners.contains(&owner) {
+                declarations.owners.push(owner);
+            }
+        }
+        declarations.patterns.extend(declared.patterns);
+    }
+
+    let mut text = GENERATED_HEADER.to_string();
+    for (dir, declarations) in &directories {
+        text.push_str(&format!("\n# {}\n", declarations.sources.join(", ")));
+        if !declarations.owners.is_empty() {
+            let pattern = if dir.is_empty() {
+                "*".to_string()
+            } else {
+                format!("/{}/", dir)
+            };
+            text.push_str(&rule_line(&pattern, &declarations.owners));
+        }
+        for (pattern, owners) in &declarations.patterns {
+            text.push_str(&rule_line(&relative_pattern(dir, pattern), owners));
+        }
+    }
+
+    Ok(Generated {
+        text,
+        sources: directories.into_values().flat_map(|d| d.sources).collect(),
+        errors,
+    })
+}
+
+fn rule_line(pattern: &str, owners: &[String]) -> String {
+    let pattern = pattern.replace(' ', "\\ ").replace('#', "\\#");
+    format!("{} {}\n", pattern, owners.join(" "))
+}
+
+/// Makes a pattern of an `OWNERS` file in `dir` relative to the root. Like
+/// CODEOWNERS patterns, one without a `/` but at the end matches at any depth
+/// below the directory.
+fn relative_pattern(dir: &str, pattern: &str) -> String {
+    if dir.is_empty() {
+        pattern.to_string()
+    } else if pattern.starts_with('/') {
+        format!("/{}{}", dir, pattern)
+    } else if pattern.trim_end_matches('/').contains('/') {
+        format!("/{}/{}", dir, pattern)
+    } else {
+        format!("/{}/**/{}", dir, pattern)
+    }
+}
+
+/// Parses an `OWNERS` file. A line of owners owns the directory; a line
+/// starting with a pattern gives the owners of the files it matches.
+fn parse_owners_file(text: &str) -> (Option<Declarations>, Vec<ParseError>) {
+    let mut declarations = Declarations::default();
+    let mut errors = Vec::new();
+
+    for (index, raw) in text.lines().enumerate() {
+        let line = index + 1;
+        let fields = split_fields(strip_comment(raw));
+        let Some(first) = fields.first() else {
+            continue;
+        };
+
+        let (pattern, owners) = if is_valid_owner(first) {
+            (None, fields.as_slice())
+        } else {
+            (Some(first), &fields[1..])
+        };
+
+        let error = if let Some(owner) = owners.iter().find(|o| !is_valid_owner(o)) {
+            Some(format!("Invalid owner \"{}\"", owner))
+        } else if let Some(pattern) = pattern {
+            unsupported_syntax(pattern).or_else(|| {
+                owners
+                    .is_empty()
+                    .then(|| format!("Pattern \"{}\" has no owners", pattern))
+            })
+        } else {
+            None
+        };
+        if let Some(message) = error {
+            errors.push(ParseError { line, message });
+            continue;
+        }
+
+        match pattern {
+            Some(pattern) => declarations
+                .patterns
+                .push((pattern.clone(), owners.to_vec())),
+            None => declarations.owners.extend(owners.iter().cloned()),
+        }
+    }
+
+    let declared = !declarations.owners.is_empty() || !declarations.patterns.is_empty();
+    (declared.then_some(declarations), errors)
+}
+
+/// Reads the owners in the `codeowners` key of a pubspec: a list of owners
+/// or a string of them separated by spaces. Pubspecs without the key declare
+/// nothing.
+fn parse_pubspec(text: &str) -> (Option<Declarations>, Vec<ParseError>) {
+    let line = text
+        .lines()
+        .position(|l| l.starts_with(&format!("{}:", PUBSPEC_KEY)))
+        .map(|index| index + 1);
+    let Some(line) = line else {
+        return (None, Vec::new());
+    };
+    let error = |message: String| (None, vec![ParseError { line, message }]);
+
+    let value = match serde_yaml::from_str::<Value>(text) {
+        Ok(value) => value.get(PUBSPEC_KEY).cloned().unwrap_or(Value::Null),
+        Err(e) => return error(format!("Invalid pubspec: {}", e)),
+    };
+    let owners: Vec<String> = match &value {
+        Value::String(owners) => owners.split_whitespace().map(str::to_string).collect(),
+        Value::Sequence(owners) => {
+            let owners: Option<Vec<String>> = owners
+                .iter()
+                .map(|o| o.as_str().map(str::to_string))
+                .collect();
+            match owners {
+                Some(owners) => owners,
+                None => return error(format!("`{}` must list owners as strings", PUBSPEC_KEY)),
+            }
+        }
+        _ => {
+            return error(format!(
+                "`{}` must be a list of owners or a string of them",
+                PUBSPEC_KEY
+            ))
+        }
+    };
+
+    if let Some(owner) = owners.iter().find(|o| !is_valid_owner(o)) {
+        return error(format!("Invalid owner \"{}\"", owner));
+    }
+    if owners.is_empty() {
+        return error(format!("`{}` has no owners", PUBSPEC_KEY));
+    }
+
+    let declarations = Declarations {
+        owners,
+        ..Declarations::default()
+    };
+    (Some(declarations), Vec::new())
+}
+
+/// Removes a comment: from a `#` at the start of the line or after
+/// whitespace, unless escaped as `\#`.
+fn strip_comment(line: &str) -> &str {
+    let mut previous: Option<char> = None;
+    for (index, c) in line.char_indices() {
+        let after_whitespace = match previous {
+            None => true,
+            Some(p) => p.is_whitespace(),
+        };
+        if c == '#' && after_whitespace {
+            return &line[..index];
+        }
+        previous = Some(c);
+    }
+    line
+}
+
+/// Splits a line on whitespace, keeping escaped spaces (`\ `) in fields and
+/// unescaping `\#`.
+fn split_fields(line: &str) -> Vec<String> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut chars = line.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' if matches!(chars.peek(), Some(' ') | Some('#')) => {
+                field.push(chars.next().expect("peeked"));
+            }
+            c if c.is_whitespace() => {
+                if !field.is_empty() {
+                    fields.push(std::mem::take(&mut field));
+                }
+            }
+            c => field.push(c),
+        }
+    }
+    if !field.is_empty() {
+        fields.push(field);
+    }
+
+    fields
+}
+
+/// Whether an owner is a `@user`, an `@org/team` or an email address.
+pub fn is_valid_owner(owner: &str) -> bool {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    PATTERN
+        .get_or_init(|| {
+            Regex::new(
+                r"^(?:@[A-Za-z0-9](?:[A-Za-z0-9-]*[A-Za-z0-9])?(?:/[A-Za-z0-9_.-]+)?|[^@\s]+@[^@\s]+\.[^@\s]+)$",
+            )
+            .expect("valid regex")
+        })
+        .is_match(owner)
+}
+
+/// The gitignore syntax GitHub does not support in CODEOWNERS.
+fn unsupported_syntax(pattern: &str) -> Option<String> {
+    if pattern.starts_with('!') {
+        Some(format!("Negated pattern \"{}\" is not supported", pattern))
+    } else if pattern.contains('[') || pattern.contains(']') {
+        Some(format!(
+            "Character ranges in \"{}\" are not supported",
+            pattern
+        ))
+    } else {
+        None
+    }
+}
+
+/// Translates a CODEOWNERS pattern to a regex over relative file paths.
+///
+/// As in gitignore, a pattern with a `/` at the start or in the middle is
+/// relative to the root, otherwise it matches at any depth, and a pattern
+/// matching a directory matches everything below it. A trailing `/` matches
+/// directories only. As GitHub documents, `dir/*` matches the files directly
+/// in `dir` but not those further down.
+fn pattern_regex(pattern: &str) -> Result<Regex> {
+    let directory_only = pattern.ends_with('/');
+    let trimmed = pattern.trim_end_matches('/');
+    let anchored = trimmed.contains('/');
+    let body = trimmed.trim_start_matches('/');
+
+    let mut regex = String::from(if anchored { "^" } else { "^(?:.*/)?" });
+    let mut chars = body.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '*' if chars.peek() == Some(&'*') => {
+                chars.next();
+                if chars.peek() == Some(&'/') {
+                    chars.next();
+                    regex.push_str("(?:.*/)?");
+                } else {
+                    regex.push_str(".*");
+                }
+            }
+            '*' => regex.push_str("[^/]*"),
+            '?' => regex.push_str("[^/]"),
+            c => regex.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+
+    regex.push_str(if directory_only {
+        "/.*$"
+    } else if body.ends_with("/*") {
+        "$"
+    } else {
+        "(?:/.*)?$"
+    });
+
+    Regex::new(&regex).with_context(|| format!("Invalid pattern \"{}\"", pattern))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matches(pattern: &str, path: &str) -> bool {
+        Rule::new(pattern, Vec::new(), 1).unwrap().is_match(path)
+    }
+
+    #[test]
+    fn test_patterns() {
+        // Examples from GitHub's CODEOWNERS documentation.
+        assert!(matches("*", "any/file.txt"));
+        assert!(matches("*.js", "src/app.js"));
+        assert!(!matches("*.js", "src/app.jsx"));
+        assert!(matches("/build/logs/", "build/logs/a/b.log"));
+        assert!(!matches("/build/logs/", "src/build/logs/a.log"));
+        assert!(matches("docs/*", "docs/getting-started.md"));
+        assert!(!matches("docs/*", "docs/build-app/troubleshooting.md"));
+        assert!(matches("apps/", "apps/a.dart"));
+        assert!(matches("apps/", "packages/apps/a.dart"));
+        assert!(matches("/docs/", "docs/a/b.md"));
+        assert!(!matches("/docs/", "app/docs/a.md"));
+        assert!(matches("**/logs", "deeply/nested/logs/a.log"));
+        assert!(matches("**/logs", "logs/a.log"));
+        assert!(matches("/scripts/", "scripts/a.sh"));
+        assert!(matches("app/lib", "app/lib/src/a.dart"));
+        assert!(!matches("app/lib", "packages/app/lib/a.dart"));
+        assert!(matches("lib", "packages/app/lib/a.dart"));
+        assert!(matches("app/**/test", "app/x/y/test/a_test.dart"));
+        assert!(matches("README.md", "./README.md"));
+    }
+
+    #[test]
+    fn test_parse() {
+        let codeowners = Codeowners::parse(
+            "\
+# Default owners
+*       @acme/mobile   # everything else
+
+*.dart  @dart-dev dev@example.com
+/app/   @acme/app-team
+/app/generated/
+docs/\\#notes.md @writer
+my\\ file.txt @writer
+!keep.txt @acme/mobile
+*.[ch]  @c-dev
+/ios/   not-an-owner
+",
+        );
+
+        let rules: Vec<(&str, Vec<&str>, usize)> = codeowners
+            .rules
+            .iter()
+            .map(|r| {
+                (
+                    r.pattern.as_str(),
+                    r.owners.iter().map(String::as_str).collect(),
+                    r.line,
+                )
+            })
+            .collect();
+        assert_eq!(
+            rules,
+            vec![
+                ("*", vec!["@acme/mobile"], 2),
+                ("*.dart", vec!["@dart-dev", "dev@example.com"], 4),
+                ("/app/", vec!["@acme/app-team"], 5),
+                ("/app/generated/", vec![], 6),
+                ("docs/#notes.md", vec!["@writer"], 7),
+                ("my file.txt", vec!["@writer"], 8),
+            ]
+        );
+        assert_eq!(
+            codeowners
+                .errors
+                .iter()
+                .map(|e| (e.line, e.message.as_str()))
+                .collect::<Vec<_>>(),
+            vec![
+                (9, "Negated pattern \"!keep.txt\" is not supported"),
+                (10, "Character ranges in \"*.[ch]\" are not supported"),
+                (11, "Invalid owner \"not-an-owner\""),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_last_match_wins() {
+        let codeowners = Codeowners::parse(
+            "* @acme/mobile\n*.dart @dart-dev\n/app/ @acme/app-team\n/app/generated/\n",
+        );
+
+        assert_eq!(codeowners.owners_of("README.md"), ["@acme/mobile"]);
+        assert_eq!(codeowners.owners_of("core/lib/a.dart"), ["@dart-dev"]);
+        assert_eq!(codeowners.owners_of("app/lib/a.dart"), ["@acme/app-team"]);
+        assert!(codeowners.owners_of("app/generated/a.dart").is_empty());
+        assert_eq!(codeowners.rule_for("app/generated/a.dart").unwrap().line, 4);
+        assert!(Codeowners::default().rule_for("a.dart").is_none());
+    }
+
+    #[test]
+    fn test_find_uses_the_first_location() {
+        let dir = tempfile::tempdir().unwrap();
+        assert!(Codeowners::find(dir.path()).unwrap().is_none());
+
+        std::fs::create_dir_all(dir.path().join("docs")).unwrap();
+        std::fs::write(dir.path().join("docs/CODEOWNERS"), "* @docs\n").unwrap();
+        std::fs::write(dir.path().join("CODEOWNERS"), "* @root\n").unwrap();
+
+        let codeowners = Codeowners::find(dir.path()).unwrap().unwrap();
+        assert_eq!(codeowners.path.as_deref(), Some("CODEOWNERS"));
+        assert_eq!(codeowners.owners_of("a.dart"), ["@root"]);
+    }
+
+    #[test]
+    fn test_generate() {
+        let dir = tempfile::tempdir().unwrap();
+        let files = [
+            ("OWNERS", "# Everything else\n@acme/mobile\n"),
+            ("README.md", "# @not-an-owner\n"),
+            (
+                "app/OWNERS",
+                "@acme/app-team\n*.graphql @acme/api\nlib/generated/ @acme/api\n/docs/ writer@example.com\n",
+            ),
+            (
+                "app/pubspec.yaml",
+                "name: app\ncodeowners:\n  - \"@acme/app-team\"\n  - \"@alice\"\n",
+            ),
+            ("packages/core/pubspec.yaml", "name: core\ncodeowners: \"@acme/core @bob\"\n"),
+            ("packages/ui/pubspec.yaml", "name: ui\n"),
+            ("packages/bad/OWNERS", "nobody\n@acme/ui\n*.[ch] @c-dev\n"),
+        ];
+        for (path, text) in files {
+            let path = dir.path().join(path);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, text).unwrap();
+        }
+        let mut paths: Vec<String> = files.iter().map(|(p, _)| p.to_string()).collect();
+        paths.sort();
+
+        let generated = generate(dir.path(), &paths).unwrap();
+        assert_eq!(
+            generated.text,
+            format!(
+                "{}\n# OWNERS\n* @acme/mobile\n\n# app/OWNERS, app/pubspec.yaml\n/app/ @acme/app-team @alice\n/app/**/*.graphql @acme/api\n/app/lib/generated/ @acme/api\n/app/docs/ writer@example.com\n\n# packages/bad/OWNERS\n/packages/bad/ @acme/ui\n\n# packages/core/pubspec.yaml\n/packages/core/ @acme/core @bob\n",
+                GENERATED_HEADER
+            )
+        );
+        assert_eq!(
+            generated.sources,
+            [
+                "OWNERS",
+                "app/OWNERS",
+                "app/pubspec.yaml",
+                "packages/bad/OWNERS",
+                "packages/core/pubspec.yaml"
+            ]
+        );
+        assert_eq!(
+            generated
+                .errors
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>(),
+            [
+                "packages/bad/OWNERS:1: Pattern \"nobody\" has no owners",
+                "packages/bad/OWNERS:3: Character ranges in \"*.[ch]\" are not supported",
+            ]
+        );
+
+        // The generated file parses cleanly, and the closest declaration wins.
+        let codeowners = Codeowners::parse(&generated.text);
+        assert!(codeowners.errors.is_empty(), "{:?}", codeowners.errors);
+        assert_eq!(codeowners.owners_of("tool/a.sh"), ["@acme/mobile"]);
+        assert_eq!(
+            codeowners.owners_of("app/lib/a.dart"),
+            ["@acme/app-team", "@alice"]
+        );
+        assert_eq!(codeowners.owners_of("app/lib/src/q.graphql"), ["@acme/api"]);
+        assert_eq!(
+            codeowners.owners_of("packages/core/lib/c.dart"),
+            ["@acme/core", "@bob"]
+        );
+        assert_eq!(
+            codeowners.owners_of("packages/ui/lib/u.dart"),
+            ["@acme/mobile"]
+        );
+    }
+
+    #[test]
+    fn test_parse_pubspec() {
+        let owners = |text: &str| {
+            let (declarations, errors) = parse_pubspec(text);
+            (
+                declarations.map(|d| d.owners),
+                errors
+                    .into_iter()
+                    .map(|e| (e.line, e.message))
+                    .collect::<Vec<_>>(),
+            )
+        };
+
+        assert_eq!(owners("name: app\n"), (None, vec![]));
+        assert_eq!(
+            owners("name: app\ncodeowners: \"@a dev@example.com\"\n"),
+            (
+                Some(vec!["@a".to_string(), "dev@example.com".to_string()]),
+                vec![]
+            )
+        );
+        assert_eq!(
+            owners("name: app\ncodeowners: [a]\n"),
+            (None, vec![(2, "Invalid owner \"a\"".to_string())])
+        );
+        assert_eq!(
+            owners("name: app\ncodeowners: {team: \"@a\"}\n"),
+            (
+                None,
+                vec![(
+                    2,
+                    "`codeowners` must be a list of owners or a string of them".to_string()
+                )]
+            )
+        );
+    }
+
+    #[test]
+    fn test_audit() {
+        let codeowners = Codeowners::parse(
+            "\
+*.md @acme/mobile
+*.dart @Dart-Dev
+/old/ @acme/old
+/app/ @acme/app-team @bob
+/app/generated/
+/app/ @acme/app-team
+*.lock
+",
+        );
+        let files: Vec<String> = [
+            "README.md",
+            "app/lib/a.dart",
+            "app/generated/g.dart",
+            "app/pubspec.lock",
+            "tool/run.sh",
+        ]
+        .iter()
+        .map(|f| f.to_string())
+        .collect();
+        let roster = parse_roster("# Teams\n@acme/mobile\n@acme/app-team  App\n@dart-dev\n");
+
+        let audit = codeowners.audit(&files, Some(&roster));
+        assert_eq!(audit.files, 5);
+        assert_eq!(audit.unowned, ["app/pubspec.lock", "tool/run.sh"]);
+        assert_eq!(
+            audit.dead_rules.iter().map(|r| r.line).collect::<Vec<_>>(),
+            [3]
+        );
+        assert_eq!(
+            audit
+                .shadowed_rules
+                .iter()
+                .map(|r| (r.rule.line, r.shadowed_by.clone()))
+                .collect::<Vec<_>>(),
+            [(2, vec![6]), (4, vec![6, 7]), (5, vec![6])]
+        );
+        assert_eq!(
+            audit.unknown_owners,
+            Some(vec![
+                UnknownOwner {
+                    owner: "@acme/old".to_string(),
+                    lines: vec![3]
+                },
+                UnknownOwner {
+                    owner: "@bob".to_string(),
+                    lines: vec![4]
+                },
+            ])
+        );
+        assert!(!audit.is_clean());
+
+        // Without a roster, owners are not checked.
+        assert_eq!(codeowners.audit(&files, None).unknown_owners, None);
+
+        let codeowners = Codeowners::parse("* @acme/mobile\n/app/ @acme/app-team\n");
+        assert!(codeowners.audit(&files, Some(&roster)).is_clean());
+    }
+}
@@ -0,0 +1,330 @@
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::time::Duration;
+
+/// Name of the per-repository configuration file, looked up at the git root.
+pub const CONFIG_FILE_NAME: &str = "mobdev.yaml";
+
+/// Repository configuration loaded from `mobdev.yaml`.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub graphql: GraphqlConfig,
+    /// Repository-specific checks run by `hook collate`.
+    pub checks: Vec<CustomCheck>,
+    pub hooks: HooksConfig,
+    pub collate: CollateConfig,
+    pub llm: LlmConfig,
+    #[serde(rename = "commit-msg")]
+    pub commit_msg: CommitMsgConfig,
+    #[serde(rename = "pr-description")]
+    pub pr_description: PrDescriptionConfig,
+    pub secrets: SecretsConfig,
+    pub codeowners: CodeownersConfig,
+}
+
+/// The generator used by commands that write text with a language model.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct LlmConfig {
+    /// Shell command reading a prompt on stdin and printing the generated
+    /// text (default: `claude -p`).
+    pub command: Option<String>,
+    /// Longest diff put in a prompt, in characters.
+    pub max_diff_chars: Option<usize>,
+    /// Longest diff put in a prompt, in tokens of about four characters.
+    pub max_diff_tokens: Option<usize>,
+}
+
+/// Diff budget used when neither `max-diff-chars` nor `max-diff-tokens` is
+/// set: about 12k tokens.
+const DEFAULT_DIFF_BUDGET: usize = 48_000;
+
+impl LlmConfig {
+    /// The diff budget in characters; the smaller one when both limits are
+    /// set.
+    pub fn diff_budget(&self) -> usize {
+        let tokens = self.max_diff_tokens.map(|t| t.saturating_mul(4));
+        match (self.max_diff_chars, tokens) {
+            (Some(chars), Some(tokens)) => chars.min(tokens),
+            (Some(limit), None) | (None, Some(limit)) => limit,
+            (None, None) => DEFAULT_DIFF_BUDGET,
+        }
+    }
+}
+
+/// Settings for `git commit-msg`.
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct CommitMsgConfig {
+    /// Prompt template replacing the built-in one, relative to the git root.
+    pub template: Option<String>,
+    /// Allowed commit types.
+    pub types: Vec<String>,
+    /// Longest allowed subject line, in characters.
+    pub max_subject_length: usize,
+}
+
+impl Default for CommitMsgConfig {
+    fn default() -> Self {
+        Self {
+            template: None,
+            types: ["feat", "fix", "docs", "test", "refactor", "chore"]
+                .iter()
+                .map(|t| t.to_string())
+                .collect(),
+            max_subject_length: 72,
+        }
+    }
+}
+
+/// Settings for `git pr-description`.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PrDescriptionConfig {
+    /// Prompt template replacing the built-in one, relative to the git root.
+    pub template: Option<String>,
+}
+
+/// Default allowlist of `hook secrets check`, relative to the git root.
+pub const DEFAULT_SECRETS_ALLOWLIST: &str = ".secrets-allowlist";
+
+/// Settings for `hook secrets check`.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SecretsConfig {
+    /// File listing the fingerprints of allowed findings, relative to the
+    /// git root (default: `.secrets-allowlist`).
+    pub allowlist: Option<String>,
+}
+
+/// Settings for `git codeowners`.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CodeownersConfig {
+    /// File listing the known owners, one per line, relative to the git
+    /// root. `git codeowners audit` reports the owners missing from it.
+    pub roster: Option<String>,
+}
+
+/// Settings for `hook collate`.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CollateConfig {
+    /// Seconds after which any check is stopped and reported as timed out.
+    pub timeout: Option<u64>,
+    /// Timeouts of individual checks, by check name, overriding `timeout`.
+    pub timeouts: BTreeMap<String, u64>,
+    pub skip: SkipConfig,
+}
+
+impl CollateConfig {
+    /// The configured timeout of a check, if any; 0 means none.
+    pub fn check_timeout(&self, name: &str) -> Option<Duration> {
+        self.timeouts
+            .get(name)
+            .copied()
+            .or(self.timeout)
+            .filter(|&seconds| seconds > 0)
+            .map(Duration::from_secs)
+    }
+}
+
+/// Restrictions on skipping checks with `MOBDEV_SKIP` or `Mobdev-Skip`.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct SkipConfig {
+    /// Globs of the branches on which skipping is restricted.
+    pub protected_branches: Vec<String>,
+    /// Checks that cannot be skipped on protected branches; all checks when
+    /// not set.
+    pub forbid: Option<Vec<String>>,
+}
+
+/// Settings for `hook graphql check`.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct GraphqlConfig {
+    /// Shell command that regenerates the fakes of a package, run from the
+    /// package root. `{output}` is replaced with the directory to write into.
+    pub command: Option<String>,
+    /// Path of the schema file used by `graphql validate`, relative to the git root.
+    pub schema: Option<String>,
+}
+
+/// Commands run by the git hooks that `hook install` writes. Each is a shell
+/// command receiving the hook's arguments as `"$@"`; an empty command
+/// disables the hook.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct HooksConfig {
+    pub pre_commit: Option<String>,
+    pub pre_push: Option<String>,
+    pub commit_msg: Option<String>,
+    pub post_checkout: Option<String>,
+}
+
+/// A check declared in `mobdev.yaml` that runs a shell command over the
+/// changed files matching its globs.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+pub struct CustomCheck {
+    pub name: String,
+    /// Shell command to run. `{files}` is replaced with the matching files,
+    /// shell-escaped and relative to the directory the command runs in.
+    pub command: String,
+    /// Globs selecting the changed files the check runs on (default: all files).
+    #[serde(default)]
+    pub include: Vec<String>,
+    /// Globs excluding files selected by `include`.
+    #[serde(default)]
+    pub exclude: Vec<String>,
+    /// Whether the command runs once per Dart package or once per file.
+    #[serde(default)]
+    pub run: CheckScope,
+    /// Seconds after which the check is stopped and reported as timed out,
+    /// overriding `collate.timeout`.
+    pub timeout: Option<u64>,
+    /// Whether the command may modify files, which keeps it from running
+    /// concurrently with other checks.
+    #[serde(default)]
+    pub modifies_files: bool,
+}
+
+/// How a custom check's command is run over the matching files.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum CheckScope {
+    /// Once per Dart package, from the package root. Files outside any
+    /// package are run from the git root.
+    #[default]
+    PerPackage,
+    /// Once per file, from the git root.
+    PerFile,
+}
+
+/// Loads the configuration file from the given repository root.
+/// Returns the default configuration if the file does not exist.
+pub fn load_config<P: AsRef<Path>>(root: P) -> Result<Config> {
+    let path = root.as_ref().join(CONFIG_FILE_NAME);
+
+    if !path.exists() {
+        return Ok(Config::default());
+    }
+
+    let contents = std::fs::read_to_string(&path)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+
+    if contents.trim().is_empty() {
+        return Ok(Config::default());
+    }
+
+    serde_yaml::from_str(&contents).with_context(|| format!("Invalid {}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_load_config_missing_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = load_config(dir.path()).unwrap();
+        assert!(config.graphql.command.is_none());
+    }
+
+    #[test]
+    fn test_load_config_graphql() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            dir.path().join(CONFIG_FILE_NAME),
+            "graphql:\n  command: melos run fakes -- --out {output}\n",
+        )
+        .unwrap();
+
+        let config = load_config(dir.path()).unwrap();
+        assert_eq!(
+            config.graphql.command.as_deref(),
+            Some("melos run fakes -- --out {output}")
+        );
+    }
+
+    #[test]
+    fn test_load_config_checks() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            dir.path().join(CONFIG_FILE_NAME),
+            "checks:\n  - name: no-print\n    command: ./scripts/no_print.sh {files}\n    include: ['*.dart']\n    run: per-file\n    timeout: 30\n  - name: sort-imports\n    command: ./scripts/sort_imports.sh {files}\n    modifies-files: true\n",
+        )
+        .unwrap();
+
+        let config = load_config(dir.path()).unwrap();
+        let check = &config.checks[0];
+        assert_eq!(check.name, "no-print");
+        assert_eq!(check.include, vec!["*.dart"]);
+        assert_eq!(check.run, CheckScope::PerFile);
+        assert_eq!(check.timeout, Some(30));
+        assert!(!check.modifies_files);
+        assert!(config.checks[1].modifies_files);
+
+        std::fs::write(
+            dir.path().join(CONFIG_FILE_NAME),
+            "checks:\n  - name: a\n    command: a\n    modifies_files: true\n",
+        )
+        .unwrap();
+        assert!(load_config(dir.path()).is_err());
+    }
+
+    #[test]
+    fn test_load_config_diff_budget() {
+        let dir = tempfile::tempdir().unwrap();
+        assert_eq!(
+            load_config(dir.path()).unwrap().llm.diff_budget(),
+            DEFAULT_DIFF_BUDGET
+        );
+
+        std::fs::write(
+            dir.path().join(CONFIG_FILE_NAME),
+            "llm:\n  max-diff-tokens: 1000\n",
+        )
+        .unwrap();
+        assert_eq!(load_config(dir.path()).unwrap().llm.diff_budget(), 4000);
+
+        std::fs::write(
+            dir.path().join(CONFIG_FILE_NAME),
+            "llm:\n  max-diff-chars: 3000\n  max-diff-tokens: 1000\n",
+        )
+        .unwrap();
+        assert_eq!(load_config(dir.path()).unwrap().llm.diff_budget(), 3000);
+    }
+
+    #[test]
+    fn test_load_config_check_timeout() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            dir.path().join(CONFIG_FILE_NAME),
+            "collate:\n  timeout: 60\n  timeouts:\n    GraphQL: 0\n    secrets: 5\n",
+        )
+        .unwrap();
+        let collate = load_config(dir.path()).unwrap().collate;
+
+        assert_eq!(
+            collate.check_timeout("secrets"),
+            Some(Duration::from_secs(5))
+        );
+        assert_eq!(collate.check_timeout("GraphQL"), None);
+        assert_eq!(
+            collate.check_timeout("dart format"),
+            Some(Duration::from_secs(60))
+        );
+    }
+
+    #[test]
+    fn test_load_config_rejects_unknown_keys() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join(CONFIG_FILE_NAME), "grapql: {}\n").unwrap();
+        assert!(load_config(dir.path()).is_err());
+    }
+}
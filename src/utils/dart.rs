@@ -1,3 +1,4 @@
+use std::collections::BTreeMap;
 use std::path::{Path, PathBuf};
 
 /// Checks if the given directory is in a Dart package.
@@ -55,6 +56,42 @@ pub fn is_generated_dart_file(file: &str) -> bool {
         .any(|suffix| file.ends_with(suffix))
 }
 
+/// Groups files, given relative to `root`, by the Dart package containing
+/// them. Files outside any package are dropped.
+pub fn group_by_package(root: &Path, files: &[String]) -> BTreeMap<PathBuf, Vec<String>> {
+    let mut packages: BTreeMap<PathBuf, Vec<String>> = BTreeMap::new();
+
+    for file in files {
+        if let Some(package) = find_file_package_root(root.join(file)) {
+            packages.entry(package).or_default().push(file.clone());
+        }
+    }
+
+    packages
+}
+
+/// Sections of `pubspec.yaml` that can declare dependencies.
+const DEPENDENCY_SECTIONS: &[&str] = &["dependencies", "dev_dependencies", "dependency_overrides"];
+
+/// Reads the `path:` dependencies of the package at `package`, as paths
+/// joined onto it. A missing or malformed `pubspec.yaml` has none.
+pub fn path_dependencies(package: &Path) -> Vec<PathBuf> {
+    let Ok(text) = std::fs::read_to_string(package.join("pubspec.yaml")) else {
+        return Vec::new();
+    };
+    let Ok(pubspec) = serde_yaml::from_str::<serde_yaml::Value>(&text) else {
+        return Vec::new();
+    };
+
+    DEPENDENCY_SECTIONS
+        .iter()
+        .filter_map(|section| pubspec.get(section)?.as_mapping())
+        .flat_map(|dependencies| dependencies.values())
+        .filter_map(|dependency| dependency.get("path")?.as_str())
+        .map(|path| package.join(path))
+        .collect()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -65,4 +102,20 @@ mod tests {
         assert!(is_generated_dart_file("freezed.freezed.dart"));
         assert!(!is_generated_dart_file("normal.dart"));
     }
+
+    #[test]
+    fn test_path_dependencies() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            dir.path().join("pubspec.yaml"),
+            "name: app\ndependencies:\n  http: ^1.0.0\n  core:\n    path: ../core\ndev_dependencies:\n  lints: any\ndependency_overrides:\n  ui:\n    path: packages/ui\n",
+        )
+        .unwrap();
+
+        assert_eq!(
+            path_dependencies(dir.path()),
+            vec![dir.path().join("../core"), dir.path().join("packages/ui")]
+        );
+        assert!(path_dependencies(&dir.path().join("missing")).is_empty());
+    }
 }
@@ -0,0 +1,517 @@
+use crate::utils::dart::is_generated_dart_file;
+
+/// Lockfiles, whose changes follow from the manifests next to them.
+const LOCKFILES: &[&str] = &[
+    "pubspec.lock",
+    "Podfile.lock",
+    "Gemfile.lock",
+    "Cargo.lock",
+    "package-lock.json",
+    "yarn.lock",
+    "pnpm-lock.yaml",
+    "composer.lock",
+    "gradle.lockfile",
+];
+
+/// Suffixes of generated files, besides generated Dart files.
+const GENERATED_SUFFIXES: &[&str] = &[".pb.dart", ".pbenum.dart", ".mocks.dart", ".min.js"];
+
+/// Why a file's changes are left out of a prompt.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Omission {
+    Generated,
+    Lockfile,
+    Binary,
+}
+
+impl std::fmt::Display for Omission {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Omission::Generated => write!(f, "generated"),
+            Omission::Lockfile => write!(f, "lockfile"),
+            Omission::Binary => write!(f, "binary"),
+        }
+    }
+}
+
+/// The changes of one file in a unified diff.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FileDiff {
+    pub path: String,
+    /// Lines from `diff --git` up to the first hunk.
+    pub header: String,
+    /// Hunks, each starting with its `@@` line.
+    pub hunks: Vec<String>,
+    pub binary: bool,
+}
+
+impl FileDiff {
+    /// Added and removed lines.
+    pub fn line_counts(&self) -> (usize, usize) {
+        self.hunks
+            .iter()
+            .map(|hunk| hunk_line_counts(hunk))
+            .fold((0, 0), |(a, r), (added, removed)| (a + added, r + removed))
+    }
+
+    /// Why the file's changes are not worth showing, if they are not.
+    pub fn omission(&self) -> Option<Omission> {
+        let name = self.path.rsplit('/').next().unwrap_or(&self.path);
+        if self.binary {
+            Some(Omission::Binary)
+        } else if LOCKFILES.contains(&name) {
+            Some(Omission::Lockfile)
+        } else if is_generated_dart_file(&self.path)
+            || GENERATED_SUFFIXES.iter().any(|s| self.path.ends_with(s))
+        {
+            Some(Omission::Generated)
+        } else {
+            None
+        }
+    }
+
+    /// What the header says changed, for files without hunks: renames,
+    /// copies, mode changes, and added or deleted empty files.
+    fn header_change(&self) -> String {
+        let lines: Vec<&str> = self.header.lines().collect();
+        let find = |prefix: &str| lines.iter().find_map(|line| line.strip_prefix(prefix));
+
+        if let Some(from) = find("rename from ") {
+            format!("renamed from {}", from)
+        } else if let Some(from) = find("copy from ") {
+            format!("copied from {}", from)
+        } else if find("new file mode").is_some() {
+            "new empty file".to_string()
+        } else if find("deleted file mode").is_some() {
+            "deleted empty file".to_string()
+        } else if find("old mode").is_some() {
+            "mode changed".to_string()
+        } else {
+            "no content changes".to_string()
+        }
+    }
+
+    /// A `--stat` like line: `lib/a.dart | +3 -1`.
+    fn stat_line(&self) -> String {
+        if self.binary {
+            return format!(" {} | binary", self.path);
+        }
+        let (added, removed) = self.line_counts();
+        format!(" {} | +{} -{}", self.path, added, removed)
+    }
+}
+
+fn hunk_line_counts(hunk: &str) -> (usize, usize) {
+    hunk.lines().skip(1).fold((0, 0), |(a, r), line| {
+        if line.starts_with('+') {
+            (a + 1, r)
+        } else if line.starts_with('-') {
+            (a, r + 1)
+        } else {
+            (a, r)
+        }
+    })
+}
+
+/// Splits the output of `git diff` into files and hunks.
+pub fn parse_diff(diff: &str) -> Vec<FileDiff> {
+    let mut files: Vec<FileDiff> = Vec::new();
+
+    for line in diff.split_inclusive('\n') {
+        if let Some(paths) = line.strip_prefix("diff --git ") {
+            let path = paths
+                .trim_end()
+                .rsplit_once(" b/")
+                .map(|(_, path)| path.to_string())
+                .unwrap_or_else(|| paths.trim_end().to_string());
+            files.push(FileDiff {
+                path,
+                header: line.to_string(),
+                hunks: Vec::new(),
+                binary: false,
+            });
+            continue;
+        }
+
+        let Some(file) = files.last_mut() else {
+            continue;
+        };
+        if line.starts_with("@@") {
+            file.hunks.push(line.to_string());
+        } else if let Some(hunk) = file.hunks.last_mut() {
+            hunk.push_str(line);
+        } else {
+            if line.starts_with("Binary files ") || line.starts_with("GIT binary patch") {
+                file.binary = true;
+            }
+            file.header.push_str(line);
+        }
+    }
+
+    files
+}
+
+/// Prepares a diff for a prompt of at most `budget` characters.
+///
+/// Generated files, lockfiles and binaries are left out with a stat line
+/// each. When the rest does not fit, hunks are picked by relevance until the
+/// budget is used, shown in their original order with an elision marker
+/// where hunks were left out, and the files without any hunk shown are
+/// summarized by their stat line. That includes files changed without hunks,
+/// such as renames, which are summarized by what changed. Stat lines that do not fit either are cut
+/// short with an elision marker. The budget counts characters, not bytes.
+pub fn budget_diff(diff: &str, budget: usize) -> String {
+    let (omitted, files): (Vec<FileDiff>, Vec<FileDiff>) = parse_diff(diff)
+        .into_iter()
+        .partition(|file| file.omission().is_some());
+
+    let omitted_section = if omitted.is_empty() {
+        String::new()
+    } else {
+        let lines: Vec<String> = omitted
+            .iter()
+            .map(|file| {
+                let reason = file.omission().expect("omitted files have a reason");
+                format!("{} ({})\n", file.stat_line(), reason)
+            })
+            .collect();
+        let room = budget.saturating_sub(char_len(OMITTED_HEADING));
+        format!("{}{}", OMITTED_HEADING, fit_lines(&lines, room))
+    };
+
+    let full: String = files
+        .iter()
+        .map(|file| format!("{}{}", file.header, file.hunks.concat()))
+        .collect();
+    if char_len(&full) + char_len(&omitted_section) <= budget {
+        return format!("{}{}", full, omitted_section);
+    }
+
+    // Reserve room to summarize every file, then fill the rest with hunks.
+    let summary_room =
+        budget.saturating_sub(char_len(&omitted_section) + char_len(SUMMARY_HEADING));
+    let summaries: usize = files.iter().map(|f| char_len(&summary_line(f))).sum();
+    let shown = pick_hunks(&files, summary_room.saturating_sub(summaries));
+
+    let mut text = String::new();
+    let mut summarized = Vec::new();
+    for (index, file) in files.iter().enumerate() {
+        let hunks: Vec<&String> = file
+            .hunks
+            .iter()
+            .enumerate()
+            .filter(|(h, _)| shown.contains(&(index, *h)))
+            .map(|(_, hunk)| hunk)
+            .collect();
+        if hunks.is_empty() {
+            summarized.push(file);
+            continue;
+        }
+
+        text.push_str(&file.header);
+        for hunk in &hunks {
+            text.push_str(hunk);
+        }
+        let elided = file.hunks.len() - hunks.len();
+        if elided > 0 {
+            text.push_str(&format!(
+                "[... {} of {} hunks omitted to fit the diff budget ...]\n",
+                elided,
+                file.hunks.len()
+            ));
+        }
+    }
+
+    if !summarized.is_empty() {
+        let lines: Vec<String> = summarized.into_iter().map(summary_line).collect();
+        text.push_str(SUMMARY_HEADING);
+        text.push_str(&fit_lines(&lines, summary_room));
+    }
+    text.push_str(&omitted_section);
+
+    text
+}
+
+/// Heading of the files left out by their kind.
+const OMITTED_HEADING: &str = "\nOmitted from the diff:\n";
+
+/// Heading of the files summarized because none of their hunks fit.
+const SUMMARY_HEADING: &str = "\nNot shown to fit the diff budget:\n";
+
+/// Room left in each shown file for its elision marker.
+const ELISION_MARKER_ROOM: usize = 64;
+
+/// Length in characters, which the budget counts, rather than bytes.
+fn char_len(text: &str) -> usize {
+    text.chars().count()
+}
+
+/// Joins as many `lines` as fit in `budget` characters, followed by an
+/// elision marker counting the lines left out.
+fn fit_lines(lines: &[String], budget: usize) -> String {
+    if lines.iter().map(|line| char_len(line)).sum::<usize>() <= budget {
+        return lines.concat();
+    }
+
+    let marker = |left: usize| {
+        format!(
+            "[... {} more file{} omitted to fit the diff budget ...]\n",
+            left,
+            if left == 1 { "" } else { "s" }
+        )
+    };
+    let mut text = String::new();
+    let mut used = 0;
+    for (i, line) in lines.iter().enumerate() {
+        let cost = char_len(line);
+        if used + cost + char_len(&marker(lines.len() - i - 1)) > budget {
+            text.push_str(&marker(lines.len() - i));
+            break;
+        }
+        used += cost;
+        text.push_str(line);
+    }
+
+    text
+}
+
+fn summary_line(file: &FileDiff) -> String {
+    let detail = match file.hunks.len() {
+        0 => file.header_change(),
+        1 => "1 hunk".to_string(),
+        hunks => format!("{} hunks", hunks),
+    };
+    format!("{} ({})\n", file.stat_line(), detail)
+}
+
+/// Picks the most relevant hunks fitting in `budget` characters, counting
+/// the header of each file shown, as `(file, hunk)` indexes.
+fn pick_hunks(files: &[FileDiff], budget: usize) -> Vec<(usize, usize)> {
+    let mut candidates: Vec<(usize, usize, usize)> = files
+        .iter()
+        .enumerate()
+        .flat_map(|(f, file)| {
+            let weight = path_weight(&file.path);
+            file.hunks.iter().enumerate().map(move |(h, hunk)| {
+                let (added, removed) = hunk_line_counts(hunk);
+                (f, h, weight * (added + removed))
+            })
+        })
+        .collect();
+    // Most relevant first; the sort is stable, so ties keep diff order.
+    candidates.sort_by_key(|&(_, _, score)| std::cmp::Reverse(score));
+
+    let mut shown = Vec::new();
+    let mut used = 0;
+    for (f, h, _) in candidates {
+        let file = &files[f];
+        // The first hunk shown of a file brings its header and room for its
+        // elision marker.
+        let header = if shown.iter().any(|(shown_f, _)| *shown_f == f) {
+            0
+        } else {
+            char_len(&file.header) + ELISION_MARKER_ROOM
+        };
+        let cost = header + char_len(&file.hunks[h]);
+        if used + cost <= budget {
+            used += cost;
+            shown.push((f, h));
+        }
+    }
+
+    shown
+}
+
+/// How much a change to a file matters for describing it: source code over
+/// tests, and tests over documentation and configuration.
+fn path_weight(path: &str) -> usize {
+    let name = path.rsplit('/').next().unwrap_or(path);
+    let is_test = path.starts_with("test/")
+        || path.contains("/test/")
+        || path.starts_with("integration_test/")
+        || path.contains("/integration_test/")
+        || name.contains("_test.");
+    let is_source = [
+        ".dart", ".rs", ".kt", ".swift", ".java", ".ts", ".js", ".py",
+    ]
+    .iter()
+    .any(|ext| name.ends_with(ext));
+
+    match (is_source, is_test) {
+        (true, false) => 3,
+        (true, true) => 2,
+        _ => 1,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn file_diff(path: &str, hunks: &[&str]) -> String {
+        let mut text = format!(
+            "diff --git a/{path} b/{path}\nindex 1111111..2222222 100644\n--- a/{path}\n+++ b/{path}\n"
+        );
+        for (i, hunk) in hunks.iter().enumerate() {
+            text.push_str(&format!("@@ -{0},1 +{0},1 @@\n{1}", i * 10 + 1, hunk));
+        }
+        text
+    }
+
+    #[test]
+    fn test_parse_diff() {
+        let diff = format!(
+            "{}diff --git a/logo.png b/logo.png\nnew file mode 100644\nBinary files /dev/null and b/logo.png differ\n",
+            file_diff("lib/a.dart", &["-a\n+b\n c\n", "+d\n"])
+        );
+
+        let files = parse_diff(&diff);
+        assert_eq!(files.len(), 2);
+        assert_eq!(files[0].path, "lib/a.dart");
+        assert_eq!(files[0].hunks.len(), 2);
+        assert_eq!(files[0].line_counts(), (2, 1));
+        assert!(files[0].header.ends_with("+++ b/lib/a.dart\n"));
+        assert_eq!(files[0].omission(), None);
+        assert_eq!(files[1].path, "logo.png");
+        assert_eq!(files[1].omission(), Some(Omission::Binary));
+        assert_eq!(
+            files
+                .iter()
+                .map(|f| format!("{}{}", f.header, f.hunks.concat()))
+                .collect::<String>(),
+            diff
+        );
+    }
+
+    #[test]
+    fn test_budget_diff_omits_generated_files_and_lockfiles() {
+        let source = file_diff("lib/a.dart", &["+a\n"]);
+        let diff = format!(
+            "{}{}{}",
+            source,
+            file_diff("lib/a.g.dart", &["+generated\n"]),
+            file_diff("app/pubspec.lock", &["-1.0.0\n+1.1.0\n"])
+        );
+
+        assert_eq!(
+            budget_diff(&diff, 10_000),
+            format!(
+                "{}\nOmitted from the diff:\n lib/a.g.dart | +1 -0 (generated)\n app/pubspec.lock | +1 -1 (lockfile)\n",
+                source
+            )
+        );
+    }
+
+    #[test]
+    fn test_budget_diff_keeps_the_most_relevant_hunks() {
+        let big = "+x\n".repeat(40);
+        let diff = format!(
+            "{}{}{}",
+            file_diff("README.md", &[&"+doc\n".repeat(50)]),
+            file_diff("lib/a.dart", &[&"+y\n".repeat(20), &big]),
+            file_diff("test/a_test.dart", &[&big]),
+        );
+
+        let text = budget_diff(&diff, 480);
+        assert!(
+            char_len(&text) <= 480,
+            "{} > 480:\n{}",
+            char_len(&text),
+            text
+        );
+        assert!(text.contains(&big), "{}", text);
+        assert!(
+            text.contains("[... 1 of 2 hunks omitted to fit the diff budget ...]"),
+            "{}",
+            text
+        );
+        assert!(
+            text.contains(
+                "\nNot shown to fit the diff budget:\n README.md | +50 -0 (1 hunk)\n test/a_test.dart | +40 -0 (1 hunk)\n"
+            ),
+            "{}",
+            text
+        );
+
+        // Without room for any hunk, every file is summarized.
+        let text = budget_diff(&diff, 200);
+        assert!(!text.contains("@@"), "{}", text);
+        assert!(text.contains(" lib/a.dart | +60 -0 (2 hunks)"), "{}", text);
+    }
+
+    #[test]
+    fn test_budget_diff_counts_characters() {
+        let diff = file_diff("lib/a.dart", &[&"+é\n".repeat(30)]);
+        assert!(diff.len() > diff.chars().count());
+
+        assert_eq!(budget_diff(&diff, diff.chars().count()), diff);
+    }
+
+    #[test]
+    fn test_budget_diff_summarizes_files_without_hunks() {
+        let diff: String = (0..50)
+            .map(|i| {
+                format!(
+                    "diff --git a/lib/old_{i}.dart b/lib/new_{i}.dart\nsimilarity index 100%\nrename from lib/old_{i}.dart\nrename to lib/new_{i}.dart\n"
+                )
+            })
+            .chain(std::iter::once(
+                "diff --git a/run.sh b/run.sh\nold mode 100644\nnew mode 100755\n".to_string(),
+            ))
+            .collect();
+
+        // Within the budget, the headers are shown as they are.
+        assert_eq!(budget_diff(&diff, diff.len()), diff);
+
+        let text = budget_diff(&diff, 300);
+        assert!(
+            char_len(&text) <= 300,
+            "{} > 300:\n{}",
+            char_len(&text),
+            text
+        );
+        assert!(!text.contains("diff --git"), "{}", text);
+        assert!(
+            text.contains(" lib/new_0.dart | +0 -0 (renamed from lib/old_0.dart)\n"),
+            "{}",
+            text
+        );
+        assert!(
+            text.ends_with("more files omitted to fit the diff budget ...]\n"),
+            "{}",
+            text
+        );
+
+        let mode_change =
+            parse_diff("diff --git a/run.sh b/run.sh\nold mode 100644\nnew mode 100755\n");
+        assert_eq!(
+            summary_line(&mode_change[0]),
+            " run.sh | +0 -0 (mode changed)\n"
+        );
+    }
+
+    #[test]
+    fn test_budget_diff_cuts_summaries_short() {
+        let diff: String = (0..50)
+            .map(|i| file_diff(&format!("lib/file_{i}.dart"), &["+a\n"]))
+            .collect();
+
+        let text = budget_diff(&diff, 300);
+        assert!(
+            char_len(&text) <= 300,
+            "{} > 300:\n{}",
+            char_len(&text),
+            text
+        );
+        assert!(
+            text.contains(" lib/file_0.dart | +1 -0 (1 hunk)\n"),
+            "{}",
+            text
+        );
+        assert!(
+            text.ends_with("more files omitted to fit the diff budget ...]\n"),
+            "{}",
+            text
+        );
+    }
+}
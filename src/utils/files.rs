@@ -1,4 +1,6 @@
+use anyhow::{Context, Result};
 use std::io::{self, BufRead};
+use std::path::{Path, PathBuf};
 
 /// Filters files by suffix patterns.
 pub fn filter_files_by_suffix(suffixes: &[String], verbose: bool) -> Result<(), std::io::Error> {
@@ -26,6 +28,29 @@ pub fn filter_files_by_suffix(suffixes: &[String], verbose: bool) -> Result<(),
     Ok(())
 }
 
+/// Recursively lists the files below a directory, skipping directories
+/// whose name is in `skip_dirs`.
+pub fn list_files(dir: &Path, skip_dirs: &[&str]) -> Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+
+    for entry in
+        std::fs::read_dir(dir).with_context(|| format!("Failed to read {}", dir.display()))?
+    {
+        let path = entry?.path();
+        if path.is_dir() {
+            let name = path.file_name().unwrap_or_default().to_string_lossy();
+            if !skip_dirs.contains(&name.as_ref()) {
+                files.extend(list_files(&path, skip_dirs)?);
+            }
+        } else {
+            files.push(path);
+        }
+    }
+
+    files.sort();
+    Ok(files)
+}
+
 #[cfg(test)]
 mod tests {
     #[test]
@@ -1,6 +1,7 @@
 use anyhow::{Context, Result};
+use std::io::Write;
 use std::path::{Path, PathBuf};
-use std::process::Command;
+use std::process::{Command, Stdio};
 
 /// Checks if the given directory is inside a git repository.
 pub fn is_git_repo<P: AsRef<Path>>(cwd: Option<P>) -> bool {
@@ -105,6 +106,45 @@ fn git_diff_files<P: AsRef<Path>>(path: P, args: &[&str]) -> Result<Vec<String>>
     }
 }
 
+/// The changed files a check works on: the git scope selected by the
+/// `--staged`, `--unstaged` and `--all` flags (see `get_changed_files`), or
+/// exactly the files given, as for `hook pre-push`.
+#[derive(Debug, Clone, Default)]
+pub struct ChangeScope {
+    pub staged: bool,
+    pub unstaged: bool,
+    pub all: bool,
+    pub base_branch: String,
+    /// Files replacing the git scope, when known up front.
+    pub files: Option<Vec<String>>,
+}
+
+impl ChangeScope {
+    pub fn new(staged: bool, unstaged: bool, all: bool, base_branch: &str) -> Self {
+        Self {
+            staged,
+            unstaged,
+            all,
+            base_branch: base_branch.to_string(),
+            files: None,
+        }
+    }
+
+    /// Restricts the scope to exactly `files`.
+    pub fn with_files(mut self, files: Vec<String>) -> Self {
+        self.files = Some(files);
+        self
+    }
+
+    /// Gets the files in the scope.
+    pub fn changed_files<P: AsRef<Path>>(&self, cwd: Option<P>) -> Result<Vec<String>> {
+        match &self.files {
+            Some(files) => Ok(files.clone()),
+            None => get_changed_files(cwd, &self.base_branch, self.staged, self.unstaged, self.all),
+        }
+    }
+}
+
 /// Gets changed files in the repository.
 pub fn get_changed_files<P: AsRef<Path>>(
     cwd: Option<P>,
@@ -143,7 +183,9 @@ pub fn get_changed_files<P: AsRef<Path>>(
     Ok(files)
 }
 
-/// Gets files that would be pushed to upstream.
+/// Gets files that would be pushed to upstream. Without an upstream branch,
+/// the files changed since the merge-base with the default branch of
+/// `origin` are returned.
 pub fn get_files_to_push<P: AsRef<Path>>(cwd: Option<P>) -> Result<Vec<String>> {
     let path = cwd
         .as_ref()
@@ -159,33 +201,472 @@ pub fn get_files_to_push<P: AsRef<Path>>(cwd: Option<P>) -> Result<Vec<String>>
         .output()
         .context("Failed to get upstream branch")?;
 
-    if !output.status.success() {
-        anyhow::bail!("No upstream branch set");
+    let upstream = if output.status.success() {
+        String::from_utf8_lossy(&output.stdout).trim().to_string()
+    } else {
+        get_default_remote_branch(path, "origin")
+            .context("No upstream branch set, and no default branch found for origin")?
+    };
+
+    git_lines(
+        path,
+        &["diff", "--name-only", &format!("{}...HEAD", upstream)],
+    )
+    .context("Failed to get files to push")
+}
+
+/// The all-zero object name git uses for a missing side of a ref update.
+fn is_null_sha(sha: &str) -> bool {
+    !sha.is_empty() && sha.chars().all(|c| c == '0')
+}
+
+/// A ref update, as passed to pre-push hooks on stdin.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PushedRef {
+    pub local_ref: String,
+    pub local_sha: String,
+    pub remote_ref: String,
+    pub remote_sha: String,
+}
+
+impl PushedRef {
+    /// Whether the push deletes the remote ref.
+    pub fn is_deletion(&self) -> bool {
+        is_null_sha(&self.local_sha)
+    }
+
+    /// Whether the push creates the remote ref.
+    pub fn is_new(&self) -> bool {
+        is_null_sha(&self.remote_sha)
+    }
+}
+
+/// Parses the `<local ref> <local sha> <remote ref> <remote sha>` lines git
+/// passes to pre-push hooks.
+pub fn parse_pushed_refs(input: &str) -> Result<Vec<PushedRef>> {
+    input
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(
+            |line| match line.split_whitespace().collect::<Vec<_>>()[..] {
+                [local_ref, local_sha, remote_ref, remote_sha] => Ok(PushedRef {
+                    local_ref: local_ref.to_string(),
+                    local_sha: local_sha.to_string(),
+                    remote_ref: remote_ref.to_string(),
+                    remote_sha: remote_sha.to_string(),
+                }),
+                _ => anyhow::bail!("Invalid pre-push line: \"{}\"", line),
+            },
+        )
+        .collect()
+}
+
+/// Returns the default branch of a remote, e.g. `origin/main`, from
+/// `refs/remotes/<remote>/HEAD`, falling back to `<remote>/main` and
+/// `<remote>/master`.
+pub fn get_default_remote_branch<P: AsRef<Path>>(cwd: P, remote: &str) -> Option<String> {
+    let cwd = cwd.as_ref();
+
+    if let Ok(lines) = git_lines(
+        cwd,
+        &[
+            "symbolic-ref",
+            "--short",
+            &format!("refs/remotes/{}/HEAD", remote),
+        ],
+    ) {
+        if let Some(branch) = lines.into_iter().next() {
+            return Some(branch);
+        }
+    }
+
+    ["main", "master"]
+        .iter()
+        .map(|name| format!("{}/{}", remote, name))
+        .find(|branch| {
+            git_lines(
+                cwd,
+                &[
+                    "rev-parse",
+                    "--verify",
+                    "-q",
+                    &format!("refs/remotes/{}", branch),
+                ],
+            )
+            .is_ok()
+        })
+}
+
+/// Gets the files changed by the pushed refs. Deletions are skipped. A ref
+/// the remote already has is diffed from its merge-base with the remote
+/// commit; a new ref, or one whose remote commit is unknown locally, from its
+/// merge-base with `default_branch`, or in full without one.
+pub fn get_pushed_files<P: AsRef<Path>>(
+    cwd: P,
+    refs: &[PushedRef],
+    default_branch: Option<&str>,
+) -> Result<Vec<String>> {
+    let cwd = cwd.as_ref();
+    let mut files = Vec::new();
+
+    for pushed in refs.iter().filter(|r| !r.is_deletion()) {
+        files.extend(match pushed_base(cwd, pushed, default_branch) {
+            Some(base) => git_lines(
+                cwd,
+                &[
+                    "diff",
+                    "--name-only",
+                    &format!("{}...{}", base, pushed.local_sha),
+                ],
+            )?,
+            None => git_lines(cwd, &["ls-tree", "-r", "--name-only", &pushed.local_sha])?,
+        });
+    }
+
+    files.sort();
+    files.dedup();
+    files.retain(|s| !s.is_empty());
+
+    Ok(files)
+}
+
+/// Gets the untracked files that are not ignored.
+pub fn get_untracked_files<P: AsRef<Path>>(cwd: P) -> Result<Vec<String>> {
+    git_lines(
+        cwd.as_ref(),
+        &["ls-files", "--others", "--exclude-standard"],
+    )
+}
+
+/// Gets the files in the index, sorted.
+pub fn get_tracked_files<P: AsRef<Path>>(cwd: P) -> Result<Vec<String>> {
+    git_lines(cwd.as_ref(), &["ls-files"])
+}
+
+/// Gets the files of the working tree that git knows of or would add:
+/// tracked files that still exist and untracked files that are not ignored,
+/// sorted.
+pub fn get_worktree_files<P: AsRef<Path>>(cwd: P) -> Result<Vec<String>> {
+    let cwd = cwd.as_ref();
+    let mut files = git_lines(
+        cwd,
+        &["ls-files", "--cached", "--others", "--exclude-standard"],
+    )?;
+    files.retain(|file| cwd.join(file).is_file());
+    files.sort();
+    files.dedup();
+    Ok(files)
+}
+
+/// Gets the `git log` revision arguments selecting the commits of the pushed
+/// refs, compared as in `get_pushed_files`: `<base>..<sha>`, or, without a
+/// base, the commits of `<sha>` that no remote has (`--not --remotes`, which
+/// comes last as it applies to every revision after it).
+pub fn get_pushed_ranges<P: AsRef<Path>>(
+    cwd: P,
+    refs: &[PushedRef],
+    default_branch: Option<&str>,
+) -> Vec<String> {
+    let cwd = cwd.as_ref();
+    let mut unknown_base = false;
+    let mut ranges: Vec<String> = refs
+        .iter()
+        .filter(|r| !r.is_deletion())
+        .map(|pushed| match pushed_base(cwd, pushed, default_branch) {
+            Some(base) => format!("{}..{}", base, pushed.local_sha),
+            None => {
+                unknown_base = true;
+                pushed.local_sha.clone()
+            }
+        })
+        .collect();
+
+    if unknown_base {
+        ranges.extend(["--not".to_string(), "--remotes".to_string()]);
     }
+    ranges
+}
 
-    let upstream = String::from_utf8_lossy(&output.stdout).trim().to_string();
+/// The commit a pushed ref is compared with: the remote commit when known
+/// locally, otherwise the merge-base with `default_branch`.
+fn pushed_base(cwd: &Path, pushed: &PushedRef, default_branch: Option<&str>) -> Option<String> {
+    let known_remote = !pushed.is_new()
+        && git_lines(
+            cwd,
+            &[
+                "cat-file",
+                "-e",
+                &format!("{}^{{commit}}", pushed.remote_sha),
+            ],
+        )
+        .is_ok();
+
+    if known_remote {
+        Some(pushed.remote_sha.clone())
+    } else {
+        default_branch.and_then(|branch| {
+            git_lines(cwd, &["merge-base", branch, &pushed.local_sha])
+                .ok()
+                .and_then(|lines| lines.into_iter().next())
+        })
+    }
+}
 
-    // Get files in commits that would be pushed
+/// Gets the values of a trailer in the commits of `ranges`, with the
+/// abbreviated name of the commit carrying each.
+pub fn get_commit_trailers<P: AsRef<Path>>(
+    cwd: P,
+    ranges: &[String],
+    key: &str,
+) -> Result<Vec<(String, String)>> {
+    let format = format!(
+        "--format=%h%x1f%(trailers:key={},valueonly,separator=%x1f)",
+        key
+    );
+    let mut args = vec!["log", format.as_str()];
+    args.extend(ranges.iter().map(String::as_str));
+
+    Ok(git_lines(cwd.as_ref(), &args)?
+        .into_iter()
+        .flat_map(|line| {
+            let mut fields = line.split('\x1f').map(str::to_string);
+            let commit = fields.next().unwrap_or_default();
+            fields
+                .filter(|value| !value.trim().is_empty())
+                .map(move |value| (commit.clone(), value))
+                .collect::<Vec<_>>()
+        })
+        .collect())
+}
+
+/// Runs `git diff` with `args` and returns its output, without colors or
+/// external diff drivers.
+pub fn get_diff<P: AsRef<Path>>(cwd: P, args: &[&str]) -> Result<String> {
     let output = Command::new("git")
-        .arg("diff")
-        .arg("--name-only")
-        .arg(format!("{}...HEAD", upstream))
-        .current_dir(path)
+        .args(["diff", "--no-color", "--no-ext-diff"])
+        .args(args)
+        .current_dir(cwd.as_ref())
         .output()
         .context("Failed to execute git diff")?;
 
     if !output.status.success() {
-        anyhow::bail!("Failed to get files to push");
+        anyhow::bail!(
+            "git diff {} failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
     }
 
-    let file_list = String::from_utf8_lossy(&output.stdout);
-    let files: Vec<String> = file_list
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Finds the best common ancestor of two commits.
+pub fn get_merge_base<P: AsRef<Path>>(cwd: P, a: &str, b: &str) -> Result<String> {
+    git_lines(cwd.as_ref(), &["merge-base", a, b])?
+        .into_iter()
+        .next()
+        .with_context(|| format!("No merge-base between {} and {}", a, b))
+}
+
+/// Lists the commits of `range`, oldest first, as `<short sha> <subject>`.
+pub fn get_commit_log<P: AsRef<Path>>(cwd: P, range: &str) -> Result<Vec<String>> {
+    git_lines(
+        cwd.as_ref(),
+        &["log", "--reverse", "--no-decorate", "--format=%h %s", range],
+    )
+}
+
+/// Reads a file as of `revision`, or `None` when it does not exist there.
+pub fn get_file_at_revision<P: AsRef<Path>>(cwd: P, revision: &str, path: &str) -> Option<String> {
+    let output = Command::new("git")
+        .args(["show", &format!("{}:{}", revision, path)])
+        .current_dir(cwd.as_ref())
+        .output()
+        .ok()?;
+
+    output
+        .status
+        .success()
+        .then(|| String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Commits the staged changes with `message`. Commit hooks run as usual.
+pub fn commit<P: AsRef<Path>>(cwd: P, message: &str) -> Result<()> {
+    let mut child = Command::new("git")
+        .args(["commit", "-q", "-F", "-"])
+        .current_dir(cwd.as_ref())
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .context("Failed to execute git commit")?;
+
+    child
+        .stdin
+        .take()
+        .expect("stdin is piped")
+        .write_all(message.as_bytes())
+        .context("Failed to pass the message to git commit")?;
+
+    let output = child
+        .wait_with_output()
+        .context("Failed to execute git commit")?;
+
+    if !output.status.success() {
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        anyhow::bail!(
+            "git commit failed: {}",
+            format!("{}\n{}", stdout.trim(), stderr.trim()).trim()
+        );
+    }
+
+    Ok(())
+}
+
+/// Runs a git command and returns the non-empty lines of its output.
+fn git_lines(cwd: &Path, args: &[&str]) -> Result<Vec<String>> {
+    let output = Command::new("git")
+        .args(args)
+        .current_dir(cwd)
+        .output()
+        .context("Failed to execute git command")?;
+
+    if !output.status.success() {
+        anyhow::bail!(
+            "git {} failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout)
         .lines()
         .filter(|s| !s.is_empty())
         .map(|s| s.to_string())
-        .collect();
+        .collect())
+}
 
-    Ok(files)
+/// Resolves a path inside the repository's git directory, e.g. `mobdev/cache`.
+/// Works in linked worktrees, where `.git` is a file.
+pub fn get_git_path<P: AsRef<Path>>(cwd: P, path: &str) -> Result<PathBuf> {
+    let cwd = cwd.as_ref();
+
+    let output = Command::new("git")
+        .arg("rev-parse")
+        .arg("--git-path")
+        .arg(path)
+        .current_dir(cwd)
+        .output()
+        .context("Failed to execute git command")?;
+
+    if !output.status.success() {
+        anyhow::bail!("Not in a git repository");
+    }
+
+    let git_path = String::from_utf8(output.stdout)
+        .context("Invalid UTF-8 in git output")?
+        .trim()
+        .to_string();
+
+    Ok(cwd.join(git_path))
+}
+
+/// Computes the git blob hash of each file, in order.
+pub fn hash_files<P: AsRef<Path>>(cwd: P, files: &[PathBuf]) -> Result<Vec<String>> {
+    if files.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let paths: String = files.iter().map(|f| format!("{}\n", f.display())).collect();
+    let hashes = hash_object(cwd.as_ref(), &["--stdin-paths"], paths.as_bytes())?;
+
+    Ok(hashes.lines().map(|s| s.to_string()).collect())
+}
+
+/// Computes the git blob hash of some text.
+pub fn hash_text<P: AsRef<Path>>(cwd: P, text: &str) -> Result<String> {
+    Ok(hash_object(cwd.as_ref(), &["--stdin"], text.as_bytes())?
+        .trim()
+        .to_string())
+}
+
+/// Reads the mode and content of a file as staged in the index.
+pub fn get_staged_content<P: AsRef<Path>>(cwd: P, file: &str) -> Result<(String, Vec<u8>)> {
+    let cwd = cwd.as_ref();
+    let entry = git_lines(cwd, &["ls-files", "--stage", "--", file])?;
+    let Some(mode) = entry
+        .first()
+        .and_then(|line| line.split_whitespace().next())
+    else {
+        anyhow::bail!("{} is not staged", file);
+    };
+
+    let output = Command::new("git")
+        .arg("cat-file")
+        .arg("blob")
+        .arg(format!(":{}", file))
+        .current_dir(cwd)
+        .output()
+        .context("Failed to execute git cat-file")?;
+
+    if !output.status.success() {
+        anyhow::bail!(
+            "git cat-file failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+
+    Ok((mode.to_string(), output.stdout))
+}
+
+/// Stages `content` as the new content of a file, leaving the working tree
+/// untouched.
+pub fn stage_content<P: AsRef<Path>>(cwd: P, file: &str, mode: &str, content: &[u8]) -> Result<()> {
+    let cwd = cwd.as_ref();
+    let blob = hash_object(cwd, &["-w", "--stdin"], content)?;
+    git_lines(
+        cwd,
+        &[
+            "update-index",
+            "--cacheinfo",
+            &format!("{},{},{}", mode, blob.trim(), file),
+        ],
+    )
+    .map(|_| ())
+}
+
+fn hash_object(cwd: &Path, args: &[&str], input: &[u8]) -> Result<String> {
+    let mut child = Command::new("git")
+        .arg("hash-object")
+        .args(args)
+        .current_dir(cwd)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .context("Failed to execute git hash-object")?;
+
+    let mut stdin = child.stdin.take().expect("stdin is piped");
+    let input = input.to_vec();
+    let writer = std::thread::spawn(move || stdin.write_all(&input));
+
+    let output = child
+        .wait_with_output()
+        .context("Failed to execute git hash-object")?;
+    writer
+        .join()
+        .expect("writer thread does not panic")
+        .context("Failed to write to git hash-object")?;
+
+    if !output.status.success() {
+        anyhow::bail!(
+            "git hash-object failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+
+    String::from_utf8(output.stdout).context("Invalid UTF-8 in git output")
 }
 
 #[cfg(test)]
@@ -201,4 +682,51 @@ mod tests {
         // Just ensure it doesn't panic
         let _ = result;
     }
+
+    #[test]
+    fn test_parse_pushed_refs() {
+        let zeros = "0".repeat(40);
+        let input = format!(
+            "refs/heads/feature abc123 refs/heads/feature {}\nrefs/heads/main def456 refs/heads/main 789abc\n(delete) {} refs/heads/old fed321\n",
+            zeros, zeros
+        );
+
+        let refs = parse_pushed_refs(&input).unwrap();
+        assert_eq!(refs.len(), 3);
+        assert!(refs[0].is_new() && !refs[0].is_deletion());
+        assert_eq!(refs[1].remote_sha, "789abc");
+        assert!(!refs[1].is_new());
+        assert!(refs[2].is_deletion());
+
+        assert!(parse_pushed_refs("refs/heads/main abc\n").is_err());
+        assert!(parse_pushed_refs("").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_get_pushed_ranges_without_base() {
+        let dir = tempfile::tempdir().unwrap();
+        let git = |args: &[&str]| {
+            let mut all = vec!["-c", "user.name=Test", "-c", "user.email=test@example.com"];
+            all.extend(args);
+            git_lines(dir.path(), &all).unwrap()
+        };
+        git(&["init", "-q"]);
+        git(&["commit", "-q", "--allow-empty", "-m", "pushed before"]);
+        git(&["update-ref", "refs/remotes/origin/main", "HEAD"]);
+        git(&["commit", "-q", "--allow-empty", "-m", "new"]);
+        let head = git(&["rev-parse", "HEAD"]).remove(0);
+
+        let refs = parse_pushed_refs(&format!(
+            "refs/heads/topic {} refs/heads/topic {}\n",
+            head,
+            "0".repeat(40)
+        ))
+        .unwrap();
+        let ranges = get_pushed_ranges(dir.path(), &refs, None);
+        assert_eq!(ranges, vec![head, "--not".into(), "--remotes".into()]);
+
+        let mut args = vec!["log", "--format=%s"];
+        args.extend(ranges.iter().map(String::as_str));
+        assert_eq!(git(&args), vec!["new"]);
+    }
 }
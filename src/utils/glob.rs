@@ -0,0 +1,89 @@
+use anyhow::{Context, Result};
+use regex::Regex;
+
+/// A glob pattern matched against `/`-separated relative paths.
+///
+/// `*` matches within a path segment, `?` matches one character other than
+/// `/`, and `**` matches any number of segments. A pattern without a `/`
+/// matches the file name in any directory, so `*.dart` is `**/*.dart`.
+#[derive(Debug, Clone)]
+pub struct Glob {
+    regex: Regex,
+}
+
+impl Glob {
+    pub fn new(pattern: &str) -> Result<Self> {
+        let anchored = if pattern.contains('/') {
+            pattern.trim_start_matches('/').to_string()
+        } else {
+            format!("**/{}", pattern)
+        };
+
+        let regex = Regex::new(&glob_to_regex(&anchored))
+            .with_context(|| format!("Invalid glob \"{}\"", pattern))?;
+
+        Ok(Self { regex })
+    }
+
+    pub fn is_match(&self, path: &str) -> bool {
+        self.regex.is_match(path)
+    }
+}
+
+/// Compiles a list of patterns.
+pub fn compile(patterns: &[String]) -> Result<Vec<Glob>> {
+    patterns.iter().map(|p| Glob::new(p)).collect()
+}
+
+fn glob_to_regex(pattern: &str) -> String {
+    let mut regex = String::from("^");
+    let mut chars = pattern.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '*' if chars.peek() == Some(&'*') => {
+                chars.next();
+                if chars.peek() == Some(&'/') {
+                    // `**/` matches zero or more leading directories.
+                    chars.next();
+                    regex.push_str("(?:.*/)?");
+                } else {
+                    regex.push_str(".*");
+                }
+            }
+            '*' => regex.push_str("[^/]*"),
+            '?' => regex.push_str("[^/]"),
+            c => regex.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+
+    regex.push('$');
+    regex
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_glob_matching() {
+        let dart = Glob::new("*.dart").unwrap();
+        assert!(dart.is_match("main.dart"));
+        assert!(dart.is_match("app/lib/main.dart"));
+        assert!(!dart.is_match("app/lib/main.dart.orig"));
+
+        let lib = Glob::new("app/lib/**/*.dart").unwrap();
+        assert!(lib.is_match("app/lib/main.dart"));
+        assert!(lib.is_match("app/lib/src/a/b.dart"));
+        assert!(!lib.is_match("app/test/main_test.dart"));
+
+        let single = Glob::new("scripts/?.sh").unwrap();
+        assert!(single.is_match("scripts/a.sh"));
+        assert!(!single.is_match("scripts/ab.sh"));
+        assert!(!Glob::new("lib/*.dart").unwrap().is_match("lib/src/a.dart"));
+
+        let generated = Glob::new("**/generated/**").unwrap();
+        assert!(generated.is_match("app/lib/generated/a.dart"));
+        assert!(generated.is_match("generated/a.dart"));
+    }
+}
@@ -0,0 +1,260 @@
+//! Minimal GraphQL SDL and operation parser with schema validation.
+
+pub mod lexer;
+pub mod parser;
+pub mod schema;
+pub mod validate;
+
+pub use parser::parse;
+pub use schema::Schema;
+pub use validate::validate;
+
+/// A 1-based line and column position in a GraphQL document.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Pos {
+    pub line: usize,
+    pub column: usize,
+}
+
+/// A syntax error in a GraphQL document.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseError {
+    pub pos: Pos,
+    pub message: String,
+}
+
+impl ParseError {
+    pub fn new(pos: Pos, message: impl Into<String>) -> Self {
+        Self {
+            pos,
+            message: message.into(),
+        }
+    }
+}
+
+impl std::fmt::Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Syntax error: {}", self.message)
+    }
+}
+
+/// A type reference such as `String`, `[ID!]` or `User!`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Type {
+    Named(String),
+    List(Box<Type>),
+    NonNull(Box<Type>),
+}
+
+impl Type {
+    /// Returns the innermost named type.
+    pub fn name(&self) -> &str {
+        match self {
+            Type::Named(name) => name,
+            Type::List(inner) | Type::NonNull(inner) => inner.name(),
+        }
+    }
+
+    pub fn is_non_null(&self) -> bool {
+        matches!(self, Type::NonNull(_))
+    }
+}
+
+impl std::fmt::Display for Type {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Type::Named(name) => write!(f, "{}", name),
+            Type::List(inner) => write!(f, "[{}]", inner),
+            Type::NonNull(inner) => write!(f, "{}!", inner),
+        }
+    }
+}
+
+/// An input value literal.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Variable(String, Pos),
+    Int(String),
+    Float(String),
+    String(String),
+    Boolean(bool),
+    Null,
+    Enum(String),
+    List(Vec<Value>),
+    Object(Vec<(String, Value)>),
+}
+
+impl Value {
+    /// Collects the variables referenced by this value.
+    pub fn variables<'a>(&'a self, out: &mut Vec<(&'a str, Pos)>) {
+        match self {
+            Value::Variable(name, pos) => out.push((name, *pos)),
+            Value::List(values) => values.iter().for_each(|v| v.variables(out)),
+            Value::Object(fields) => fields.iter().for_each(|(_, v)| v.variables(out)),
+            _ => {}
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Argument {
+    pub name: String,
+    pub value: Value,
+    pub pos: Pos,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Directive {
+    pub name: String,
+    pub arguments: Vec<Argument>,
+    pub pos: Pos,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Field {
+    pub alias: Option<String>,
+    pub name: String,
+    pub arguments: Vec<Argument>,
+    pub directives: Vec<Directive>,
+    pub selection_set: Vec<Selection>,
+    pub pos: Pos,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Selection {
+    Field(Field),
+    FragmentSpread {
+        name: String,
+        directives: Vec<Directive>,
+        pos: Pos,
+    },
+    InlineFragment {
+        type_condition: Option<String>,
+        directives: Vec<Directive>,
+        selection_set: Vec<Selection>,
+        pos: Pos,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OperationKind {
+    Query,
+    Mutation,
+    Subscription,
+}
+
+impl std::fmt::Display for OperationKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            OperationKind::Query => write!(f, "query"),
+            OperationKind::Mutation => write!(f, "mutation"),
+            OperationKind::Subscription => write!(f, "subscription"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct VariableDefinition {
+    pub name: String,
+    pub ty: Type,
+    pub default: Option<Value>,
+    pub pos: Pos,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Operation {
+    pub kind: OperationKind,
+    pub name: Option<String>,
+    pub variables: Vec<VariableDefinition>,
+    pub directives: Vec<Directive>,
+    pub selection_set: Vec<Selection>,
+    pub pos: Pos,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Fragment {
+    pub name: String,
+    pub type_condition: String,
+    pub directives: Vec<Directive>,
+    pub selection_set: Vec<Selection>,
+    pub pos: Pos,
+}
+
+/// An argument or input field declared in the schema.
+#[derive(Debug, Clone, PartialEq)]
+pub struct InputValueDefinition {
+    pub name: String,
+    pub ty: Type,
+    pub has_default: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FieldDefinition {
+    pub name: String,
+    pub arguments: Vec<InputValueDefinition>,
+    pub ty: Type,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TypeKind {
+    Scalar,
+    Object,
+    Interface,
+    Union,
+    Enum,
+    InputObject,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TypeDefinition {
+    pub kind: TypeKind,
+    pub name: String,
+    pub interfaces: Vec<String>,
+    pub fields: Vec<FieldDefinition>,
+    pub members: Vec<String>,
+    pub input_fields: Vec<InputValueDefinition>,
+    /// Whether this is an `extend` of a type declared elsewhere.
+    pub extension: bool,
+    pub pos: Pos,
+}
+
+/// A top-level definition of a GraphQL document.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Definition {
+    Operation(Operation),
+    Fragment(Fragment),
+    Type(TypeDefinition),
+    /// A `schema { query: ... }` block, as `(operation, type)` pairs.
+    Schema(Vec<(OperationKind, String)>),
+    /// Directive definitions are accepted but not validated.
+    Directive,
+}
+
+/// A parsed GraphQL document.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Document {
+    pub definitions: Vec<Definition>,
+}
+
+impl Document {
+    pub fn operations(&self) -> impl Iterator<Item = &Operation> {
+        self.definitions.iter().filter_map(|d| match d {
+            Definition::Operation(op) => Some(op),
+            _ => None,
+        })
+    }
+
+    pub fn fragments(&self) -> impl Iterator<Item = &Fragment> {
+        self.definitions.iter().filter_map(|d| match d {
+            Definition::Fragment(fragment) => Some(fragment),
+            _ => None,
+        })
+    }
+
+    /// Whether the document declares any types, i.e. is (part of) a schema.
+    pub fn is_schema(&self) -> bool {
+        self.definitions
+            .iter()
+            .any(|d| matches!(d, Definition::Type(_) | Definition::Schema(_)))
+    }
+}
@@ -0,0 +1,214 @@
+use super::{ParseError, Pos};
+
+/// A lexical token of a GraphQL document.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Token {
+    /// One of `! $ & ( ) : = @ [ ] { | }`.
+    Punct(char),
+    /// The `...` spread operator.
+    Spread,
+    Name(String),
+    Int(String),
+    Float(String),
+    String(String),
+    Eof,
+}
+
+impl std::fmt::Display for Token {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Token::Punct(c) => write!(f, "\"{}\"", c),
+            Token::Spread => write!(f, "\"...\""),
+            Token::Name(name) => write!(f, "Name \"{}\"", name),
+            Token::Int(value) => write!(f, "Int \"{}\"", value),
+            Token::Float(value) => write!(f, "Float \"{}\"", value),
+            Token::String(_) => write!(f, "String"),
+            Token::Eof => write!(f, "<EOF>"),
+        }
+    }
+}
+
+/// Splits a GraphQL document into tokens, skipping whitespace, commas and comments.
+pub fn tokenize(source: &str) -> Result<Vec<(Token, Pos)>, ParseError> {
+    let chars: Vec<char> = source.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    let mut line = 1;
+    let mut line_start = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        let pos = Pos {
+            line,
+            column: i - line_start + 1,
+        };
+
+        match c {
+            '\n' => {
+                i += 1;
+                line += 1;
+                line_start = i;
+            }
+            ' ' | '\t' | '\r' | ',' | '\u{feff}' => i += 1,
+            '#' => {
+                while i < chars.len() && chars[i] != '\n' {
+                    i += 1;
+                }
+            }
+            '!' | '$' | '&' | '(' | ')' | ':' | '=' | '@' | '[' | ']' | '{' | '|' | '}' => {
+                tokens.push((Token::Punct(c), pos));
+                i += 1;
+            }
+            '.' => {
+                if chars.get(i + 1) == Some(&'.') && chars.get(i + 2) == Some(&'.') {
+                    tokens.push((Token::Spread, pos));
+                    i += 3;
+                } else {
+                    return Err(ParseError::new(pos, "Unexpected \".\""));
+                }
+            }
+            '"' => {
+                let block = chars.get(i + 1) == Some(&'"') && chars.get(i + 2) == Some(&'"');
+                let mut value = String::new();
+
+                if block {
+                    i += 3;
+                    loop {
+                        match chars.get(i) {
+                            None => return Err(ParseError::new(pos, "Unterminated string")),
+                            Some('"')
+                                if chars.get(i + 1) == Some(&'"')
+                                    && chars.get(i + 2) == Some(&'"') =>
+                            {
+                                i += 3;
+                                break;
+                            }
+                            Some(&ch) => {
+                                if ch == '\n' {
+                                    line += 1;
+                                    line_start = i + 1;
+                                }
+                                value.push(ch);
+                                i += 1;
+                            }
+                        }
+                    }
+                } else {
+                    i += 1;
+                    loop {
+                        match chars.get(i) {
+                            None | Some('\n') => {
+                                return Err(ParseError::new(pos, "Unterminated string"))
+                            }
+                            Some('"') => {
+                                i += 1;
+                                break;
+                            }
+                            Some('\\') => {
+                                if let Some(&escaped) = chars.get(i + 1) {
+                                    value.push(escaped);
+                                }
+                                i += 2;
+                            }
+                            Some(&ch) => {
+                                value.push(ch);
+                                i += 1;
+                            }
+                        }
+                    }
+                }
+
+                tokens.push((Token::String(value), pos));
+            }
+            c if c == '-' || c.is_ascii_digit() => {
+                let start = i;
+                let mut float = false;
+                i += 1;
+                while i < chars.len() {
+                    match chars[i] {
+                        '0'..='9' => {}
+                        '.' | 'e' | 'E' => float = true,
+                        '+' | '-' if matches!(chars[i - 1], 'e' | 'E') => {}
+                        _ => break,
+                    }
+                    i += 1;
+                }
+                let text: String = chars[start..i].iter().collect();
+                if text == "-" {
+                    return Err(ParseError::new(pos, "Unexpected \"-\""));
+                }
+                tokens.push((
+                    if float {
+                        Token::Float(text)
+                    } else {
+                        Token::Int(text)
+                    },
+                    pos,
+                ));
+            }
+            c if c == '_' || c.is_ascii_alphabetic() => {
+                let start = i;
+                while i < chars.len() && (chars[i] == '_' || chars[i].is_ascii_alphanumeric()) {
+                    i += 1;
+                }
+                tokens.push((Token::Name(chars[start..i].iter().collect()), pos));
+            }
+            other => {
+                return Err(ParseError::new(
+                    pos,
+                    format!("Unexpected character \"{}\"", other),
+                ))
+            }
+        }
+    }
+
+    tokens.push((
+        Token::Eof,
+        Pos {
+            line,
+            column: i - line_start + 1,
+        },
+    ));
+
+    Ok(tokens)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tokenize() {
+        let tokens: Vec<Token> = tokenize("query Q($id: ID!) { ...F # comment\n }")
+            .unwrap()
+            .into_iter()
+            .map(|(token, _)| token)
+            .collect();
+
+        assert_eq!(
+            tokens,
+            vec![
+                Token::Name("query".into()),
+                Token::Name("Q".into()),
+                Token::Punct('('),
+                Token::Punct('$'),
+                Token::Name("id".into()),
+                Token::Punct(':'),
+                Token::Name("ID".into()),
+                Token::Punct('!'),
+                Token::Punct(')'),
+                Token::Punct('{'),
+                Token::Spread,
+                Token::Name("F".into()),
+                Token::Punct('}'),
+                Token::Eof,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_tokenize_positions() {
+        let tokens = tokenize("{\n  id\n}").unwrap();
+        assert_eq!(tokens[1].1, Pos { line: 2, column: 3 });
+    }
+}
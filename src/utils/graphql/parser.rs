@@ -0,0 +1,590 @@
+use super::lexer::{tokenize, Token};
+use super::*;
+
+/// Parses a GraphQL document containing schema and/or executable definitions.
+pub fn parse(source: &str) -> Result<Document, ParseError> {
+    let mut parser = Parser {
+        tokens: tokenize(source)?,
+        index: 0,
+    };
+
+    let mut definitions = Vec::new();
+    while parser.peek() != &Token::Eof {
+        definitions.push(parser.definition()?);
+    }
+
+    Ok(Document { definitions })
+}
+
+struct Parser {
+    tokens: Vec<(Token, Pos)>,
+    index: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> &Token {
+        &self.tokens[self.index].0
+    }
+
+    fn pos(&self) -> Pos {
+        self.tokens[self.index].1
+    }
+
+    fn advance(&mut self) -> Token {
+        let token = self.tokens[self.index].0.clone();
+        if self.index < self.tokens.len() - 1 {
+            self.index += 1;
+        }
+        token
+    }
+
+    fn unexpected<T>(&self, expected: &str) -> Result<T, ParseError> {
+        Err(ParseError::new(
+            self.pos(),
+            format!("Expected {}, found {}", expected, self.peek()),
+        ))
+    }
+
+    fn is_punct(&self, c: char) -> bool {
+        self.peek() == &Token::Punct(c)
+    }
+
+    fn is_keyword(&self, keyword: &str) -> bool {
+        matches!(self.peek(), Token::Name(name) if name == keyword)
+    }
+
+    fn skip_punct(&mut self, c: char) -> bool {
+        if self.is_punct(c) {
+            self.advance();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect_punct(&mut self, c: char) -> Result<(), ParseError> {
+        if self.skip_punct(c) {
+            Ok(())
+        } else {
+            self.unexpected(&format!("\"{}\"", c))
+        }
+    }
+
+    fn expect_keyword(&mut self, keyword: &str) -> Result<(), ParseError> {
+        if self.is_keyword(keyword) {
+            self.advance();
+            Ok(())
+        } else {
+            self.unexpected(&format!("\"{}\"", keyword))
+        }
+    }
+
+    fn name(&mut self) -> Result<String, ParseError> {
+        match self.peek() {
+            Token::Name(_) => match self.advance() {
+                Token::Name(name) => Ok(name),
+                _ => unreachable!(),
+            },
+            _ => self.unexpected("Name"),
+        }
+    }
+
+    fn skip_description(&mut self) {
+        if matches!(self.peek(), Token::String(_)) {
+            self.advance();
+        }
+    }
+
+    fn definition(&mut self) -> Result<Definition, ParseError> {
+        if self.is_punct('{') {
+            let pos = self.pos();
+            return Ok(Definition::Operation(Operation {
+                kind: OperationKind::Query,
+                name: None,
+                variables: Vec::new(),
+                directives: Vec::new(),
+                selection_set: self.selection_set()?,
+                pos,
+            }));
+        }
+
+        self.skip_description();
+
+        let keyword = match self.peek() {
+            Token::Name(name) => name.clone(),
+            _ => return self.unexpected("a definition"),
+        };
+
+        match keyword.as_str() {
+            "query" | "mutation" | "subscription" => self.operation().map(Definition::Operation),
+            "fragment" => self.fragment().map(Definition::Fragment),
+            "extend" => {
+                self.advance();
+                self.type_system_definition(true)
+            }
+            _ => self.type_system_definition(false),
+        }
+    }
+
+    fn operation(&mut self) -> Result<Operation, ParseError> {
+        let pos = self.pos();
+        let kind = match self.name()?.as_str() {
+            "query" => OperationKind::Query,
+            "mutation" => OperationKind::Mutation,
+            _ => OperationKind::Subscription,
+        };
+
+        let name = if matches!(self.peek(), Token::Name(_)) {
+            Some(self.name()?)
+        } else {
+            None
+        };
+
+        let mut variables = Vec::new();
+        if self.skip_punct('(') {
+            while !self.skip_punct(')') {
+                let pos = self.pos();
+                self.expect_punct('$')?;
+                let name = self.name()?;
+                self.expect_punct(':')?;
+                let ty = self.type_ref()?;
+                let default = if self.skip_punct('=') {
+                    Some(self.value(true)?)
+                } else {
+                    None
+                };
+                self.directives()?;
+                variables.push(VariableDefinition {
+                    name,
+                    ty,
+                    default,
+                    pos,
+                });
+            }
+        }
+
+        Ok(Operation {
+            kind,
+            name,
+            variables,
+            directives: self.directives()?,
+            selection_set: self.selection_set()?,
+            pos,
+        })
+    }
+
+    fn fragment(&mut self) -> Result<Fragment, ParseError> {
+        let pos = self.pos();
+        self.expect_keyword("fragment")?;
+        let name = self.name()?;
+        self.expect_keyword("on")?;
+        let type_condition = self.name()?;
+
+        Ok(Fragment {
+            name,
+            type_condition,
+            directives: self.directives()?,
+            selection_set: self.selection_set()?,
+            pos,
+        })
+    }
+
+    fn selection_set(&mut self) -> Result<Vec<Selection>, ParseError> {
+        self.expect_punct('{')?;
+        let mut selections = Vec::new();
+
+        while !self.skip_punct('}') {
+            selections.push(self.selection()?);
+        }
+
+        Ok(selections)
+    }
+
+    fn selection(&mut self) -> Result<Selection, ParseError> {
+        let pos = self.pos();
+
+        if self.peek() == &Token::Spread {
+            self.advance();
+
+            if matches!(self.peek(), Token::Name(name) if name != "on") {
+                return Ok(Selection::FragmentSpread {
+                    name: self.name()?,
+                    directives: self.directives()?,
+                    pos,
+                });
+            }
+
+            let type_condition = if self.is_keyword("on") {
+                self.advance();
+                Some(self.name()?)
+            } else {
+                None
+            };
+
+            return Ok(Selection::InlineFragment {
+                type_condition,
+                directives: self.directives()?,
+                selection_set: self.selection_set()?,
+                pos,
+            });
+        }
+
+        let mut name = self.name()?;
+        let mut alias = None;
+        if self.skip_punct(':') {
+            alias = Some(name);
+            name = self.name()?;
+        }
+
+        let arguments = self.arguments(false)?;
+        let directives = self.directives()?;
+        let selection_set = if self.is_punct('{') {
+            self.selection_set()?
+        } else {
+            Vec::new()
+        };
+
+        Ok(Selection::Field(Field {
+            alias,
+            name,
+            arguments,
+            directives,
+            selection_set,
+            pos,
+        }))
+    }
+
+    fn arguments(&mut self, constant: bool) -> Result<Vec<Argument>, ParseError> {
+        let mut arguments = Vec::new();
+
+        if self.skip_punct('(') {
+            while !self.skip_punct(')') {
+                let pos = self.pos();
+                let name = self.name()?;
+                self.expect_punct(':')?;
+                arguments.push(Argument {
+                    name,
+                    value: self.value(constant)?,
+                    pos,
+                });
+            }
+        }
+
+        Ok(arguments)
+    }
+
+    fn directives(&mut self) -> Result<Vec<Directive>, ParseError> {
+        let mut directives = Vec::new();
+
+        while self.is_punct('@') {
+            let pos = self.pos();
+            self.advance();
+            directives.push(Directive {
+                name: self.name()?,
+                arguments: self.arguments(false)?,
+                pos,
+            });
+        }
+
+        Ok(directives)
+    }
+
+    fn value(&mut self, constant: bool) -> Result<Value, ParseError> {
+        let pos = self.pos();
+
+        match self.peek().clone() {
+            Token::Punct('$') if !constant => {
+                self.advance();
+                Ok(Value::Variable(self.name()?, pos))
+            }
+            Token::Punct('[') => {
+                self.advance();
+                let mut values = Vec::new();
+                while !self.skip_punct(']') {
+                    values.push(self.value(constant)?);
+                }
+                Ok(Value::List(values))
+            }
+            Token::Punct('{') => {
+                self.advance();
+                let mut fields = Vec::new();
+                while !self.skip_punct('}') {
+                    let name = self.name()?;
+                    self.expect_punct(':')?;
+                    fields.push((name, self.value(constant)?));
+                }
+                Ok(Value::Object(fields))
+            }
+            Token::Int(value) => {
+                self.advance();
+                Ok(Value::Int(value))
+            }
+            Token::Float(value) => {
+                self.advance();
+                Ok(Value::Float(value))
+            }
+            Token::String(value) => {
+                self.advance();
+                Ok(Value::String(value))
+            }
+            Token::Name(name) => {
+                self.advance();
+                Ok(match name.as_str() {
+                    "true" => Value::Boolean(true),
+                    "false" => Value::Boolean(false),
+                    "null" => Value::Null,
+                    _ => Value::Enum(name),
+                })
+            }
+            _ => self.unexpected("a value"),
+        }
+    }
+
+    fn type_ref(&mut self) -> Result<Type, ParseError> {
+        let ty = if self.skip_punct('[') {
+            let inner = self.type_ref()?;
+            self.expect_punct(']')?;
+            Type::List(Box::new(inner))
+        } else {
+            Type::Named(self.name()?)
+        };
+
+        if self.skip_punct('!') {
+            Ok(Type::NonNull(Box::new(ty)))
+        } else {
+            Ok(ty)
+        }
+    }
+
+    fn type_system_definition(&mut self, extension: bool) -> Result<Definition, ParseError> {
+        let pos = self.pos();
+        let keyword = self.name()?;
+
+        if keyword == "schema" {
+            self.directives()?;
+            let mut roots = Vec::new();
+            if self.skip_punct('{') {
+                while !self.skip_punct('}') {
+                    let kind = match self.name()?.as_str() {
+                        "query" => OperationKind::Query,
+                        "mutation" => OperationKind::Mutation,
+                        "subscription" => OperationKind::Subscription,
+                        _ => return self.unexpected("an operation type"),
+                    };
+                    self.expect_punct(':')?;
+                    roots.push((kind, self.name()?));
+                }
+            }
+            return Ok(Definition::Schema(roots));
+        }
+
+        if keyword == "directive" {
+            self.expect_punct('@')?;
+            self.name()?;
+            self.input_values('(', ')')?;
+            if self.is_keyword("repeatable") {
+                self.advance();
+            }
+            self.expect_keyword("on")?;
+            self.skip_punct('|');
+            self.name()?;
+            while self.skip_punct('|') {
+                self.name()?;
+            }
+            return Ok(Definition::Directive);
+        }
+
+        let kind = match keyword.as_str() {
+            "scalar" => TypeKind::Scalar,
+            "type" => TypeKind::Object,
+            "interface" => TypeKind::Interface,
+            "union" => TypeKind::Union,
+            "enum" => TypeKind::Enum,
+            "input" => TypeKind::InputObject,
+            _ => {
+                return Err(ParseError::new(
+                    pos,
+                    format!("Unexpected Name \"{}\"", keyword),
+                ))
+            }
+        };
+
+        let mut definition = TypeDefinition {
+            kind,
+            name: self.name()?,
+            interfaces: Vec::new(),
+            fields: Vec::new(),
+            members: Vec::new(),
+            input_fields: Vec::new(),
+            extension,
+            pos,
+        };
+
+        if matches!(kind, TypeKind::Object | TypeKind::Interface) && self.is_keyword("implements") {
+            self.advance();
+            self.skip_punct('&');
+            definition.interfaces.push(self.name()?);
+            while self.skip_punct('&') {
+                definition.interfaces.push(self.name()?);
+            }
+        }
+
+        self.directives()?;
+
+        match kind {
+            TypeKind::Object | TypeKind::Interface => {
+                if self.skip_punct('{') {
+                    while !self.skip_punct('}') {
+                        self.skip_description();
+                        let name = self.name()?;
+                        let arguments = self.input_values('(', ')')?;
+                        self.expect_punct(':')?;
+                        let ty = self.type_ref()?;
+                        self.directives()?;
+                        definition.fields.push(FieldDefinition {
+                            name,
+                            arguments,
+                            ty,
+                        });
+                    }
+                }
+            }
+            TypeKind::Union => {
+                if self.skip_punct('=') {
+                    self.skip_punct('|');
+                    definition.members.push(self.name()?);
+                    while self.skip_punct('|') {
+                        definition.members.push(self.name()?);
+                    }
+                }
+            }
+            TypeKind::Enum => {
+                if self.skip_punct('{') {
+                    while !self.skip_punct('}') {
+                        self.skip_description();
+                        self.name()?;
+                        self.directives()?;
+                    }
+                }
+            }
+            TypeKind::InputObject => {
+                definition.input_fields = self.input_values('{', '}')?;
+            }
+            TypeKind::Scalar => {}
+        }
+
+        Ok(Definition::Type(definition))
+    }
+
+    fn input_values(
+        &mut self,
+        open: char,
+        close: char,
+    ) -> Result<Vec<InputValueDefinition>, ParseError> {
+        let mut values = Vec::new();
+
+        if self.skip_punct(open) {
+            while !self.skip_punct(close) {
+                self.skip_description();
+                let name = self.name()?;
+                self.expect_punct(':')?;
+                let ty = self.type_ref()?;
+                let has_default = if self.skip_punct('=') {
+                    self.value(true)?;
+                    true
+                } else {
+                    false
+                };
+                self.directives()?;
+                values.push(InputValueDefinition {
+                    name,
+                    ty,
+                    has_default,
+                });
+            }
+        }
+
+        Ok(values)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_operation() {
+        let document = parse(
+            r#"
+            query GetUser($id: ID!, $first: Int = 10) @cached {
+              user(id: $id) {
+                id
+                displayName: name
+                ...UserFields
+                ... on Admin { level }
+              }
+            }
+            fragment UserFields on User { email }
+            "#,
+        )
+        .unwrap();
+
+        let operation = document.operations().next().unwrap();
+        assert_eq!(operation.name.as_deref(), Some("GetUser"));
+        assert_eq!(operation.variables.len(), 2);
+        assert_eq!(
+            operation.variables[0].ty,
+            Type::NonNull(Box::new(Type::Named("ID".into())))
+        );
+
+        let Selection::Field(user) = &operation.selection_set[0] else {
+            panic!("expected field");
+        };
+        assert_eq!(user.selection_set.len(), 4);
+        assert_eq!(document.fragments().count(), 1);
+    }
+
+    #[test]
+    fn test_parse_schema() {
+        let document = parse(
+            r#"
+            """The root query"""
+            type Query {
+              "Look up a user"
+              user(id: ID!, active: Boolean = true): User
+            }
+            interface Node { id: ID! }
+            type User implements Node & Entity @key(fields: "id") { id: ID! }
+            union SearchResult = | User | Post
+            enum Role { ADMIN USER }
+            input Filter { role: Role = USER }
+            scalar Date
+            directive @key(fields: String!) repeatable on OBJECT | INTERFACE
+            extend type Query { me: User }
+            schema { query: Query }
+            "#,
+        )
+        .unwrap();
+
+        assert!(document.is_schema());
+        assert_eq!(document.definitions.len(), 10);
+
+        let Definition::Type(query) = &document.definitions[0] else {
+            panic!("expected type");
+        };
+        assert_eq!(query.fields[0].arguments.len(), 2);
+        assert!(query.fields[0].arguments[1].has_default);
+    }
+
+    #[test]
+    fn test_parse_error_position() {
+        let error = parse("query {\n  user(id: ) }").unwrap_err();
+        assert_eq!(
+            error.pos,
+            Pos {
+                line: 2,
+                column: 12
+            }
+        );
+    }
+}
@@ -0,0 +1,156 @@
+use super::*;
+use std::collections::{BTreeSet, HashMap};
+
+/// Scalars every schema provides without declaring them.
+const BUILT_IN_SCALARS: &[&str] = &["Int", "Float", "String", "Boolean", "ID"];
+
+/// A GraphQL schema assembled from one or more SDL documents.
+#[derive(Debug, Clone, Default)]
+pub struct Schema {
+    types: HashMap<String, TypeDefinition>,
+    roots: HashMap<String, String>,
+}
+
+impl Schema {
+    /// Builds a schema from the type system definitions of the given documents,
+    /// merging `extend` definitions into the types they extend.
+    pub fn from_documents<'a>(documents: impl IntoIterator<Item = &'a Document>) -> Self {
+        let mut schema = Schema::default();
+
+        for name in BUILT_IN_SCALARS {
+            schema.types.insert(
+                name.to_string(),
+                TypeDefinition {
+                    kind: TypeKind::Scalar,
+                    name: name.to_string(),
+                    interfaces: Vec::new(),
+                    fields: Vec::new(),
+                    members: Vec::new(),
+                    input_fields: Vec::new(),
+                    extension: false,
+                    pos: Pos::default(),
+                },
+            );
+        }
+
+        for document in documents {
+            for definition in &document.definitions {
+                match definition {
+                    Definition::Type(ty) => match schema.types.get_mut(&ty.name) {
+                        Some(existing) => {
+                            existing.interfaces.extend(ty.interfaces.iter().cloned());
+                            existing.fields.extend(ty.fields.iter().cloned());
+                            existing.members.extend(ty.members.iter().cloned());
+                            existing
+                                .input_fields
+                                .extend(ty.input_fields.iter().cloned());
+                        }
+                        None => {
+                            schema.types.insert(ty.name.clone(), ty.clone());
+                        }
+                    },
+                    Definition::Schema(roots) => {
+                        for (kind, name) in roots {
+                            schema.roots.insert(kind.to_string(), name.clone());
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        schema
+    }
+
+    /// Returns the type declared with the given name.
+    pub fn get_type(&self, name: &str) -> Option<&TypeDefinition> {
+        self.types.get(name)
+    }
+
+    /// Returns the root type for an operation kind, e.g. `Query` for queries.
+    pub fn root_type(&self, kind: OperationKind) -> Option<&TypeDefinition> {
+        match self.roots.get(&kind.to_string()) {
+            Some(name) => self.get_type(name),
+            None => self.get_type(match kind {
+                OperationKind::Query => "Query",
+                OperationKind::Mutation => "Mutation",
+                OperationKind::Subscription => "Subscription",
+            }),
+        }
+    }
+
+    /// Returns the field named `field` on an object or interface type.
+    pub fn field(&self, type_name: &str, field: &str) -> Option<&FieldDefinition> {
+        self.get_type(type_name)?
+            .fields
+            .iter()
+            .find(|f| f.name == field)
+    }
+
+    /// Whether the type has fields that must be selected (object, interface or union).
+    pub fn is_composite(&self, type_name: &str) -> bool {
+        matches!(
+            self.get_type(type_name).map(|t| t.kind),
+            Some(TypeKind::Object | TypeKind::Interface | TypeKind::Union)
+        )
+    }
+
+    /// Returns the names of the object types a value of `type_name` can be.
+    pub fn possible_types(&self, type_name: &str) -> BTreeSet<&str> {
+        let Some(ty) = self.get_type(type_name) else {
+            return BTreeSet::new();
+        };
+
+        match ty.kind {
+            TypeKind::Object => BTreeSet::from([ty.name.as_str()]),
+            TypeKind::Union => ty.members.iter().map(String::as_str).collect(),
+            TypeKind::Interface => self
+                .types
+                .values()
+                .filter(|t| t.kind == TypeKind::Object && t.interfaces.contains(&ty.name))
+                .map(|t| t.name.as_str())
+                .collect(),
+            _ => BTreeSet::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_possible_types() {
+        let document = parse(
+            r#"
+            interface Node { id: ID! }
+            type User implements Node { id: ID! }
+            type Post implements Node { id: ID! }
+            type Comment { id: ID! }
+            union Feed = Post | Comment
+            "#,
+        )
+        .unwrap();
+        let schema = Schema::from_documents([&document]);
+
+        assert_eq!(
+            schema.possible_types("Node"),
+            BTreeSet::from(["Post", "User"])
+        );
+        assert_eq!(
+            schema.possible_types("Feed"),
+            BTreeSet::from(["Comment", "Post"])
+        );
+        assert!(schema.is_composite("Feed"));
+        assert!(!schema.is_composite("ID"));
+    }
+
+    #[test]
+    fn test_extensions_are_merged() {
+        let document = parse("type Query { a: Int } extend type Query { b: Int }").unwrap();
+        let schema = Schema::from_documents([&document]);
+
+        assert!(schema.field("Query", "a").is_some());
+        assert!(schema.field("Query", "b").is_some());
+    }
+}
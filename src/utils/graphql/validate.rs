@@ -0,0 +1,483 @@
+use super::*;
+use std::collections::{HashMap, HashSet};
+
+/// A validation error, located in the document at index `document`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    pub document: usize,
+    pub pos: Pos,
+    /// Short identifier of the violated rule, e.g. `unknown-field`.
+    pub rule: &'static str,
+    pub message: String,
+}
+
+/// Validates executable documents against a schema.
+///
+/// Fragments are shared across all documents, so fragments defined in one
+/// document may be spread in another. Reports unknown fields and types,
+/// missing required arguments, fragment type mismatches, and unused or
+/// undefined fragments and variables.
+pub fn validate(schema: &Schema, documents: &[Document]) -> Vec<Diagnostic> {
+    let mut validator = Validator {
+        schema,
+        fragments: HashMap::new(),
+        diagnostics: Vec::new(),
+        document: 0,
+    };
+
+    for (index, document) in documents.iter().enumerate() {
+        for fragment in document.fragments() {
+            if validator.fragments.contains_key(fragment.name.as_str()) {
+                validator.diagnostics.push(Diagnostic {
+                    document: index,
+                    pos: fragment.pos,
+                    rule: "unique-fragment",
+                    message: format!("There can be only one fragment named \"{}\"", fragment.name),
+                });
+            } else {
+                validator
+                    .fragments
+                    .insert(fragment.name.as_str(), (index, fragment));
+            }
+        }
+    }
+
+    let mut used_fragments = HashSet::new();
+
+    for (index, document) in documents.iter().enumerate() {
+        validator.document = index;
+
+        for operation in document.operations() {
+            validator.operation(operation, &mut used_fragments);
+        }
+
+        for fragment in document.fragments() {
+            validator.fragment(fragment);
+        }
+    }
+
+    for (index, document) in documents.iter().enumerate() {
+        for fragment in document.fragments() {
+            if !used_fragments.contains(fragment.name.as_str()) {
+                validator.diagnostics.push(Diagnostic {
+                    document: index,
+                    pos: fragment.pos,
+                    rule: "unused-fragment",
+                    message: format!("Fragment \"{}\" is never used", fragment.name),
+                });
+            }
+        }
+    }
+
+    let mut diagnostics = validator.diagnostics;
+    diagnostics.sort_by_key(|d| (d.document, d.pos));
+    diagnostics.dedup();
+    diagnostics
+}
+
+struct Validator<'a> {
+    schema: &'a Schema,
+    fragments: HashMap<&'a str, (usize, &'a Fragment)>,
+    diagnostics: Vec<Diagnostic>,
+    document: usize,
+}
+
+impl<'a> Validator<'a> {
+    fn report(&mut self, rule: &'static str, pos: Pos, message: String) {
+        self.diagnostics.push(Diagnostic {
+            document: self.document,
+            pos,
+            rule,
+            message,
+        });
+    }
+
+    fn operation(&mut self, operation: &'a Operation, used_fragments: &mut HashSet<&'a str>) {
+        let name = operation.name.as_deref().unwrap_or("<anonymous>");
+
+        for variable in &operation.variables {
+            if self.schema.get_type(variable.ty.name()).is_none() {
+                self.report(
+                    "unknown-type",
+                    variable.pos,
+                    format!("Unknown type \"{}\"", variable.ty.name()),
+                );
+            }
+        }
+
+        match self.schema.root_type(operation.kind) {
+            Some(root) => self.selection_set(&root.name, &operation.selection_set),
+            None => self.report(
+                "root-type",
+                operation.pos,
+                format!("Schema does not define a {} root type", operation.kind),
+            ),
+        }
+
+        let mut usages = Vec::new();
+        let mut visited = HashSet::new();
+        let document = self.document;
+        for directive in &operation.directives {
+            directive_variables(directive, document, &mut usages);
+        }
+        self.variable_usages(
+            &operation.selection_set,
+            document,
+            &mut usages,
+            &mut visited,
+        );
+        used_fragments.extend(visited);
+
+        for variable in &operation.variables {
+            if !usages.iter().any(|(used, _, _)| *used == variable.name) {
+                self.report(
+                    "unused-variable",
+                    variable.pos,
+                    format!(
+                        "Variable \"${}\" is never used in operation \"{}\"",
+                        variable.name, name
+                    ),
+                );
+            }
+        }
+
+        for (used, pos, document) in usages {
+            if !operation.variables.iter().any(|v| v.name == used) {
+                self.diagnostics.push(Diagnostic {
+                    document,
+                    pos,
+                    rule: "undefined-variable",
+                    message: format!(
+                        "Variable \"${}\" is not defined by operation \"{}\"",
+                        used, name
+                    ),
+                });
+            }
+        }
+    }
+
+    fn fragment(&mut self, fragment: &'a Fragment) {
+        let condition = fragment.type_condition.as_str();
+
+        if self.schema.get_type(condition).is_none() {
+            self.report(
+                "unknown-type",
+                fragment.pos,
+                format!("Unknown type \"{}\"", condition),
+            );
+        } else if !self.schema.is_composite(condition) {
+            self.report(
+                "fragment-on-composite",
+                fragment.pos,
+                format!(
+                    "Fragment \"{}\" cannot condition on non composite type \"{}\"",
+                    fragment.name, condition
+                ),
+            );
+        } else {
+            self.selection_set(condition, &fragment.selection_set);
+        }
+    }
+
+    fn selection_set(&mut self, parent: &str, selections: &'a [Selection]) {
+        for selection in selections {
+            match selection {
+                Selection::Field(field) => self.field(parent, field),
+                Selection::FragmentSpread { name, pos, .. } => {
+                    match self.fragments.get(name.as_str()) {
+                        Some((_, fragment)) => {
+                            let condition = fragment.type_condition.as_str();
+                            self.spread(parent, condition, Some(name), *pos);
+                        }
+                        None => self.report(
+                            "unknown-fragment",
+                            *pos,
+                            format!("Unknown fragment \"{}\"", name),
+                        ),
+                    }
+                }
+                Selection::InlineFragment {
+                    type_condition,
+                    selection_set,
+                    pos,
+                    ..
+                } => {
+                    let ty = type_condition.as_deref().unwrap_or(parent);
+
+                    if self.schema.get_type(ty).is_none() {
+                        self.report("unknown-type", *pos, format!("Unknown type \"{}\"", ty));
+                        continue;
+                    }
+
+                    self.spread(parent, ty, None, *pos);
+                    self.selection_set(ty, selection_set);
+                }
+            }
+        }
+    }
+
+    fn field(&mut self, parent: &str, field: &'a Field) {
+        if field.name.starts_with("__") {
+            return;
+        }
+
+        let Some(definition) = self.schema.field(parent, &field.name) else {
+            let mut message = format!(
+                "Cannot query field \"{}\" on type \"{}\"",
+                field.name, parent
+            );
+            if let Some(TypeKind::Union) = self.schema.get_type(parent).map(|t| t.kind) {
+                message.push_str(". Did you mean to use an inline fragment?");
+            }
+            self.report("unknown-field", field.pos, message);
+            return;
+        };
+
+        for argument in &field.arguments {
+            if !definition.arguments.iter().any(|a| a.name == argument.name) {
+                self.report(
+                    "unknown-argument",
+                    argument.pos,
+                    format!(
+                        "Unknown argument \"{}\" on field \"{}.{}\"",
+                        argument.name, parent, field.name
+                    ),
+                );
+            }
+        }
+
+        for argument in &definition.arguments {
+            let provided = field
+                .arguments
+                .iter()
+                .any(|a| a.name == argument.name && a.value != Value::Null);
+
+            if argument.ty.is_non_null() && !argument.has_default && !provided {
+                self.report(
+                    "required-argument",
+                    field.pos,
+                    format!(
+                        "Field \"{}\" argument \"{}\" of type \"{}\" is required, but it was not provided",
+                        field.name, argument.name, argument.ty
+                    ),
+                );
+            }
+        }
+
+        let ty = definition.ty.name();
+        if self.schema.is_composite(ty) {
+            if field.selection_set.is_empty() {
+                self.report(
+                    "selection-required",
+                    field.pos,
+                    format!(
+                        "Field \"{}\" of type \"{}\" must have a selection of subfields",
+                        field.name, definition.ty
+                    ),
+                );
+            } else {
+                self.selection_set(ty, &field.selection_set);
+            }
+        } else if !field.selection_set.is_empty() && self.schema.get_type(ty).is_some() {
+            self.report(
+                "selection-forbidden",
+                field.pos,
+                format!(
+                    "Field \"{}\" must not have a selection since type \"{}\" has no subfields",
+                    field.name, definition.ty
+                ),
+            );
+        }
+    }
+
+    /// Reports a spread whose type condition can never apply to the parent type.
+    fn spread(&mut self, parent: &str, condition: &str, name: Option<&str>, pos: Pos) {
+        if self.schema.get_type(parent).is_none() || self.schema.get_type(condition).is_none() {
+            return;
+        }
+
+        let parent_types = self.schema.possible_types(parent);
+        let condition_types = self.schema.possible_types(condition);
+
+        if parent_types.is_disjoint(&condition_types) {
+            let fragment = match name {
+                Some(name) => format!("Fragment \"{}\"", name),
+                None => "Fragment".to_string(),
+            };
+            self.report(
+                "fragment-spread",
+                pos,
+                format!(
+                    "{} cannot be spread here as objects of type \"{}\" can never be of type \"{}\"",
+                    fragment, parent, condition
+                ),
+            );
+        }
+    }
+
+    /// Collects variable usages of a selection set, following fragment spreads.
+    fn variable_usages(
+        &self,
+        selections: &'a [Selection],
+        document: usize,
+        usages: &mut Vec<(&'a str, Pos, usize)>,
+        visited: &mut HashSet<&'a str>,
+    ) {
+        for selection in selections {
+            match selection {
+                Selection::Field(field) => {
+                    for argument in &field.arguments {
+                        value_variables(&argument.value, document, usages);
+                    }
+                    for directive in &field.directives {
+                        directive_variables(directive, document, usages);
+                    }
+                    self.variable_usages(&field.selection_set, document, usages, visited);
+                }
+                Selection::FragmentSpread {
+                    name, directives, ..
+                } => {
+                    for directive in directives {
+                        directive_variables(directive, document, usages);
+                    }
+                    if let Some((index, fragment)) = self.fragments.get(name.as_str()) {
+                        if visited.insert(fragment.name.as_str()) {
+                            for directive in &fragment.directives {
+                                directive_variables(directive, *index, usages);
+                            }
+                            self.variable_usages(&fragment.selection_set, *index, usages, visited);
+                        }
+                    }
+                }
+                Selection::InlineFragment {
+                    directives,
+                    selection_set,
+                    ..
+                } => {
+                    for directive in directives {
+                        directive_variables(directive, document, usages);
+                    }
+                    self.variable_usages(selection_set, document, usages, visited);
+                }
+            }
+        }
+    }
+}
+
+fn value_variables<'a>(value: &'a Value, document: usize, usages: &mut Vec<(&'a str, Pos, usize)>) {
+    let mut variables = Vec::new();
+    value.variables(&mut variables);
+    usages.extend(
+        variables
+            .into_iter()
+            .map(|(name, pos)| (name, pos, document)),
+    );
+}
+
+fn directive_variables<'a>(
+    directive: &'a Directive,
+    document: usize,
+    usages: &mut Vec<(&'a str, Pos, usize)>,
+) {
+    for argument in &directive.arguments {
+        value_variables(&argument.value, document, usages);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SCHEMA: &str = r#"
+        type Query {
+          user(id: ID!): User
+          search(term: String!, limit: Int = 10): [SearchResult!]!
+          node(id: ID!): Node
+        }
+        interface Node { id: ID! }
+        type User implements Node { id: ID! name: String friends: [User!]! }
+        type Post implements Node { id: ID! title: String }
+        type Comment { body: String }
+        union SearchResult = User | Post
+    "#;
+
+    fn messages(operations: &[&str]) -> Vec<String> {
+        let schema = Schema::from_documents([&parse(SCHEMA).unwrap()]);
+        let documents: Vec<Document> = operations.iter().map(|o| parse(o).unwrap()).collect();
+        validate(&schema, &documents)
+            .into_iter()
+            .map(|d| d.message)
+            .collect()
+    }
+
+    #[test]
+    fn test_valid_operation() {
+        let errors = messages(&[r#"
+            query Q($id: ID!, $term: String!) {
+              user(id: $id) { ...UserFields friends { id } }
+              search(term: $term) { __typename ... on Post { title } }
+              node(id: $id) { id ... on User { name } }
+            }
+            fragment UserFields on User { id name }
+        "#]);
+        assert!(errors.is_empty(), "{:?}", errors);
+    }
+
+    #[test]
+    fn test_unknown_field() {
+        assert_eq!(
+            messages(&["{ user(id: 1) { id email } }"]),
+            vec!["Cannot query field \"email\" on type \"User\""]
+        );
+    }
+
+    #[test]
+    fn test_missing_required_argument() {
+        assert_eq!(
+            messages(&["{ user { id } }"]),
+            vec!["Field \"user\" argument \"id\" of type \"ID!\" is required, but it was not provided"]
+        );
+    }
+
+    #[test]
+    fn test_fragment_type_mismatch() {
+        assert_eq!(
+            messages(&["{ user(id: 1) { ...C } } fragment C on Comment { body }"]),
+            vec!["Fragment \"C\" cannot be spread here as objects of type \"User\" can never be of type \"Comment\""]
+        );
+    }
+
+    #[test]
+    fn test_unused_fragment_and_variable() {
+        assert_eq!(
+            messages(&[
+                "query Q($id: ID!, $unused: Int) { user(id: $id) { id } }",
+                "fragment F on User { id }",
+            ]),
+            vec![
+                "Variable \"$unused\" is never used in operation \"Q\"",
+                "Fragment \"F\" is never used",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_variable_used_through_fragment_in_other_document() {
+        let errors = messages(&[
+            "query Q($id: ID!) { ...Root }",
+            "fragment Root on Query { user(id: $id) { id } }",
+        ]);
+        assert!(errors.is_empty(), "{:?}", errors);
+    }
+
+    #[test]
+    fn test_selection_shape() {
+        assert_eq!(
+            messages(&["{ user(id: 1) { name { x } friends } }"]),
+            vec![
+                "Field \"name\" must not have a selection since type \"String\" has no subfields",
+                "Field \"friends\" of type \"[User!]!\" must have a selection of subfields",
+            ]
+        );
+    }
+}
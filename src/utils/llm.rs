@@ -0,0 +1,161 @@
+use crate::utils::config::LlmConfig;
+use crate::utils::diff::budget_diff;
+use crate::utils::secrets::redact_diff;
+use anyhow::{Context, Result};
+use std::io::Write;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+/// Generator used when `llm.command` is not configured.
+pub const DEFAULT_GENERATOR: &str = "claude -p";
+
+/// Runs a generator: a shell command that reads a prompt on stdin and prints
+/// the generated text. Runs from `dir` and returns the trimmed output.
+pub fn generate(generator: &str, prompt: &str, dir: &Path) -> Result<String> {
+    let mut child = Command::new("sh")
+        .arg("-c")
+        .arg(generator)
+        .current_dir(dir)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .with_context(|| format!("Failed to run generator \"{}\"", generator))?;
+
+    // Write from another thread, so that a generator printing before it has
+    // read the whole prompt cannot block on a full pipe.
+    let mut stdin = child.stdin.take().expect("stdin is piped");
+    let prompt = prompt.to_string();
+    let writer = std::thread::spawn(move || stdin.write_all(prompt.as_bytes()));
+
+    let output = child
+        .wait_with_output()
+        .with_context(|| format!("Failed to run generator \"{}\"", generator))?;
+    // A generator may exit without reading its input; that is not an error.
+    let _ = writer.join();
+
+    if !output.status.success() {
+        anyhow::bail!(
+            "Generator \"{}\" failed ({}): {}",
+            generator,
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+
+    let text = clean_output(&String::from_utf8_lossy(&output.stdout));
+    if text.is_empty() {
+        anyhow::bail!("Generator \"{}\" printed nothing", generator);
+    }
+
+    Ok(text)
+}
+
+/// Prepares a diff for a prompt: redacts the secrets in it, then fits it in
+/// the configured budget. With `show_redactions`, each redacted secret is
+/// listed on stderr; with `verbose`, their number.
+pub fn prepare_diff(
+    diff: &str,
+    config: &LlmConfig,
+    show_redactions: bool,
+    verbose: bool,
+) -> String {
+    let (diff, redactions) = redact_diff(diff);
+
+    if show_redactions {
+        if redactions.is_empty() {
+            eprintln!("No secrets redacted");
+        } else {
+            eprintln!("Redacted {} secret(s):", redactions.len());
+            for redaction in &redactions {
+                eprintln!("  {}", redaction);
+            }
+        }
+    } else if verbose && !redactions.is_empty() {
+        eprintln!(
+            "Redacted {} secret(s) from the diff (list them with --show-redactions)",
+            redactions.len()
+        );
+    }
+
+    budget_diff(&diff, config.diff_budget())
+}
+
+/// Trims generated text and unwraps it from a code fence, which models
+/// sometimes add despite being asked not to.
+pub fn clean_output(text: &str) -> String {
+    let text = text.trim();
+    let lines: Vec<&str> = text.lines().collect();
+
+    match lines.as_slice() {
+        [first, inner @ .., last] if first.starts_with("```") && last.trim() == "```" => {
+            inner.join("\n").trim().to_string()
+        }
+        _ => text.to_string(),
+    }
+}
+
+/// Fills a prompt template: each `{{name}}` is replaced with its value.
+/// Unknown placeholders are left as they are.
+pub fn render_template(template: &str, values: &[(&str, &str)]) -> String {
+    values
+        .iter()
+        .fold(template.to_string(), |text, (name, value)| {
+            text.replace(&format!("{{{{{}}}}}", name), value)
+        })
+}
+
+/// Loads a prompt template from `path`, relative to `root`, or returns
+/// `default` without one.
+pub fn load_template(root: &Path, path: Option<&str>, default: &str) -> Result<String> {
+    match path {
+        Some(path) => {
+            let path = root.join(path);
+            std::fs::read_to_string(&path)
+                .with_context(|| format!("Failed to read prompt template {}", path.display()))
+        }
+        None => Ok(default.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_clean_output() {
+        assert_eq!(clean_output("\n  feat: add a\n\n"), "feat: add a");
+        assert_eq!(
+            clean_output("```text\nfeat: add a\n\nBody\n```\n"),
+            "feat: add a\n\nBody"
+        );
+        assert_eq!(clean_output("```"), "```");
+    }
+
+    #[test]
+    fn test_render_template() {
+        assert_eq!(
+            render_template(
+                "Diff of {{branch}}:\n{{diff}}\n{{unknown}}",
+                &[("branch", "main"), ("diff", "+a")]
+            ),
+            "Diff of main:\n+a\n{{unknown}}"
+        );
+    }
+
+    #[test]
+    fn test_generate() {
+        let dir = tempfile::tempdir().unwrap();
+
+        assert_eq!(
+            generate("tr a-z A-Z", "feat: add a\n", dir.path()).unwrap(),
+            "FEAT: ADD A"
+        );
+
+        let error = generate("echo oops >&2; exit 3", "", dir.path()).unwrap_err();
+        assert!(error.to_string().contains("oops"), "{}", error);
+
+        let error = generate("cat >/dev/null", "prompt", dir.path()).unwrap_err();
+        assert!(error.to_string().contains("printed nothing"), "{}", error);
+    }
+}
@@ -1,4 +1,11 @@
+pub mod codeowners;
+pub mod config;
 pub mod dart;
+pub mod diff;
 pub mod files;
 pub mod git;
+pub mod glob;
+pub mod graphql;
+pub mod llm;
+pub mod secrets;
 pub mod shell;
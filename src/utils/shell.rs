@@ -1,6 +1,11 @@
-use anyhow::{bail, Result};
+use anyhow::{bail, Context, Result};
 use regex::Regex;
-use std::process::Command;
+use std::cell::RefCell;
+use std::io::Read;
+use std::process::{Child, Command, Output, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 /// Regex pattern for validating safe shell inputs.
 /// Allows: alphanumeric, dots, underscores, slashes, spaces, and dashes.
@@ -10,7 +15,6 @@ const SAFE_SHELL_INPUT_PATTERN: &str = r"^[a-zA-Z0-9._/\s-]+$";
 
 /// Escapes a shell argument to prevent injection attacks.
 /// Uses single-quote escaping which is safe for most shells.
-#[allow(dead_code)]
 pub fn escape_shell_arg(arg: &str) -> String {
     format!("'{}'", arg.replace('\'', r"'\''"))
 }
@@ -45,10 +49,259 @@ pub fn is_command_installed(command: &str) -> bool {
         .unwrap_or(false)
 }
 
+/// Why `run_with_timeout` stopped a command before it finished.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Interrupted {
+    /// The command ran past its timeout, or the thread's deadline.
+    TimedOut(Duration),
+    /// The thread's cancel flag was set.
+    Cancelled,
+}
+
+impl std::fmt::Display for Interrupted {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Interrupted::TimedOut(timeout) => {
+                write!(f, "Timed out after {}s", timeout.as_secs_f64())
+            }
+            Interrupted::Cancelled => write!(f, "Cancelled after another check failed"),
+        }
+    }
+}
+
+impl std::error::Error for Interrupted {}
+
+/// Limits on every command run with `run_with_timeout` on a thread, such as
+/// the timeout of the hook check running them.
+#[derive(Debug, Clone, Default)]
+pub struct CommandLimits {
+    /// When commands must be stopped, and the timeout it came from.
+    pub deadline: Option<(Instant, Duration)>,
+    /// Stops commands once set.
+    pub cancelled: Option<Arc<AtomicBool>>,
+}
+
+thread_local! {
+    static LIMITS: RefCell<CommandLimits> = RefCell::default();
+}
+
+/// Runs `f` with `limits` applied to the commands it runs on this thread.
+pub fn with_limits<R>(limits: CommandLimits, f: impl FnOnce() -> R) -> R {
+    let previous = LIMITS.with(|l| l.replace(limits));
+    let result = f();
+    LIMITS.with(|l| *l.borrow_mut() = previous);
+    result
+}
+
+/// Runs a command to completion, capturing its output.
+///
+/// The command is stopped once `timeout` elapses, or when the limits of the
+/// current thread say so (see `with_limits`). It is killed together with
+/// every process it started, and an `Interrupted` error is returned.
+pub fn run_with_timeout(command: &mut Command, timeout: Option<Duration>) -> Result<Output> {
+    let limits = LIMITS.with(|l| l.borrow().clone());
+    let start = Instant::now();
+    let deadline = timeout
+        .map(|t| (start + t, t))
+        .into_iter()
+        .chain(limits.deadline)
+        .min_by_key(|(at, _)| *at);
+
+    let interrupted = || {
+        if limits
+            .cancelled
+            .as_ref()
+            .is_some_and(|c| c.load(Ordering::SeqCst))
+        {
+            Some(Interrupted::Cancelled)
+        } else {
+            deadline
+                .filter(|(at, _)| Instant::now() >= *at)
+                .map(|(_, t)| Interrupted::TimedOut(t))
+        }
+    };
+
+    if let Some(reason) = interrupted() {
+        return Err(reason.into());
+    }
+
+    let mut child = command
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .with_context(|| format!("Failed to run {:?}", command.get_program()))?;
+
+    // Drain the pipes while waiting so the command never blocks on a full pipe.
+    let mut stdout = child.stdout.take().expect("stdout is piped");
+    let mut stderr = child.stderr.take().expect("stderr is piped");
+    let stdout = std::thread::spawn(move || {
+        let mut buf = Vec::new();
+        stdout.read_to_end(&mut buf).map(|_| buf)
+    });
+    let stderr = std::thread::spawn(move || {
+        let mut buf = Vec::new();
+        stderr.read_to_end(&mut buf).map(|_| buf)
+    });
+
+    let status = loop {
+        if let Some(status) = child.try_wait().context("Failed to wait for command")? {
+            break status;
+        }
+
+        if let Some(reason) = interrupted() {
+            kill_tree(&mut child);
+            return Err(reason.into());
+        }
+
+        std::thread::sleep(Duration::from_millis(10));
+    };
+
+    Ok(Output {
+        status,
+        stdout: stdout
+            .join()
+            .expect("reader does not panic")
+            .unwrap_or_default(),
+        stderr: stderr
+            .join()
+            .expect("reader does not panic")
+            .unwrap_or_default(),
+    })
+}
+
+/// Kills a child process and every process it started.
+fn kill_tree(child: &mut Child) {
+    // List the descendants first: once the child is gone they are reparented.
+    let descendants = descendants(child.id());
+    let _ = child.kill();
+    let _ = child.wait();
+
+    if !descendants.is_empty() {
+        let _ = Command::new("kill")
+            .arg("-KILL")
+            .args(descendants.iter().map(u32::to_string))
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status();
+    }
+}
+
+/// Lists the descendants of a process, using `ps`.
+#[cfg(unix)]
+fn descendants(pid: u32) -> Vec<u32> {
+    let Ok(output) = Command::new("ps")
+        .args(["-A", "-o", "pid=", "-o", "ppid="])
+        .output()
+    else {
+        return Vec::new();
+    };
+
+    let processes: Vec<(u32, u32)> = String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace().map(|f| f.parse().ok());
+            Some((fields.next()??, fields.next()??))
+        })
+        .collect();
+
+    let mut found = vec![pid];
+    let mut i = 0;
+    while i < found.len() {
+        let parent = found[i];
+        found.extend(
+            processes
+                .iter()
+                .filter(|(_, ppid)| *ppid == parent)
+                .map(|(pid, _)| *pid),
+        );
+        i += 1;
+    }
+
+    found.split_off(1)
+}
+
+#[cfg(not(unix))]
+fn descendants(_pid: u32) -> Vec<u32> {
+    Vec::new()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_run_with_timeout() {
+        let output = run_with_timeout(
+            Command::new("sh").args(["-c", "echo out; echo err >&2; exit 3"]),
+            Some(Duration::from_secs(10)),
+        )
+        .unwrap();
+        assert_eq!(output.status.code(), Some(3));
+        assert_eq!(output.stdout, b"out\n");
+        assert_eq!(output.stderr, b"err\n");
+
+        let start = Instant::now();
+        let error = run_with_timeout(
+            Command::new("sh").args(["-c", "sleep 5"]),
+            Some(Duration::from_millis(100)),
+        )
+        .unwrap_err();
+        assert!(error.to_string().starts_with("Timed out"));
+        assert!(start.elapsed() < Duration::from_secs(4));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_run_with_timeout_kills_the_process_tree() {
+        let dir = tempfile::tempdir().unwrap();
+        let pid_file = dir.path().join("pid");
+
+        let error = run_with_timeout(
+            Command::new("sh")
+                .arg("-c")
+                .arg("sleep 30 & echo $! > pid; wait")
+                .current_dir(dir.path()),
+            Some(Duration::from_millis(200)),
+        )
+        .unwrap_err();
+        assert_eq!(
+            error.downcast_ref::<Interrupted>(),
+            Some(&Interrupted::TimedOut(Duration::from_millis(200)))
+        );
+
+        // A killed process that nobody has reaped yet shows as a zombie.
+        let pid = std::fs::read_to_string(pid_file).unwrap();
+        let state = Command::new("ps")
+            .args(["-o", "stat=", "-p", pid.trim()])
+            .output()
+            .unwrap()
+            .stdout;
+        let alive = !state.is_empty() && !state.starts_with(b"Z");
+        assert!(!alive, "the background sleep survived");
+    }
+
+    #[test]
+    fn test_run_with_timeout_honors_thread_limits() {
+        let cancelled = Arc::new(AtomicBool::new(true));
+        let limits = CommandLimits {
+            deadline: None,
+            cancelled: Some(cancelled),
+        };
+
+        let error = with_limits(limits, || {
+            run_with_timeout(Command::new("sh").args(["-c", "sleep 5"]), None)
+        })
+        .unwrap_err();
+        assert_eq!(
+            error.downcast_ref::<Interrupted>(),
+            Some(&Interrupted::Cancelled)
+        );
+
+        // Limits only apply inside `with_limits`.
+        assert!(run_with_timeout(Command::new("true").arg("x"), None).is_ok());
+    }
+
     #[test]
     fn test_is_safe_shell_input() {
         assert!(is_safe_shell_input("myfile.txt"));